@@ -1,4 +1,6 @@
 use aes::{AESCipher, expand_key, encrypt_block, decrypt_block, AES_BLOCK_SIZE};
+use aes::stream::{AESCBCEncryptor, AESCBCDecryptor};
+use error::{Error, Result};
 use util::rand_bytes;
 use xor::fixed_xor;
 
@@ -8,20 +10,34 @@ pub struct AESCipherCBC {
 }
 
 impl AESCipherCBC {
-    pub fn new(key: &[u8], iv: &[u8]) -> AESCipherCBC {
-        AESCipherCBC {
-            key_schedule: expand_key(key),
-            iv: iv.to_vec(),
+    pub fn new(key: &[u8], iv: &[u8]) -> Result<AESCipherCBC> {
+        if iv.len() != AES_BLOCK_SIZE {
+            return Err(Error::BadIVLength(iv.len()));
         }
+        Ok(AESCipherCBC {
+            key_schedule: expand_key(key)?,
+            iv: iv.to_vec(),
+        })
     }
 
-    pub fn new_rand_iv(key: &[u8]) -> (AESCipherCBC, Vec<u8>) {
+    pub fn new_rand_iv(key: &[u8]) -> Result<(AESCipherCBC, Vec<u8>)> {
         let iv = rand_bytes(AES_BLOCK_SIZE);
-        (AESCipherCBC {
-            key_schedule: expand_key(key),
-            iv: iv.clone(),
-         },
-         iv)
+        Ok((AESCipherCBC {
+                key_schedule: expand_key(key)?,
+                iv: iv.clone(),
+            },
+            iv))
+    }
+
+    /// Streaming pad_and_encrypt with the cipher's iv
+    pub fn encryptor(&self) -> AESCBCEncryptor<'_> {
+        // new and set_iv already made sure the iv is a block long
+        AESCBCEncryptor::new(self, &self.iv).expect("bad cbc iv")
+    }
+
+    /// Streaming decrypt_and_unpad with the cipher's iv
+    pub fn decryptor(&self) -> AESCBCDecryptor<'_> {
+        AESCBCDecryptor::new(self, &self.iv).expect("bad cbc iv")
     }
 
     pub fn encrypt_iv(&self, init_iv: &[u8], plaintext: &[u8]) -> Vec<u8> {
@@ -63,8 +79,12 @@ impl AESCipherCBC {
 }
 
 impl AESCipher for AESCipherCBC {
-    fn set_iv(&mut self, iv: &[u8]) {
+    fn set_iv(&mut self, iv: &[u8]) -> Result<()> {
+        if iv.len() != AES_BLOCK_SIZE {
+            return Err(Error::BadIVLength(iv.len()));
+        }
         self.iv = iv.to_vec();
+        Ok(())
     }
 
     fn encrypt(&self, plaintext: &[u8]) -> Vec<u8> {
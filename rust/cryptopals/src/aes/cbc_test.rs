@@ -2,12 +2,12 @@ extern crate rand;
 
 use std::str;
 
-use aes::{AESCipher, AES_BLOCK_SIZE};
+use aes::AES_BLOCK_SIZE;
 use aes::cbc::AESCipherCBC;
-use pkcs7::{pkcs7_pad, pkcs7_maybe_unpad_copy};
+use error::Error;
 use ssv::{SSV_PREFIX, SSV_SUFFIX, ssv_aes_encrypt, ssv_aes_decrypt, has_admin,
           ssv_aes_decrypt_and_check};
-use util::{rand_key, rand_bytes, EncryptOracle, DecryptOracle};
+use util::{rand_key, EncryptOracle, DecryptOracle};
 use xor::slice_xor;
 
 // XXX: should be okay to be constant for this?
@@ -15,16 +15,16 @@ const SSV_IV: [u8; 16] = [7u8; 16];
 
 // ssv: semicolon-separated values
 fn get_encrypt_aes_cbc_ssv_oracle(key: &[u8]) -> Box<EncryptOracle> {
-    let cipher = AESCipherCBC::new(key, &SSV_IV);
+    let cipher = AESCipherCBC::new(key, &SSV_IV).unwrap();
     Box::new(move |plaintext: &[u8]| -> Vec<u8> {
         ssv_aes_encrypt(&cipher, plaintext)
     })
 }
 
 fn get_decrypt_aes_cbc_ssv(key: &[u8]) -> Box<DecryptOracle> {
-    let cipher = AESCipherCBC::new(key, &SSV_IV);
+    let cipher = AESCipherCBC::new(key, &SSV_IV).unwrap();
     Box::new(move |ciphertext: &[u8]| -> Vec<u8> {
-        ssv_aes_decrypt(&cipher, ciphertext)
+        ssv_aes_decrypt(&cipher, ciphertext).unwrap()
     })
 }
 
@@ -82,167 +82,11 @@ fn decrypt_aes_cbc_ssv_bitflip_test() {
     println!("Finished AES CBC bitflip test");
 }
 
-fn aes_cbc_decrypt_padding_oracle(cipher: &AESCipherCBC,
-                                  iv: &[u8],
-                                  ciphertext: &[u8]) ->
-                                    Result<Vec<u8>, String> {
-    let padded = cipher.decrypt_iv(iv, ciphertext);
-    match pkcs7_maybe_unpad_copy(&padded, AES_BLOCK_SIZE) {
-        Ok(_) => Ok(padded),
-        Err(e) => Err(e),
-    }
-}
-
-fn disambiguate_padding(cipher: &AESCipherCBC,
-                        prev_block: &[u8],
-                        block: &[u8],
-                        i: usize) -> bool {
-    // we want the one that gives us padding of [1]
-    // however, if the block actually has [3, 3, x], we
-    // might accidentally succeed with [3, 3, 3]
-    // disambiguate by flipping high bits on all
-    // the earlier bytes...e.g. if the actual data is
-    // padded with 15 bytes like [15, 15, 15 ...],
-    // then flipping the 2nd byte would cause an error
-    let mut false_positive = false;
-    let mut c1 = prev_block.to_vec();
-    for p_i in 0..i {
-        c1[p_i] = 0xffu8;
-        match aes_cbc_decrypt_padding_oracle(cipher, &c1, block) {
-            Ok(_) => {},
-            Err(_) => {
-                false_positive = true;
-                break;
-            },
-        };
-    }
-    false_positive
-}
-
-fn aes_cbc_padding_oracle_guess_block(cipher: &AESCipherCBC,
-                                      prev_block: &[u8],
-                                      block: &[u8]) -> Vec<u8> {
-    // assume we have ciphertext blocks c1, c2 and plaintext p1, p2
-    // p2 = decrypt(c2) ^ c1
-    //
-    // for the last byte, the only valid byte if we screw up decryption is 1.
-    // so we can brute force until padding is valid.
-    // XXX: corner case if original padding is 1, what are the other corners?
-    // once we do so, we can assume:
-    // 1 = decrypt(c2)[15] ^ brute_forced_byte
-    //
-    // then we solve with the 2 constraints to get p2 back out
-    // let dc2 = decrypt(c2)
-    //
-    // xor both sides of orig equality by 1:
-    //  p2[15] ^ 1 = dc2[15] ^ c1[15] ^ 1
-    // replace 1 with our brute forced equality :
-    //  p2[15] ^ 1 = dc2[15] ^ c1[15] ^ (dc2[15] ^ brute_forced_byte)
-    // xor is transitive:
-    //  p2[15] ^ 1 = dc2[15] ^ dc2[15] ^ c1[15] ^ brute_forced_byte
-    // for all a, a ^ a == 0:
-    //  p2[15] ^ 1 = c1[15] ^ brute_forced_byte
-    // xor both sides by 1, done:
-    //  p2[15] = c1[15] ^ brute_forced_byte ^ 1
-    //
-    // once we know p2[15], we can recover decrypt(c2)[15] and choose b
-    // s.t. decrypt(c2)[15] ^ b = anything
-    // so repeat with padding [2, 2], [3, 3, 3], [4, 4, 4, 4] etc.
-
-    // plain_block is p2
-    let mut plain_block = vec![0u8; AES_BLOCK_SIZE];
-    // decrypt_block is decrypt(c2)
-    let mut decrypt_block = vec![0u8; AES_BLOCK_SIZE];
-
-    for i in (0..AES_BLOCK_SIZE).rev() {
-        let mut c1 = prev_block.to_vec();
-
-        // if we're looking at the n - 3 byte, we need to set n - 2 and
-        // n - 1 to 0x3 for proper padding
-        let pad = (AES_BLOCK_SIZE - i) as u8;
-        for j in (i + 1)..AES_BLOCK_SIZE {
-            // pad = decrypt(c2) ^ c1
-            c1[j] = pad ^ decrypt_block[j];
-        }
-
-        let mut found = false;
-        let c1_byte = c1[i];
-
-        // in case nothing matches, set defaults
-        plain_block[i] = pad;
-        decrypt_block[i] = pad ^ c1_byte;
-        for j in 0..256 {
-            let b = j as u8;
-            if b == c1_byte {
-                continue;
-            }
-
-            c1[i] = b;
-            match aes_cbc_decrypt_padding_oracle(cipher, &c1, block) {
-                Ok(_) => {
-                    let mut p2_byte = c1_byte ^ b ^ pad;
-                    if found {
-                        assert!(i == AES_BLOCK_SIZE - 1,
-                                concat!("cbc padding ambiguity {}:",
-                                        "prev {} curr {}\n{:?}\n{:?}\n{:?}"),
-                                i, plain_block[i], p2_byte,
-                                c1, plain_block, decrypt_block);
-
-                        let false_positive = disambiguate_padding(cipher,
-                                                                  &c1,
-                                                                  block,
-                                                                  i);
-                        if false_positive {
-                            p2_byte = plain_block[i];
-                        }
-                    }
-                    plain_block[i] = p2_byte;
-                    decrypt_block[i] = p2_byte ^ c1_byte;
-                    found = true;
-                },
-                Err(_) => {},
-            }
-        }
-    }
-
-    plain_block
-}
-
-fn decrypt_aes_cbc_padding_test(plaintext: &[u8]) {
-    let key = rand_key();
-    let iv = rand_bytes(AES_BLOCK_SIZE);
-    let cipher = AESCipherCBC::new(&key, &iv);
-
-    let ciphertext = cipher.pad_and_encrypt(plaintext);
-
-    println!("aes cbc padding oracle decrypt {:?}", plaintext);
-    println!("key: {:?}", &key);
-    println!("initial ciphertext:");
-    for chunk in (&ciphertext).chunks(AES_BLOCK_SIZE) {
-        println!("{:?}", chunk);
-    }
-
-    let mut decrypted = aes_cbc_padding_oracle_guess_block(&cipher, &iv,
-                                            &ciphertext[..AES_BLOCK_SIZE]);
-    let mut chunks = Vec::new();
-    for chunk in (&ciphertext).chunks(AES_BLOCK_SIZE) {
-        chunks.push(chunk);
-    }
-    for w in chunks.windows(2) {
-        decrypted.extend(
-            aes_cbc_padding_oracle_guess_block(&cipher, w[0], w[1]));
-    }
-
-    // XXX: make slice compare and panic assert macro
-    assert!(pkcs7_pad(&plaintext, AES_BLOCK_SIZE) == decrypted,
-            "\nplaintext: {:?}\ndecrypted: {:?}", plaintext, decrypted);
-}
-
 fn key_as_iv_test() {
     let key = rand_key();
     println!("Running key_as_iv_test with key {:?}", key);
 
-    let cipher = AESCipherCBC::new(&key, &key);
+    let cipher = AESCipherCBC::new(&key, &key).unwrap();
     let input = vec!('A' as u8; AES_BLOCK_SIZE * 2);
     let mut ciphertext = ssv_aes_encrypt(&cipher, &input);
     for b in ciphertext[AES_BLOCK_SIZE..AES_BLOCK_SIZE * 2].iter_mut() {
@@ -257,13 +101,14 @@ fn key_as_iv_test() {
             // we could flip some bits and retry until we get a failure
             panic!("key_as_iv_test failed, should retry? {:?}", &v)
         },
-        Err(v) => {
+        Err(Error::InvalidAscii(v)) => {
             let recovered =
                 slice_xor(&v[..AES_BLOCK_SIZE],
                           &v[AES_BLOCK_SIZE * 2..AES_BLOCK_SIZE * 3]);
             assert!(&key as &[u8] == &recovered as &[u8],
                     "recovered {:?} expected the key", &recovered);
         },
+        Err(e) => panic!("key_as_iv_test decrypt failed {}", e),
     };
 }
 
@@ -271,22 +116,5 @@ pub fn decrypt_aes_cbc_test() {
     println!("Starting AES CBC decrypt tests");
     decrypt_aes_cbc_ssv_bitflip_test();
     key_as_iv_test();
-    let plaintexts = [
-        "1234567890123\x03\x036", // test disambiguating [1] vs [3, 3, 3]
-        "\x16\x16\x16\x16\x16\x16\x16\x16\x16\x16\x16\x16\x16\x16\x16",
-        "MDAwMDAwTm93IHRoYXQgdGhlIHBhcnR5IGlzIGp1bXBpbmc=",
-        "MDAwMDAxV2l0aCB0aGUgYmFzcyBraWNrZWQgaW4gYW5kIHRoZSBWZWdhJ3MgYXJlIHB1bXBpbic=",
-        "MDAwMDAyUXVpY2sgdG8gdGhlIHBvaW50LCB0byB0aGUgcG9pbnQsIG5vIGZha2luZw==",
-        "MDAwMDAzQ29va2luZyBNQydzIGxpa2UgYSBwb3VuZCBvZiBiYWNvbg==",
-        "MDAwMDA0QnVybmluZyAnZW0sIGlmIHlvdSBhaW4ndCBxdWljayBhbmQgbmltYmxl",
-        "MDAwMDA1SSBnbyBjcmF6eSB3aGVuIEkgaGVhciBhIGN5bWJhbA==",
-        "MDAwMDA2QW5kIGEgaGlnaCBoYXQgd2l0aCBhIHNvdXBlZCB1cCB0ZW1wbw==",
-        "MDAwMDA3SSdtIG9uIGEgcm9sbCwgaXQncyB0aW1lIHRvIGdvIHNvbG8=",
-        "MDAwMDA4b2xsaW4nIGluIG15IGZpdmUgcG9pbnQgb2g=",
-        "MDAwMDA5aXRoIG15IHJhZy10b3AgZG93biBzbyBteSBoYWlyIGNhbiBibG93",
-    ];
-    for plaintext in &plaintexts {
-        decrypt_aes_cbc_padding_test(&plaintext.as_bytes());
-    }
     println!("Finished AES CBC decrypt tests");
 }
@@ -1,6 +1,8 @@
 use std::cmp::min;
 
 use aes::{AESCipher, expand_key, encrypt_block, AES_BLOCK_SIZE};
+use aes::stream::AESCTRStream;
+use error::Result;
 use xor::{slice_xor, slice_xor_inplace};
 
 pub struct AESCipherCTR {
@@ -10,22 +12,32 @@ pub struct AESCipherCTR {
 }
 
 impl AESCipherCTR {
-    pub fn new(key: &[u8], nonce_le: u64) -> AESCipherCTR {
-        AESCipherCTR {
-            key_schedule: expand_key(key),
+    pub fn new(key: &[u8], nonce_le: u64) -> Result<AESCipherCTR> {
+        Ok(AESCipherCTR {
+            key_schedule: expand_key(key)?,
             nonce_le: nonce_le,
-        }
+        })
     }
 
-    fn ctr_mode(&self, text: &[u8]) -> Vec<u8> {
-        let mut result: Vec<u8> = Vec::new();
-        let mut counter = vec![0u8; AES_BLOCK_SIZE];
+    /// Streaming encrypt/decrypt starting from the beginning of the
+    /// keystream
+    pub fn stream(&self) -> AESCTRStream<'_> {
+        AESCTRStream::new(self)
+    }
 
+    /// Keystream for the block_index'th block
+    pub fn keystream_block(&self, block_index: u64) -> Vec<u8> {
+        let mut counter = vec![0u8; AES_BLOCK_SIZE];
         u64_fill_slice_le(&mut counter[..8], self.nonce_le);
+        u64_fill_slice_le(&mut counter[8..], block_index);
+        encrypt_block(&self.key_schedule, &counter)
+    }
+
+    fn ctr_mode(&self, text: &[u8]) -> Vec<u8> {
+        let mut result: Vec<u8> = Vec::new();
 
         for (i, chunk) in text.chunks(AES_BLOCK_SIZE).enumerate() {
-            u64_fill_slice_le(&mut counter[8..], i as u64);
-            let mut keystream = encrypt_block(&self.key_schedule, &counter);
+            let mut keystream = self.keystream_block(i as u64);
 
             // xor into keystream since we're going to drop it
             slice_xor_inplace(&mut keystream, chunk);
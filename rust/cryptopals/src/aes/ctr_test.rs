@@ -98,12 +98,12 @@ fn nonce_reuse_test(ciphertexts: &Vec<Vec<u8>>) {
 
 fn nonce_reuse_test_19() {
     let key = rand_key();
-    let cipher = AESCipherCTR::new(&key, 0);
+    let cipher = AESCipherCTR::new(&key, 0).unwrap();
     println!("Starting nonce reuse AES CTR test 19 with key {:?}", &key);
 
     let mut ciphertexts = Vec::new();
     for &plaintext in SET_3_CHALLENGE_19 {
-        let ciphertext = cipher.encrypt(&base64_decode(plaintext));
+        let ciphertext = cipher.encrypt(&base64_decode(plaintext).unwrap());
         ciphertexts.push(ciphertext);
     }
     nonce_reuse_test(&ciphertexts);
@@ -111,7 +111,7 @@ fn nonce_reuse_test_19() {
 
 fn nonce_reuse_test_20() {
     let key = rand_key();
-    let cipher = AESCipherCTR::new(&key, 0);
+    let cipher = AESCipherCTR::new(&key, 0).unwrap();
     println!("Starting nonce reuse AES CTR test 20 with key {:?}", &key);
 
     let mut ciphertexts = Vec::new();
@@ -120,7 +120,7 @@ fn nonce_reuse_test_20() {
     let buffered = BufReader::new(&f);
     for rline in buffered.lines() {
         let line = rline.unwrap();
-        let ciphertext = cipher.encrypt(&base64_decode(&line));
+        let ciphertext = cipher.encrypt(&base64_decode(&line).unwrap());
         ciphertexts.push(ciphertext);
     }
 
@@ -138,7 +138,8 @@ fn edit_test() {
         (&['A' as u8; 64] as &[u8], 31, &[0u8; 32] as &[u8]),
     ];
     for &(plaintext, offset, replacetext) in &tests {
-        let cipher = AESCipherCTR::new("YELLOW SUBMARINE".as_bytes(), 0);
+        let cipher = AESCipherCTR::new("YELLOW SUBMARINE".as_bytes(),
+                                       0).unwrap();
         let mut ciphertext = cipher.encrypt(&plaintext);
         cipher.edit(&mut ciphertext, offset, &replacetext);
         let modified = cipher.decrypt(&ciphertext);
@@ -151,9 +152,9 @@ fn random_access_test() {
     edit_test();
 
     let from_file = base64_decode_file("data/1.7.txt");
-    let ecb = AESCipherECB::new("YELLOW SUBMARINE".as_bytes());
-    let plaintext = ecb.decrypt_and_unpad(&from_file);
-    let cipher = AESCipherCTR::new(&rand_key(), rand_u64());
+    let ecb = AESCipherECB::new("YELLOW SUBMARINE".as_bytes()).unwrap();
+    let plaintext = ecb.decrypt_and_unpad(&from_file).unwrap();
+    let cipher = AESCipherCTR::new(&rand_key(), rand_u64()).unwrap();
     let ciphertext = cipher.encrypt(&plaintext);
 
     // given ciphertext get original plaintext with edit function
@@ -170,7 +171,7 @@ fn bitflip_test() {
     let key = rand_key();
     let nonce = rand_u64();
     println!("AES CTR bitflip admin key {:?} nonce {}", key, nonce);
-    let cipher = AESCipherCTR::new(&key, nonce);
+    let cipher = AESCipherCTR::new(&key, nonce).unwrap();
 
     // need to flip the byte between admin and true to an '='
     // and the first byte to ';'
@@ -199,7 +200,7 @@ fn bitflip_test() {
         ciphertext[offset] = (b ^ 0x10u8) | (b & !0x10u8);
     }
 
-    let modified = ssv_aes_decrypt(&cipher, &ciphertext);
+    let modified = ssv_aes_decrypt(&cipher, &ciphertext).unwrap();
     assert!(has_admin(&modified),
             "FAILED: AES CTR bitflip test {:?}", &modified);
 }
@@ -208,8 +209,8 @@ pub fn decrypt_aes_ctr_test() {
     let encoded = "L77na/nrFsKvynd6HzOoG7GHTLXsTVu9qvY/2syLXzhPweyyMTJULu/6/kXX0KSvoOLSFQ==";
     let expected = "Yo, VIP Let's kick it Ice, Ice, baby Ice, Ice, baby ";
 
-    let ciphertext = base64_decode(encoded);
-    let cipher = AESCipherCTR::new("YELLOW SUBMARINE".as_bytes(), 0);
+    let ciphertext = base64_decode(encoded).unwrap();
+    let cipher = AESCipherCTR::new("YELLOW SUBMARINE".as_bytes(), 0).unwrap();
 
     let decrypted = cipher.decrypt(&ciphertext);
     match str::from_utf8(&decrypted) {
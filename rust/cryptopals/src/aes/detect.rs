@@ -40,11 +40,11 @@ fn aes_cbc_ecb_random_encrypt(plaintext: &[u8]) -> (&'static str, Vec<u8>) {
                   {
                       let iv = vec![rng.gen_range(0, 256) as u8;
                                     AES_BLOCK_SIZE];
-                      let cipher = AESCipherCBC::new(&key, &iv);
+                      let cipher = AESCipherCBC::new(&key, &iv).unwrap();
                       cipher.encrypt(&padded)
                   }),
             1 => ("ecb", {
-                let cipher = AESCipherECB::new(&key);
+                let cipher = AESCipherECB::new(&key).unwrap();
                 cipher.encrypt(&padded)
             }),
             _ => panic!("welp"),  // should be unreachable
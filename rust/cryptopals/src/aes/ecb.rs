@@ -1,14 +1,15 @@
 use aes::{AESCipher, expand_key, encrypt_block, decrypt_block, AES_BLOCK_SIZE};
+use error::Result;
 
 pub struct AESCipherECB {
     key_schedule: Vec<Vec<u8>>,
 }
 
 impl AESCipherECB {
-    pub fn new(key: &[u8]) -> AESCipherECB {
-        AESCipherECB {
-            key_schedule: expand_key(key),
-        }
+    pub fn new(key: &[u8]) -> Result<AESCipherECB> {
+        Ok(AESCipherECB {
+            key_schedule: expand_key(key)?,
+        })
     }
 }
 
@@ -0,0 +1,133 @@
+use aes::{expand_key, encrypt_block, AES_BLOCK_SIZE};
+use error::{Error, Result};
+use util::constant_time_eq;
+use xor::slice_xor_inplace;
+
+pub const GCM_NONCE_SIZE: usize = 12;
+pub const GCM_TAG_SIZE: usize = 16;
+
+// x^128 + x^7 + x^2 + x + 1, but GCM is bit-reflected so the low terms
+// end up at the top of the u128
+const GF128_R: u128 = 0xe1 << 120;
+
+pub struct AESCipherGCM {
+    key_schedule: Vec<Vec<u8>>,
+    // GHASH key, H = E(K, 0^128)
+    h: u128,
+}
+
+pub fn block_to_u128(block: &[u8]) -> u128 {
+    assert!(block.len() <= AES_BLOCK_SIZE);
+    // partial blocks are zero padded on the right
+    let mut tmp = [0u8; AES_BLOCK_SIZE];
+    tmp[..block.len()].copy_from_slice(block);
+    u128::from_be_bytes(tmp)
+}
+
+pub fn u128_to_block(x: u128) -> [u8; AES_BLOCK_SIZE] {
+    x.to_be_bytes()
+}
+
+/// Multiply in GF(2^128) with GCM's bit order, i.e. the msb of the first
+/// byte is the coefficient of x^0.
+pub fn gf128_mul(x: u128, y: u128) -> u128 {
+    // NIST SP 800-38D algorithm 1, masks instead of branches so the
+    // timing doesn't depend on H
+    let mut z = 0u128;
+    let mut v = y;
+    for i in 0..128 {
+        let bit = (x >> (127 - i)) & 1;
+        z ^= v & 0u128.wrapping_sub(bit);
+        let lsb = v & 1;
+        v = (v >> 1) ^ (GF128_R & 0u128.wrapping_sub(lsb));
+    }
+    z
+}
+
+pub fn ghash(h: u128, aad: &[u8], ciphertext: &[u8]) -> u128 {
+    let mut y = 0u128;
+    for chunk in aad.chunks(AES_BLOCK_SIZE) {
+        y = gf128_mul(y ^ block_to_u128(chunk), h);
+    }
+    for chunk in ciphertext.chunks(AES_BLOCK_SIZE) {
+        y = gf128_mul(y ^ block_to_u128(chunk), h);
+    }
+
+    // last block is len(A) || len(C) in bits
+    let lens = ((aad.len() as u128 * 8) << 64) |
+               (ciphertext.len() as u128 * 8);
+    gf128_mul(y ^ lens, h)
+}
+
+impl AESCipherGCM {
+    pub fn new(key: &[u8]) -> Result<AESCipherGCM> {
+        let key_schedule = expand_key(key)?;
+        let h = block_to_u128(&encrypt_block(&key_schedule,
+                                             &[0u8; AES_BLOCK_SIZE]));
+        Ok(AESCipherGCM {
+            key_schedule,
+            h,
+        })
+    }
+
+    /// Exposed for the nonce-reuse attack tests
+    pub fn ghash_key(&self) -> u128 {
+        self.h
+    }
+
+    fn initial_counter(nonce: &[u8]) -> Result<[u8; AES_BLOCK_SIZE]> {
+        // only support 96-bit nonces, J0 = nonce || 0^31 || 1
+        if nonce.len() != GCM_NONCE_SIZE {
+            return Err(Error::BadIVLength(nonce.len()));
+        }
+        let mut counter = [0u8; AES_BLOCK_SIZE];
+        counter[..GCM_NONCE_SIZE].copy_from_slice(nonce);
+        counter[AES_BLOCK_SIZE - 1] = 1;
+        Ok(counter)
+    }
+
+    fn gctr(&self, j0: &[u8; AES_BLOCK_SIZE], text: &[u8]) -> Vec<u8> {
+        let mut result: Vec<u8> = Vec::with_capacity(text.len());
+        let mut counter = *j0;
+        let mut ctr = u32::from_be_bytes([j0[12], j0[13], j0[14], j0[15]]);
+
+        for chunk in text.chunks(AES_BLOCK_SIZE) {
+            // inc32 only touches the low 32 bits
+            ctr = ctr.wrapping_add(1);
+            counter[GCM_NONCE_SIZE..].copy_from_slice(&ctr.to_be_bytes());
+
+            let mut keystream = encrypt_block(&self.key_schedule, &counter);
+            slice_xor_inplace(&mut keystream, chunk);
+            result.extend_from_slice(&keystream[..chunk.len()]);
+        }
+        result
+    }
+
+    fn tag(&self, j0: &[u8; AES_BLOCK_SIZE], aad: &[u8],
+           ciphertext: &[u8]) -> [u8; GCM_TAG_SIZE] {
+        let s = ghash(self.h, aad, ciphertext);
+        let mask = block_to_u128(&encrypt_block(&self.key_schedule, j0));
+        u128_to_block(s ^ mask)
+    }
+
+    /// Returns (ciphertext, tag)
+    pub fn encrypt(&self, nonce: &[u8], aad: &[u8],
+                   plaintext: &[u8]) -> Result<(Vec<u8>, Vec<u8>)> {
+        let j0 = AESCipherGCM::initial_counter(nonce)?;
+        let ciphertext = self.gctr(&j0, plaintext);
+        let tag = self.tag(&j0, aad, &ciphertext);
+        Ok((ciphertext, tag.to_vec()))
+    }
+
+    pub fn decrypt(&self, nonce: &[u8], aad: &[u8], ciphertext: &[u8],
+                   tag: &[u8]) -> Result<Vec<u8>> {
+        let j0 = AESCipherGCM::initial_counter(nonce)?;
+        let expected = self.tag(&j0, aad, ciphertext);
+
+        // check the tag before decrypting anything
+        if !constant_time_eq(&expected, tag) {
+            return Err(Error::AuthenticationFailed);
+        }
+        Ok(self.gctr(&j0, ciphertext))
+    }
+}
@@ -0,0 +1,72 @@
+use aes::AES_BLOCK_SIZE;
+use aes::gcm::{ghash, block_to_u128, u128_to_block};
+use poly::{GF128Poly, roots};
+
+/// Everything an eavesdropper sees for one GCM message, the nonce is
+/// assumed to be shared by all of them
+#[derive(Clone, Debug)]
+pub struct GCMMessage {
+    pub aad: Vec<u8>,
+    pub ciphertext: Vec<u8>,
+    pub tag: Vec<u8>,
+}
+
+/// tag = b_1 * H^m + b_2 * H^(m - 1) + ... + b_m * H + S where S is
+/// E(K, J0). This returns the polynomial in H with the tag moved over,
+/// so evaluating it at the real H gives S.
+pub fn gcm_tag_poly(msg: &GCMMessage) -> GF128Poly {
+    let mut blocks: Vec<u128> = Vec::new();
+    for chunk in msg.aad.chunks(AES_BLOCK_SIZE) {
+        blocks.push(block_to_u128(chunk));
+    }
+    for chunk in msg.ciphertext.chunks(AES_BLOCK_SIZE) {
+        blocks.push(block_to_u128(chunk));
+    }
+    blocks.push(((msg.aad.len() as u128 * 8) << 64) |
+                (msg.ciphertext.len() as u128 * 8));
+
+    // coeffs[0] is the constant term, the last block goes with H^1
+    let mut coeffs = vec!(block_to_u128(&msg.tag));
+    coeffs.extend(blocks.iter().rev());
+    GF128Poly::new(&coeffs)
+}
+
+/// S is the same for both messages when the nonce is reused, so H is a
+/// root of the sum of their tag polynomials
+pub fn gcm_auth_key_candidates(msg1: &GCMMessage,
+                               msg2: &GCMMessage) -> Vec<u128> {
+    let f = gcm_tag_poly(msg1).add(&gcm_tag_poly(msg2));
+    roots(&f)
+}
+
+/// Intersect the candidates from pairing the first message with each of
+/// the others until there's only one left. Takes at least 2 messages with
+/// the same nonce, None with fewer.
+pub fn gcm_recover_auth_key(msgs: &[GCMMessage]) -> Option<u128> {
+    if msgs.len() < 2 {
+        return None;
+    }
+
+    let mut candidates = gcm_auth_key_candidates(&msgs[0], &msgs[1]);
+    for msg in &msgs[2..] {
+        if candidates.len() <= 1 {
+            break;
+        }
+        let others = gcm_auth_key_candidates(&msgs[0], msg);
+        candidates.retain(|h| others.contains(h));
+    }
+
+    if candidates.len() == 1 {
+        Some(candidates[0])
+    } else {
+        None
+    }
+}
+
+/// Once we have H, S falls out of any known message and we can tag
+/// whatever we want under the same nonce
+pub fn gcm_forge_tag(h: u128, known: &GCMMessage, aad: &[u8],
+                     ciphertext: &[u8]) -> Vec<u8> {
+    let s = gcm_tag_poly(known).eval(h);
+    u128_to_block(ghash(h, aad, ciphertext) ^ s).to_vec()
+}
@@ -0,0 +1,192 @@
+use aes::gcm::{AESCipherGCM, gf128_mul, block_to_u128};
+use aes::gcm_nonce_reuse::{GCMMessage, gcm_recover_auth_key, gcm_forge_tag};
+use hex::{hex_to_bytes, bytes_to_hex};
+use util::{rand_key, rand_bytes};
+use xor::slice_xor;
+
+// from the GCM spec (McGrew and Viega), the ones with 96-bit IVs
+// key, iv, plaintext, aad, ciphertext, tag
+const GCM_TEST_VECTORS: &[(&str, &str, &str, &str, &str, &str)] = &[
+    // test case 1
+    ("00000000000000000000000000000000",
+     "000000000000000000000000",
+     "",
+     "",
+     "",
+     "58e2fccefa7e3061367f1d57a4e7455a"),
+    // test case 2
+    ("00000000000000000000000000000000",
+     "000000000000000000000000",
+     "00000000000000000000000000000000",
+     "",
+     "0388dace60b6a392f328c2b971b2fe78",
+     "ab6e47d42cec13bdf53a67b21257bddf"),
+    // test case 3
+    ("feffe9928665731c6d6a8f9467308308",
+     "cafebabefacedbaddecaf888",
+     concat!("d9313225f88406e5a55909c5aff5269a",
+             "86a7a9531534f7da2e4c303d8a318a72",
+             "1c3c0c95956809532fcf0e2449a6b525",
+             "b16aedf5aa0de657ba637b391aafd255"),
+     "",
+     concat!("42831ec2217774244b7221b784d0d49c",
+             "e3aa212f2c02a4e035c17e2329aca12e",
+             "21d514b25466931c7d8f6a5aac84aa05",
+             "1ba30b396a0aac973d58e091473f5985"),
+     "4d5c2af327cd64a62cf35abd2ba6fab4"),
+    // test case 4
+    ("feffe9928665731c6d6a8f9467308308",
+     "cafebabefacedbaddecaf888",
+     concat!("d9313225f88406e5a55909c5aff5269a",
+             "86a7a9531534f7da2e4c303d8a318a72",
+             "1c3c0c95956809532fcf0e2449a6b525",
+             "b16aedf5aa0de657ba637b39"),
+     "feedfacedeadbeeffeedfacedeadbeefabaddad2",
+     concat!("42831ec2217774244b7221b784d0d49c",
+             "e3aa212f2c02a4e035c17e2329aca12e",
+             "21d514b25466931c7d8f6a5aac84aa05",
+             "1ba30b396a0aac973d58e091"),
+     "5bc94fbc3221a5db94fae95ae7121a47"),
+    // test case 16
+    (concat!("feffe9928665731c6d6a8f9467308308",
+             "feffe9928665731c6d6a8f9467308308"),
+     "cafebabefacedbaddecaf888",
+     concat!("d9313225f88406e5a55909c5aff5269a",
+             "86a7a9531534f7da2e4c303d8a318a72",
+             "1c3c0c95956809532fcf0e2449a6b525",
+             "b16aedf5aa0de657ba637b39"),
+     "feedfacedeadbeeffeedfacedeadbeefabaddad2",
+     concat!("522dc1f099567d07f47f37a32a84427d",
+             "643a8cdcbfe5c0c97598a2bd2555d1aa",
+             "8cb08e48590dbb3da7b08b1056828838",
+             "c5f61e6393ba7a0abcc9f662"),
+     "76fc6ece0f4e1768cddf8853bb2d551b"),
+];
+
+fn gf128_mul_test() {
+    // x^0 is the msb, so this is the multiplicative identity
+    let one = 1u128 << 127;
+    let h = block_to_u128(
+        &hex_to_bytes("66e94bd4ef8a2c3b884cfa59ca342b2e").unwrap());
+    assert_eq!(gf128_mul(h, one), h, "gf128_mul identity failed");
+    assert_eq!(gf128_mul(one, h), h, "gf128_mul identity failed");
+    assert_eq!(gf128_mul(h, 0), 0, "gf128_mul zero failed");
+
+    let x = block_to_u128(
+        &hex_to_bytes("0388dace60b6a392f328c2b971b2fe78").unwrap());
+    assert_eq!(gf128_mul(x, h), gf128_mul(h, x), "gf128_mul not commutative");
+}
+
+fn gcm_test_vectors_test() {
+    for &(key, iv, plaintext, aad, expected_ciphertext, expected_tag) in
+            GCM_TEST_VECTORS {
+        let cipher = AESCipherGCM::new(&hex_to_bytes(key).unwrap()).unwrap();
+        let (ciphertext, tag) =
+            cipher.encrypt(&hex_to_bytes(iv).unwrap(),
+                           &hex_to_bytes(aad).unwrap(),
+                           &hex_to_bytes(plaintext).unwrap()).unwrap();
+        if ciphertext != hex_to_bytes(expected_ciphertext).unwrap() {
+            panic!("FAILED: AES GCM key {} expected ciphertext {} got {}",
+                   key, expected_ciphertext, bytes_to_hex(&ciphertext));
+        }
+        if tag != hex_to_bytes(expected_tag).unwrap() {
+            panic!("FAILED: AES GCM key {} expected tag {} got {}",
+                   key, expected_tag, bytes_to_hex(&tag));
+        }
+
+        match cipher.decrypt(&hex_to_bytes(iv).unwrap(),
+                             &hex_to_bytes(aad).unwrap(), &ciphertext, &tag) {
+            Ok(decrypted) => {
+                assert!(decrypted == hex_to_bytes(plaintext).unwrap(),
+                        "AES GCM key {} decrypted {:?}", key, decrypted);
+            },
+            Err(e) => panic!("AES GCM key {} failed to decrypt {}", key, e),
+        };
+    }
+}
+
+fn gcm_tamper_test() {
+    let key = rand_key();
+    let nonce = rand_bytes(12);
+    let aad = "beep boop".as_bytes();
+    let plaintext = "meow meow meow meow meow".as_bytes();
+    println!("AES GCM tamper test with key {:?} nonce {:?}", key, nonce);
+
+    let cipher = AESCipherGCM::new(&key).unwrap();
+    let (ciphertext, tag) = cipher.encrypt(&nonce, aad, plaintext).unwrap();
+
+    let mut flipped = ciphertext.clone();
+    flipped[3] ^= 0x1u8;
+    assert!(cipher.decrypt(&nonce, aad, &flipped, &tag).is_err(),
+            "AES GCM accepted modified ciphertext");
+
+    assert!(cipher.decrypt(&nonce, "beep bop".as_bytes(),
+                           &ciphertext, &tag).is_err(),
+            "AES GCM accepted modified aad");
+
+    let mut bad_tag = tag.clone();
+    bad_tag[15] ^= 0x80u8;
+    assert!(cipher.decrypt(&nonce, aad, &ciphertext, &bad_tag).is_err(),
+            "AES GCM accepted modified tag");
+    assert!(cipher.decrypt(&nonce, aad, &ciphertext, &tag[..12]).is_err(),
+            "AES GCM accepted truncated tag");
+}
+
+fn gcm_nonce_reuse_forgery_test() {
+    let key = rand_key();
+    let nonce = rand_bytes(12);
+    println!("AES GCM nonce reuse forgery with key {:?} nonce {:?}",
+             key, nonce);
+    let cipher = AESCipherGCM::new(&key).unwrap();
+
+    // oops the server reused the nonce
+    let plaintexts = [
+        "user=alice;role=user;note=hello there",
+        "user=bob;role=user;note=lunch at noon?",
+        "user=carol;role=user;note=meow meow",
+    ];
+    let mut msgs = Vec::new();
+    for plaintext in &plaintexts {
+        let aad = rand_bytes(20);
+        let (ciphertext, tag) = cipher.encrypt(&nonce, &aad,
+                                               plaintext.as_bytes()).unwrap();
+        msgs.push(GCMMessage{aad, ciphertext, tag});
+    }
+
+    let h = match gcm_recover_auth_key(&msgs) {
+        Some(h) => h,
+        None => panic!("gcm_nonce_reuse_forgery_test couldn't narrow down H"),
+    };
+    assert_eq!(h, cipher.ghash_key(), "recovered the wrong H");
+
+    // nothing to pair a lone message with
+    assert_eq!(gcm_recover_auth_key(&msgs[..1]), None);
+    assert_eq!(gcm_recover_auth_key(&[]), None);
+
+    // we know the first plaintext so we know the keystream, write whatever
+    // we want over it and tag it
+    let forged_plaintext = "user=alice;role=admin;note=hi".as_bytes();
+    let keystream = slice_xor(&msgs[0].ciphertext,
+                              plaintexts[0].as_bytes());
+    let forged_ciphertext = slice_xor(&keystream, forged_plaintext);
+    let forged_aad = "anything goes".as_bytes();
+    let forged_tag = gcm_forge_tag(h, &msgs[0], forged_aad,
+                                   &forged_ciphertext);
+
+    match cipher.decrypt(&nonce, forged_aad, &forged_ciphertext,
+                         &forged_tag) {
+        Ok(decrypted) => {
+            assert!(&decrypted as &[u8] == forged_plaintext,
+                    "forged message decrypted to {:?}", decrypted);
+        },
+        Err(e) => panic!("gcm_nonce_reuse_forgery_test forgery failed {}", e),
+    };
+}
+
+pub fn aes_gcm_test() {
+    gf128_mul_test();
+    gcm_test_vectors_test();
+    gcm_tamper_test();
+    gcm_nonce_reuse_forgery_test();
+    println!("Finished AES GCM tests");
+}
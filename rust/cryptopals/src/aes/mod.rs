@@ -6,6 +6,11 @@ mod ctr_test;
 mod detect;
 pub mod ecb;
 mod ecb_decrypt;
+pub mod gcm;
+pub mod gcm_nonce_reuse;
+mod gcm_test;
+pub mod stream;
+mod stream_test;
 use self::cbc::AESCipherCBC;
 use self::cbc_test::decrypt_aes_cbc_test;
 use self::constants::{SBOX,INV_SBOX,GF256_MUL_2, GF256_MUL_3, GF256_MUL_9,
@@ -15,7 +20,10 @@ use self::detect::distinguish_aes_cbc_ecb_test;
 use self::ecb::AESCipherECB;
 use self::ecb_decrypt::{decrypt_aes_ecb_simple_test,
                         decrypt_aes_ecb_sandwich_test};
+use self::gcm_test::aes_gcm_test;
+use self::stream_test::aes_stream_test;
 use base64::base64_decode_file;
+use error::{Error, Result};
 use hex::{hex_to_bytes,bytes_to_hex};
 use pkcs7::{pkcs7_pad, pkcs7_unpad, pkcs7_unpad_copy};
 use std::collections::HashSet;
@@ -24,9 +32,14 @@ use std::io::{BufRead, BufReader};
 use std::str;
 
 pub const AES_BLOCK_SIZE: usize = 16;
+pub const AES_128_KEY_SIZE: usize = 16;
+pub const AES_192_KEY_SIZE: usize = 24;
+pub const AES_256_KEY_SIZE: usize = 32;
 
 pub trait AESCipher {
-    fn set_iv(&mut self, _iv: &[u8]) { panic!("set_iv not implemented "); }
+    fn set_iv(&mut self, _iv: &[u8]) -> Result<()> {
+        Err(Error::NotImplemented("set_iv"))
+    }
 
     fn encrypt(&self, plaintext: &[u8]) -> Vec<u8>;
     fn decrypt(&self, ciphertext: &[u8]) -> Vec<u8>;
@@ -36,17 +49,28 @@ pub trait AESCipher {
         self.encrypt(&padded)
     }
 
-    fn decrypt_and_unpad(&self, ciphertext: &[u8]) -> Vec<u8> {
+    fn decrypt_and_unpad(&self, ciphertext: &[u8]) -> Result<Vec<u8>> {
+        // padded ciphertext is always whole blocks
+        if ciphertext.is_empty() ||
+                !ciphertext.len().is_multiple_of(AES_BLOCK_SIZE) {
+            return Err(Error::BadCiphertextLength(ciphertext.len()));
+        }
         let decrypted = self.decrypt(ciphertext);
         pkcs7_unpad_copy(&decrypted, AES_BLOCK_SIZE)
     }
 }
 
-pub fn encrypt_block(key_schedule: &Vec<Vec<u8>>,
-                     plaintext: &[u8]) -> Vec<u8> {
+// round keys are one block each, plus one for the initial round
+fn key_schedule_rounds(key_schedule: &Vec<Vec<u8>>) -> usize {
     let rounds = key_schedule.len() - 1;
     assert!(rounds == 10 || rounds == 12 || rounds == 14,
             "key_schedule was unexpected length {}", key_schedule.len());
+    rounds
+}
+
+pub fn encrypt_block(key_schedule: &Vec<Vec<u8>>,
+                     plaintext: &[u8]) -> Vec<u8> {
+    let rounds = key_schedule_rounds(key_schedule);
 
     let mut state = AESBlock::from_slice(plaintext);
 
@@ -69,9 +93,7 @@ pub fn encrypt_block(key_schedule: &Vec<Vec<u8>>,
 
 pub fn decrypt_block(key_schedule: &Vec<Vec<u8>>,
                      ciphertext: &[u8]) -> Vec<u8> {
-    let rounds = key_schedule.len() - 1;
-    assert!(rounds == 10 || rounds == 12 || rounds == 14,
-            "key_schedule was unexpected length {}", key_schedule.len());
+    let rounds = key_schedule_rounds(key_schedule);
 
     let mut state = AESBlock::from_slice(ciphertext);
 
@@ -248,15 +270,21 @@ impl AESBlock {
     }
 }
 
-pub fn expand_key(key: &[u8]) -> Vec<Vec<u8>> {
+// FIPS-197 picks 10/12/14 rounds for AES-128/192/256
+pub fn aes_rounds(key_len: usize) -> Result<usize> {
+    match key_len {
+        AES_128_KEY_SIZE => Ok(10),
+        AES_192_KEY_SIZE => Ok(12),
+        AES_256_KEY_SIZE => Ok(14),
+        _ => Err(Error::BadKeyLength(key_len)),
+    }
+}
+
+pub fn expand_key(key: &[u8]) -> Result<Vec<Vec<u8>>> {
     // from the wiki for Rijndael key schedule
-    let (n, b) = match key.len() {
-        16 => (16, 176),
-        24 => (24, 208),
-        32 => (32, 240),
-        // XXX: return error instead of panicking
-        _ => panic!("bad key len {}", key.len())
-    };
+    // n is the key len, b is the len of all the round keys
+    let n = key.len();
+    let b = (aes_rounds(n)? + 1) * AES_BLOCK_SIZE;
 
     let mut expanded: Vec<u8> = Vec::with_capacity(b);
 
@@ -297,7 +325,7 @@ pub fn expand_key(key: &[u8]) -> Vec<Vec<u8>> {
         keys.push(chunk.to_vec());
     }
 
-    keys
+    Ok(keys)
 }
 
 fn rijndael_core(t: &mut [u8; 4], rcon_i: usize) {
@@ -378,7 +406,7 @@ fn expand_key_test() {
     ];
 
     for &(key, expected) in &tests {
-        let expanded = expand_key(&key);
+        let expanded = expand_key(&key).unwrap();
         for ((i, chunk), block) in
              expected.chunks(AES_BLOCK_SIZE).enumerate().zip(expanded) {
             if block != chunk {
@@ -389,6 +417,63 @@ fn expand_key_test() {
     }
 }
 
+fn expand_key_sizes_test() {
+    // FIPS-197 appendix A, just check the last round key
+    let tests = [
+        ("2b7e151628aed2a6abf7158809cf4f3c",
+         "d014f9a8c9ee2589e13f0cc8b6630ca6"),
+        ("8e73b0f7da0e6452c810f32b809079e562f8ead2522c6b7b",
+         "e98ba06f448c773c8ecc720401002202"),
+        (concat!("603deb1015ca71be2b73aef0857d7781",
+                 "1f352c073b6108d72d9810a30914dff4"),
+         "fe4890d1e6188d0b046df344706c631e"),
+    ];
+
+    for &(key, expected) in &tests {
+        let key_bytes = hex_to_bytes(key).unwrap();
+        let expanded = expand_key(&key_bytes).unwrap();
+        let rounds = aes_rounds(key_bytes.len()).unwrap();
+        assert_eq!(expanded.len(), rounds + 1,
+                   "expand_key_sizes_test wrong number of round keys");
+        let last = expanded.last().unwrap();
+        if last != &hex_to_bytes(expected).unwrap() {
+            panic!("FAILED: expand_key {} expected {} got {}",
+                   key, expected, bytes_to_hex(last));
+        }
+    }
+
+    for &len in &[0, 15, 17, 20, 64] {
+        assert_eq!(expand_key(&vec!(0u8; len)), Err(Error::BadKeyLength(len)),
+                   "expand_key accepted key len {}", len);
+    }
+}
+
+fn aes_cbc_key_sizes_test() {
+    // NIST SP 800-38A F.2.3 and F.2.5, first two blocks
+    let tests = [
+        ("8e73b0f7da0e6452c810f32b809079e562f8ead2522c6b7b",
+         "4f021db243bc633d7178183a9fa071e8b4d9ada9ad7dedf4e5e738763f69145a"),
+        ("603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4",
+         "f58c4c04d6e5f1ba779eabfb5f7bfbd69cfc4e967edb808d679f777bc6702c7d"),
+    ];
+    let iv = hex_to_bytes("000102030405060708090a0b0c0d0e0f").unwrap();
+    let plaintext = hex_to_bytes(concat!("6bc1bee22e409f96e93d7e117393172a",
+                                         "ae2d8a571e03ac9c9eb76fac45af8e51"))
+                                .unwrap();
+
+    for &(key, expected) in &tests {
+        let cipher = AESCipherCBC::new(&hex_to_bytes(key).unwrap(),
+                                       &iv).unwrap();
+        let ciphertext = cipher.encrypt(&plaintext);
+        if ciphertext != hex_to_bytes(expected).unwrap() {
+            panic!("FAILED: AES CBC key {} expected {} got {}",
+                   key, expected, bytes_to_hex(&ciphertext));
+        }
+        assert!(cipher.decrypt(&ciphertext) == plaintext,
+                "AES CBC key {} failed to decrypt", key);
+    }
+}
+
 fn mix_columns_test() {
     // from wiki
     let tests = [
@@ -456,15 +541,17 @@ fn detect_aes_ecb_in_file(filename: &str) {
 
 fn decrypt_aes_cbc_base64_file(filename: &str, key: &[u8], iv: &[u8]) {
     let f = base64_decode_file(filename);
-    let cipher = AESCipherCBC::new(key, iv);
+    let cipher = AESCipherCBC::new(key, iv).unwrap();
     let decrypted_bytes = cipher.decrypt(&f);
-    let decrypted = str::from_utf8(&pkcs7_unpad(&decrypted_bytes,
-                                                AES_BLOCK_SIZE)).unwrap();
+    let decrypted = str::from_utf8(pkcs7_unpad(&decrypted_bytes,
+                                               AES_BLOCK_SIZE).unwrap())
+                        .unwrap();
     println!("AES CBC decrypt {}:\n{}", filename, decrypted);
 }
 
 pub fn aes_test(full_test: bool) {
     expand_key_test();
+    expand_key_sizes_test();
     mix_columns_test();
 
     let encrypt_tests = [
@@ -474,27 +561,34 @@ pub fn aes_test(full_test: bool) {
         ("59454c4c4f57205355424d4152494e45",
          "626f6f70626f6f70626f6f70626f6f70",
          "524086dcdd3fba9d571165a93e5bf91c",),
+        // FIPS-197 appendix C.2 and C.3
+        ("000102030405060708090a0b0c0d0e0f1011121314151617",
+         "00112233445566778899aabbccddeeff",
+         "dda97ca4864cdfe06eaf70a0ec0d7191",),
+        ("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+         "00112233445566778899aabbccddeeff",
+         "8ea2b7ca516745bfeafc49904b496089",),
     ];
 
     for &(key, plaintext, expected_ciphertext) in &encrypt_tests {
-        let key_schedule = expand_key(&hex_to_bytes(key));
+        let key_schedule = expand_key(&hex_to_bytes(key).unwrap()).unwrap();
         let ciphertext = encrypt_block(&key_schedule,
-                                       &hex_to_bytes(plaintext));
-        if ciphertext != hex_to_bytes(expected_ciphertext) {
+                                       &hex_to_bytes(plaintext).unwrap());
+        if ciphertext != hex_to_bytes(expected_ciphertext).unwrap() {
             panic!("FAILED: encrypt expected {} got {}",
                    expected_ciphertext, bytes_to_hex(&ciphertext));
         }
 
         let decrypted = decrypt_block(&key_schedule, &ciphertext);
-        if decrypted != hex_to_bytes(plaintext) {
+        if decrypted != hex_to_bytes(plaintext).unwrap() {
             panic!("FAILED: decrypt expected {} got {}",
                    plaintext, bytes_to_hex(&decrypted));
         }
     }
 
     let f = base64_decode_file("data/1.7.txt");
-    let cipher = AESCipherECB::new("YELLOW SUBMARINE".as_bytes());
-    let decrypted_bytes = cipher.decrypt_and_unpad(&f);
+    let cipher = AESCipherECB::new("YELLOW SUBMARINE".as_bytes()).unwrap();
+    let decrypted_bytes = cipher.decrypt_and_unpad(&f).unwrap();
     let decrypted = str::from_utf8(&decrypted_bytes).unwrap();
     println!("AES ECB decrypt 1.7.txt:\n{}----", decrypted);
 
@@ -503,9 +597,12 @@ pub fn aes_test(full_test: bool) {
     decrypt_aes_cbc_base64_file("data/2.10.txt",
                                 "YELLOW SUBMARINE".as_bytes(),
                                 &[0u8; 16]);
+    aes_cbc_key_sizes_test();
     distinguish_aes_cbc_ecb_test();
 
     decrypt_aes_ctr_test();
+    aes_gcm_test();
+    aes_stream_test();
 
     decrypt_aes_ecb_simple_test();
     if full_test {
@@ -0,0 +1,239 @@
+use std::io;
+use std::io::{Read, Write};
+
+use aes::AES_BLOCK_SIZE;
+use aes::cbc::AESCipherCBC;
+use aes::ctr::AESCipherCTR;
+use error::{Error, Result};
+use pkcs7::{pkcs7_pad, pkcs7_unpad_copy};
+use xor::slice_xor_inplace;
+
+/// Incremental version of AESCipher::encrypt/decrypt so we don't have to
+/// hold the whole thing in memory
+pub trait AESStream {
+    /// Feed in more input, returns whatever output is ready so far
+    fn update(&mut self, input: &[u8]) -> Vec<u8>;
+
+    /// Flush out the rest, e.g. padding. Don't call update after this.
+    fn finalize(&mut self) -> Result<Vec<u8>>;
+}
+
+pub struct AESCBCEncryptor<'a> {
+    cipher: &'a AESCipherCBC,
+    // last ciphertext block
+    iv: Vec<u8>,
+    pending: Vec<u8>,
+}
+
+pub struct AESCBCDecryptor<'a> {
+    cipher: &'a AESCipherCBC,
+    iv: Vec<u8>,
+    pending: Vec<u8>,
+}
+
+pub struct AESCTRStream<'a> {
+    cipher: &'a AESCipherCTR,
+    // bytes processed so far, tells us where we are in the keystream
+    offset: u64,
+}
+
+impl<'a> AESCBCEncryptor<'a> {
+    pub fn new(cipher: &'a AESCipherCBC,
+               iv: &[u8]) -> Result<AESCBCEncryptor<'a>> {
+        if iv.len() != AES_BLOCK_SIZE {
+            return Err(Error::BadIVLength(iv.len()));
+        }
+        Ok(AESCBCEncryptor {
+            cipher,
+            iv: iv.to_vec(),
+            pending: Vec::new(),
+        })
+    }
+
+    fn encrypt_blocks(&mut self, blocks: &[u8]) -> Vec<u8> {
+        let result = self.cipher.encrypt_iv(&self.iv, blocks);
+        if !result.is_empty() {
+            self.iv = result[result.len() - AES_BLOCK_SIZE..].to_vec();
+        }
+        result
+    }
+}
+
+impl<'a> AESStream for AESCBCEncryptor<'a> {
+    fn update(&mut self, input: &[u8]) -> Vec<u8> {
+        self.pending.extend_from_slice(input);
+        let full = self.pending.len() - (self.pending.len() % AES_BLOCK_SIZE);
+        let blocks: Vec<u8> = self.pending.drain(..full).collect();
+        self.encrypt_blocks(&blocks)
+    }
+
+    fn finalize(&mut self) -> Result<Vec<u8>> {
+        // pkcs7 always adds at least 1 byte so there's always a last block
+        let padded = pkcs7_pad(&self.pending, AES_BLOCK_SIZE);
+        self.pending.clear();
+        Ok(self.encrypt_blocks(&padded))
+    }
+}
+
+impl<'a> AESCBCDecryptor<'a> {
+    pub fn new(cipher: &'a AESCipherCBC,
+               iv: &[u8]) -> Result<AESCBCDecryptor<'a>> {
+        if iv.len() != AES_BLOCK_SIZE {
+            return Err(Error::BadIVLength(iv.len()));
+        }
+        Ok(AESCBCDecryptor {
+            cipher,
+            iv: iv.to_vec(),
+            pending: Vec::new(),
+        })
+    }
+}
+
+impl<'a> AESStream for AESCBCDecryptor<'a> {
+    fn update(&mut self, input: &[u8]) -> Vec<u8> {
+        self.pending.extend_from_slice(input);
+
+        // hold back the last full block since it has the padding and we
+        // don't know it's the last one until finalize
+        let mut full = self.pending.len() - (self.pending.len() %
+                                             AES_BLOCK_SIZE);
+        if full == self.pending.len() && full > 0 {
+            full -= AES_BLOCK_SIZE;
+        }
+        let blocks: Vec<u8> = self.pending.drain(..full).collect();
+        let result = self.cipher.decrypt_iv(&self.iv, &blocks);
+        if !blocks.is_empty() {
+            self.iv = blocks[blocks.len() - AES_BLOCK_SIZE..].to_vec();
+        }
+        result
+    }
+
+    fn finalize(&mut self) -> Result<Vec<u8>> {
+        if self.pending.len() != AES_BLOCK_SIZE {
+            return Err(Error::BadCiphertextLength(self.pending.len()));
+        }
+        let last = self.cipher.decrypt_iv(&self.iv, &self.pending);
+        self.pending.clear();
+        pkcs7_unpad_copy(&last, AES_BLOCK_SIZE)
+    }
+}
+
+impl<'a> AESCTRStream<'a> {
+    pub fn new(cipher: &'a AESCipherCTR) -> AESCTRStream<'a> {
+        AESCTRStream {
+            cipher,
+            offset: 0,
+        }
+    }
+}
+
+impl<'a> AESStream for AESCTRStream<'a> {
+    fn update(&mut self, input: &[u8]) -> Vec<u8> {
+        // encrypt and decrypt are the same, just xor with the keystream
+        let mut result = input.to_vec();
+        let mut done = 0;
+        while done < result.len() {
+            let block_index = self.offset / AES_BLOCK_SIZE as u64;
+            let rem = (self.offset % AES_BLOCK_SIZE as u64) as usize;
+            let keystream = self.cipher.keystream_block(block_index);
+
+            // might start or end in the middle of a block
+            let len = ::std::cmp::min(AES_BLOCK_SIZE - rem,
+                                      result.len() - done);
+            slice_xor_inplace(&mut result[done..done + len],
+                              &keystream[rem..rem + len]);
+            done += len;
+            self.offset += len as u64;
+        }
+        result
+    }
+
+    fn finalize(&mut self) -> Result<Vec<u8>> {
+        Ok(Vec::new())
+    }
+}
+
+/// Encrypts (or decrypts) everything written to it into the inner writer.
+/// Call finish() at the end or the final block never gets written.
+pub struct AESWriter<W: Write, S: AESStream> {
+    inner: W,
+    stream: S,
+}
+
+impl<W: Write, S: AESStream> AESWriter<W, S> {
+    pub fn new(inner: W, stream: S) -> AESWriter<W, S> {
+        AESWriter {
+            inner,
+            stream,
+        }
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        let last = self.stream.finalize().map_err(|e| {
+            io::Error::new(io::ErrorKind::InvalidData, e)
+        })?;
+        self.inner.write_all(&last)?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write, S: AESStream> Write for AESWriter<W, S> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let out = self.stream.update(buf);
+        self.inner.write_all(&out)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        // can't flush a partial block, just whatever's already out
+        self.inner.flush()
+    }
+}
+
+/// Reads from the inner reader and hands back the encrypted (or decrypted)
+/// bytes. Padding errors show up as InvalidData at the end.
+pub struct AESReader<R: Read, S: AESStream> {
+    inner: R,
+    stream: S,
+    ready: Vec<u8>,
+    finished: bool,
+}
+
+impl<R: Read, S: AESStream> AESReader<R, S> {
+    pub fn new(inner: R, stream: S) -> AESReader<R, S> {
+        AESReader {
+            inner,
+            stream,
+            ready: Vec::new(),
+            finished: false,
+        }
+    }
+}
+
+impl<R: Read, S: AESStream> Read for AESReader<R, S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut chunk = [0u8; 4096];
+
+        // keep pulling until there's something to hand back or we're done,
+        // cbc may eat a whole block before giving anything out
+        while self.ready.is_empty() && !self.finished {
+            let n = self.inner.read(&mut chunk)?;
+            if n == 0 {
+                self.finished = true;
+                let last = self.stream.finalize().map_err(|e| {
+                    io::Error::new(io::ErrorKind::InvalidData, e)
+                })?;
+                self.ready.extend(last);
+            } else {
+                let out = self.stream.update(&chunk[..n]);
+                self.ready.extend(out);
+            }
+        }
+
+        let len = ::std::cmp::min(buf.len(), self.ready.len());
+        buf[..len].copy_from_slice(&self.ready[..len]);
+        self.ready.drain(..len);
+        Ok(len)
+    }
+}
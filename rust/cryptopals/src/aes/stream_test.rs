@@ -0,0 +1,125 @@
+use std::io::{Cursor, Read, Write, copy};
+use std::str;
+
+use aes::{AESCipher, AES_BLOCK_SIZE};
+use aes::cbc::AESCipherCBC;
+use aes::ctr::AESCipherCTR;
+use aes::stream::{AESStream, AESCBCEncryptor, AESCBCDecryptor, AESReader,
+                  AESWriter};
+use base64::base64_decode_file;
+use error::{Error, Result};
+use util::{rand_key, rand_bytes, rand_u64, assert_slice_cmp};
+
+// feed the input in uneven pieces to hit the partial block cases
+fn stream_chunked(stream: &mut dyn AESStream, input: &[u8],
+                  chunk_len: usize) -> Result<Vec<u8>> {
+    let mut result = Vec::new();
+    for chunk in input.chunks(chunk_len) {
+        result.extend(stream.update(chunk));
+    }
+    result.extend(stream.finalize()?);
+    Ok(result)
+}
+
+fn cbc_stream_test() {
+    let key = rand_key();
+    let iv = rand_bytes(AES_BLOCK_SIZE);
+    println!("AES CBC stream test with key {:?} iv {:?}", key, iv);
+    let cipher = AESCipherCBC::new(&key, &iv).unwrap();
+
+    for &len in &[0, 1, 15, 16, 17, 31, 32, 100] {
+        let plaintext = rand_bytes(len);
+        let expected = cipher.pad_and_encrypt(&plaintext);
+        for &chunk_len in &[1, 5, 16, 33] {
+            let ciphertext = stream_chunked(&mut cipher.encryptor(),
+                                            &plaintext, chunk_len).unwrap();
+            assert_slice_cmp("cbc_stream_test encrypt", &expected,
+                             &ciphertext);
+
+            let decrypted = stream_chunked(&mut cipher.decryptor(),
+                                           &ciphertext, chunk_len).unwrap();
+            assert_slice_cmp("cbc_stream_test decrypt", &plaintext,
+                             &decrypted);
+        }
+    }
+
+    // bad padding and truncated ciphertext are errors at the end
+    let mut ciphertext =
+        cipher.pad_and_encrypt("beep boop meow meow meow".as_bytes());
+    let last = ciphertext.len() - AES_BLOCK_SIZE - 1;
+    ciphertext[last] ^= 0x42u8;
+    assert!(stream_chunked(&mut cipher.decryptor(),
+                           &ciphertext, 7).is_err(),
+            "cbc_stream_test accepted bad padding");
+    assert!(stream_chunked(&mut cipher.decryptor(),
+                           &ciphertext[..20], 7).is_err(),
+            "cbc_stream_test accepted truncated ciphertext");
+
+    // an iv that isn't a block long is an error up front
+    let short_iv = &iv[..AES_BLOCK_SIZE - 1];
+    assert_eq!(AESCBCEncryptor::new(&cipher, short_iv).err(),
+               Some(Error::BadIVLength(AES_BLOCK_SIZE - 1)));
+    assert_eq!(AESCBCDecryptor::new(&cipher, short_iv).err(),
+               Some(Error::BadIVLength(AES_BLOCK_SIZE - 1)));
+}
+
+fn ctr_stream_test() {
+    let key = rand_key();
+    let nonce = rand_u64();
+    println!("AES CTR stream test with key {:?} nonce {}", key, nonce);
+    let cipher = AESCipherCTR::new(&key, nonce).unwrap();
+
+    let plaintext = rand_bytes(100);
+    let expected = cipher.encrypt(&plaintext);
+    for &chunk_len in &[1, 5, 16, 33] {
+        let ciphertext = stream_chunked(&mut cipher.stream(), &plaintext,
+                                        chunk_len).unwrap();
+        assert_slice_cmp("ctr_stream_test encrypt", &expected, &ciphertext);
+
+        let decrypted = stream_chunked(&mut cipher.stream(), &ciphertext,
+                                       chunk_len).unwrap();
+        assert_slice_cmp("ctr_stream_test decrypt", &plaintext, &decrypted);
+    }
+}
+
+fn stream_io_test() {
+    // decrypt 2.10.txt by piping through a reader
+    let f = base64_decode_file("data/2.10.txt");
+    let cipher = AESCipherCBC::new("YELLOW SUBMARINE".as_bytes(),
+                                   &[0u8; 16]).unwrap();
+    let expected = cipher.decrypt_and_unpad(&f).unwrap();
+
+    let mut reader = AESReader::new(Cursor::new(f.clone()),
+                                    cipher.decryptor());
+    let mut decrypted = Vec::new();
+    reader.read_to_end(&mut decrypted).unwrap();
+    assert!(str::from_utf8(&decrypted).is_ok());
+    assert_slice_cmp("stream_io_test reader", &expected, &decrypted);
+
+    // and back out through a writer
+    let mut writer = AESWriter::new(Vec::new(), cipher.encryptor());
+    copy(&mut Cursor::new(decrypted.clone()), &mut writer).unwrap();
+    let encrypted = writer.finish().unwrap();
+    assert_slice_cmp("stream_io_test writer", &f, &encrypted);
+
+    // ctr goes both ways with the same stream
+    let ctr = AESCipherCTR::new(&rand_key(), rand_u64()).unwrap();
+    let mut writer = AESWriter::new(Vec::new(), ctr.stream());
+    writer.write_all(&decrypted[..77]).unwrap();
+    writer.write_all(&decrypted[77..]).unwrap();
+    let encrypted = writer.finish().unwrap();
+    assert_slice_cmp("stream_io_test ctr writer", &ctr.encrypt(&decrypted),
+                     &encrypted);
+
+    let mut reader = AESReader::new(Cursor::new(encrypted), ctr.stream());
+    let mut roundtrip = Vec::new();
+    reader.read_to_end(&mut roundtrip).unwrap();
+    assert_slice_cmp("stream_io_test ctr reader", &decrypted, &roundtrip);
+}
+
+pub fn aes_stream_test() {
+    cbc_stream_test();
+    ctr_stream_test();
+    stream_io_test();
+    println!("Finished AES stream tests");
+}
@@ -0,0 +1,228 @@
+extern crate gmp;
+
+use self::gmp::mpz::Mpz;
+use asn1::{TagType, SEQUENCE};
+use error::{Error, Result};
+use util::{bytes_to_mpz, mpz_bytes, mpz_bytes_zero_pad};
+
+/// DER length octets: short form under 128, otherwise 0x80 | the number
+/// of big endian length bytes that follow
+pub fn encode_length(len: usize) -> Vec<u8> {
+    if len < 0x80 {
+        return vec!(len as u8);
+    }
+    let bytes = len.to_be_bytes();
+    let skip = bytes.iter().take_while(|&&b| b == 0).count();
+    let mut res = vec!(0x80u8 | (bytes.len() - skip) as u8);
+    res.extend_from_slice(&bytes[skip..]);
+    res
+}
+
+pub fn encode_tlv(tag: u8, contents: &[u8]) -> Vec<u8> {
+    let mut res = vec!(tag);
+    res.extend(encode_length(contents.len()));
+    res.extend_from_slice(contents);
+    res
+}
+
+/// Two's complement, as few bytes as possible
+pub fn encode_integer(num: &Mpz) -> Vec<u8> {
+    let contents = if *num >= Mpz::zero() {
+        let mut bytes = mpz_bytes(num);
+        if bytes[0] & 0x80 != 0 {
+            bytes.insert(0, 0u8);
+        }
+        bytes
+    } else {
+        // smallest len where num >= -2^(8 len - 1), then 2^(8 len) + num
+        let mut len = 1;
+        while *num < -(Mpz::one() << (8 * len - 1)) {
+            len += 1;
+        }
+        mpz_bytes_zero_pad(&((Mpz::one() << (8 * len)) + num), len)
+    };
+    encode_tlv(TagType::Integer as u8, &contents)
+}
+
+pub fn encode_null() -> Vec<u8> {
+    encode_tlv(TagType::Null as u8, &[])
+}
+
+/// oid is the already encoded contents, e.g. asn1::SHA1_OID
+pub fn encode_oid(oid: &[u8]) -> Vec<u8> {
+    encode_tlv(TagType::OID as u8, oid)
+}
+
+pub fn encode_octet_string(bytes: &[u8]) -> Vec<u8> {
+    encode_tlv(TagType::OctetString as u8, bytes)
+}
+
+/// Only whole bytes, so the unused bits count is always 0
+pub fn encode_bit_string(bytes: &[u8]) -> Vec<u8> {
+    let mut contents = vec!(0u8);
+    contents.extend_from_slice(bytes);
+    encode_tlv(TagType::BitString as u8, &contents)
+}
+
+/// items are already encoded
+pub fn encode_sequence(items: &[Vec<u8>]) -> Vec<u8> {
+    encode_tlv(SEQUENCE, &items.concat())
+}
+
+/// Contents of an INTEGER back to a number, checking it's minimal
+pub fn decode_integer(contents: &[u8]) -> Result<Mpz> {
+    if contents.is_empty() {
+        return Err(Error::InvalidDer("empty integer"));
+    }
+    if contents.len() > 1 &&
+       ((contents[0] == 0 && contents[1] & 0x80 == 0) ||
+        (contents[0] == 0xff && contents[1] & 0x80 != 0)) {
+        return Err(Error::InvalidDer("integer not minimal"));
+    }
+
+    let num = bytes_to_mpz(contents);
+    if contents[0] & 0x80 != 0 {
+        Ok(num - (Mpz::one() << (8 * contents.len())))
+    } else {
+        Ok(num)
+    }
+}
+
+/// Pulls DER values off the front of a buffer one at a time. Sequences
+/// come back as another reader over their contents.
+pub struct DerReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> DerReader<'a> {
+    pub fn new(data: &'a [u8]) -> DerReader<'a> {
+        DerReader { data }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Errors if there's anything left over
+    pub fn finish(&self) -> Result<()> {
+        if self.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::InvalidDer("trailing data"))
+        }
+    }
+
+    pub fn peek_tag(&self) -> Option<u8> {
+        self.data.first().cloned()
+    }
+
+    fn read_length(&mut self) -> Result<usize> {
+        let first = match self.data.first() {
+            Some(&b) => b,
+            None => return Err(Error::InvalidDer("missing length")),
+        };
+        self.data = &self.data[1..];
+        if first < 0x80 {
+            return Ok(first as usize);
+        }
+
+        let num_bytes = (first & 0x7f) as usize;
+        if num_bytes == 0 {
+            return Err(Error::InvalidDer("indefinite length"));
+        }
+        if num_bytes > self.data.len() {
+            return Err(Error::InvalidDer("truncated length"));
+        }
+        if num_bytes > (usize::BITS / 8) as usize {
+            return Err(Error::InvalidDer("length too long"));
+        }
+        if self.data[0] == 0 {
+            return Err(Error::InvalidDer("length not minimal"));
+        }
+        let mut len = 0usize;
+        for &b in &self.data[..num_bytes] {
+            len = (len << 8) | b as usize;
+        }
+        self.data = &self.data[num_bytes..];
+        if len < 0x80 {
+            return Err(Error::InvalidDer("length should be short form"));
+        }
+        Ok(len)
+    }
+
+    /// (tag, contents) of the next value
+    pub fn read_tlv(&mut self) -> Result<(u8, &'a [u8])> {
+        let tag = match self.peek_tag() {
+            Some(tag) => tag,
+            None => return Err(Error::InvalidDer("unexpected end")),
+        };
+        if tag & 0x1f == 0x1f {
+            return Err(Error::InvalidDer("high tag numbers not supported"));
+        }
+        self.data = &self.data[1..];
+
+        let len = self.read_length()?;
+        if len > self.data.len() {
+            return Err(Error::InvalidDer("contents past the end"));
+        }
+        let (contents, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok((tag, contents))
+    }
+
+    /// The whole encoding of the next value, tag and length included, e.g.
+    /// for the part of a certificate that gets signed
+    pub fn read_raw(&mut self) -> Result<&'a [u8]> {
+        let start = self.data;
+        self.read_tlv()?;
+        Ok(&start[..start.len() - self.data.len()])
+    }
+
+    /// Contents of the next value, which has to have this tag
+    pub fn expect(&mut self, tag: u8) -> Result<&'a [u8]> {
+        let (found, contents) = self.read_tlv()?;
+        if found != tag {
+            return Err(Error::InvalidDer("unexpected tag"));
+        }
+        Ok(contents)
+    }
+
+    pub fn read_integer(&mut self) -> Result<Mpz> {
+        decode_integer(self.expect(TagType::Integer as u8)?)
+    }
+
+    pub fn read_null(&mut self) -> Result<()> {
+        let contents = self.expect(TagType::Null as u8)?;
+        if !contents.is_empty() {
+            return Err(Error::InvalidDer("null with contents"));
+        }
+        Ok(())
+    }
+
+    /// The encoded oid, compare it against e.g. asn1::SHA1_OID
+    pub fn read_oid(&mut self) -> Result<&'a [u8]> {
+        let contents = self.expect(TagType::OID as u8)?;
+        if contents.is_empty() {
+            return Err(Error::InvalidDer("empty oid"));
+        }
+        Ok(contents)
+    }
+
+    pub fn read_octet_string(&mut self) -> Result<&'a [u8]> {
+        self.expect(TagType::OctetString as u8)
+    }
+
+    /// Only bit strings made of whole bytes
+    pub fn read_bit_string(&mut self) -> Result<&'a [u8]> {
+        let contents = self.expect(TagType::BitString as u8)?;
+        match contents.first() {
+            Some(&0) => Ok(&contents[1..]),
+            Some(_) => Err(Error::InvalidDer("partial byte bit string")),
+            None => Err(Error::InvalidDer("empty bit string")),
+        }
+    }
+
+    pub fn read_sequence(&mut self) -> Result<DerReader<'a>> {
+        Ok(DerReader::new(self.expect(SEQUENCE)?))
+    }
+}
@@ -1,20 +1,59 @@
+pub mod der;
+pub mod test;
+pub mod value;
+
+use sha1;
+use sha2;
+
 // rust enums have to be type isize, not u8
 pub enum TagType {
-    Integer =       0x02,
-    BitString =     0x03,
-    OctetString =   0x04,
-    Null =          0x05,
-    OID =           0x06,
-    Sequence =      0x10,
+    Boolean =           0x01,
+    Integer =           0x02,
+    BitString =         0x03,
+    OctetString =       0x04,
+    Null =              0x05,
+    OID =               0x06,
+    Utf8String =        0x0c,
+    Sequence =          0x10,
+    Set =               0x11,
+    PrintableString =   0x13,
+    IA5String =         0x16,
+    UtcTime =           0x17,
+    GeneralizedTime =   0x18,
 }
 
+// or'd into the tag for types built out of other types, e.g. sequences
+pub const CONSTRUCTED: u8 = 0x20;
+// class bits for [0], [1] etc
+pub const CONTEXT_SPECIFIC: u8 = 0x80;
+
+pub const SEQUENCE: u8 = TagType::Sequence as u8 | CONSTRUCTED;
+pub const SET: u8 = TagType::Set as u8 | CONSTRUCTED;
+
 pub const SHA1_OID: [u8; 5] = [
     0x2bu8, 0x0eu8, 0x03u8, 0x02u8, 0x1au8,
 ];
 
+// 1.2.840.113549.1.1.1
+pub const RSA_ENCRYPTION_OID: [u8; 9] = [
+    0x2au8, 0x86u8, 0x48u8, 0x86u8, 0xf7u8, 0x0du8, 0x01u8, 0x01u8, 0x01u8,
+];
+
+// 2.16.840.1.101.3.4.2.{1,2,3}
+pub const SHA256_OID: [u8; 9] = [
+    0x60u8, 0x86u8, 0x48u8, 0x01u8, 0x65u8, 0x03u8, 0x04u8, 0x02u8, 0x01u8,
+];
+pub const SHA384_OID: [u8; 9] = [
+    0x60u8, 0x86u8, 0x48u8, 0x01u8, 0x65u8, 0x03u8, 0x04u8, 0x02u8, 0x02u8,
+];
+pub const SHA512_OID: [u8; 9] = [
+    0x60u8, 0x86u8, 0x48u8, 0x01u8, 0x65u8, 0x03u8, 0x04u8, 0x02u8, 0x03u8,
+];
+
+// DigestInfo up to the hash itself, per RFC 8017 section 9.2 note 1
 pub const PKCS1V15_SHA1_DIGEST_PREFIX: [u8; 15] = [
-    TagType::Sequence as u8, 33u8,
-        TagType::Sequence as u8, 9u8,
+    SEQUENCE, 33u8,
+        SEQUENCE, 9u8,
             // sha1 oid
             TagType::OID as u8, 5u8,
                 SHA1_OID[0], SHA1_OID[1], SHA1_OID[2],
@@ -24,3 +63,75 @@ pub const PKCS1V15_SHA1_DIGEST_PREFIX: [u8; 15] = [
         // sha1 is 20 bytes
         TagType::OctetString as u8, 20u8,
 ];
+
+pub const PKCS1V15_SHA256_DIGEST_PREFIX: [u8; 19] = [
+    SEQUENCE, 49u8,
+        SEQUENCE, 13u8,
+            TagType::OID as u8, 9u8,
+                SHA256_OID[0], SHA256_OID[1], SHA256_OID[2],
+                SHA256_OID[3], SHA256_OID[4], SHA256_OID[5],
+                SHA256_OID[6], SHA256_OID[7], SHA256_OID[8],
+            TagType::Null as u8, 0u8,
+        TagType::OctetString as u8, 32u8,
+];
+
+pub const PKCS1V15_SHA384_DIGEST_PREFIX: [u8; 19] = [
+    SEQUENCE, 65u8,
+        SEQUENCE, 13u8,
+            TagType::OID as u8, 9u8,
+                SHA384_OID[0], SHA384_OID[1], SHA384_OID[2],
+                SHA384_OID[3], SHA384_OID[4], SHA384_OID[5],
+                SHA384_OID[6], SHA384_OID[7], SHA384_OID[8],
+            TagType::Null as u8, 0u8,
+        TagType::OctetString as u8, 48u8,
+];
+
+pub const PKCS1V15_SHA512_DIGEST_PREFIX: [u8; 19] = [
+    SEQUENCE, 81u8,
+        SEQUENCE, 13u8,
+            TagType::OID as u8, 9u8,
+                SHA512_OID[0], SHA512_OID[1], SHA512_OID[2],
+                SHA512_OID[3], SHA512_OID[4], SHA512_OID[5],
+                SHA512_OID[6], SHA512_OID[7], SHA512_OID[8],
+            TagType::Null as u8, 0u8,
+        TagType::OctetString as u8, 64u8,
+];
+
+/// Hashes that can go in a PKCS#1 v1.5 signature
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DigestAlgorithm {
+    Sha1,
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+impl DigestAlgorithm {
+    /// The DigestInfo bytes that go in front of the hash
+    pub fn digest_info_prefix(&self) -> &[u8] {
+        match *self {
+            DigestAlgorithm::Sha1 => &PKCS1V15_SHA1_DIGEST_PREFIX,
+            DigestAlgorithm::Sha256 => &PKCS1V15_SHA256_DIGEST_PREFIX,
+            DigestAlgorithm::Sha384 => &PKCS1V15_SHA384_DIGEST_PREFIX,
+            DigestAlgorithm::Sha512 => &PKCS1V15_SHA512_DIGEST_PREFIX,
+        }
+    }
+
+    pub fn digest_length(&self) -> usize {
+        match *self {
+            DigestAlgorithm::Sha1 => sha1::DIGEST_LENGTH,
+            DigestAlgorithm::Sha256 => sha2::SHA256_DIGEST_LENGTH,
+            DigestAlgorithm::Sha384 => sha2::SHA384_DIGEST_LENGTH,
+            DigestAlgorithm::Sha512 => sha2::SHA512_DIGEST_LENGTH,
+        }
+    }
+
+    pub fn digest(&self, msg: &[u8]) -> Vec<u8> {
+        match *self {
+            DigestAlgorithm::Sha1 => sha1::digest(msg).to_vec(),
+            DigestAlgorithm::Sha256 => sha2::sha256(msg).to_vec(),
+            DigestAlgorithm::Sha384 => sha2::sha384(msg).to_vec(),
+            DigestAlgorithm::Sha512 => sha2::sha512(msg).to_vec(),
+        }
+    }
+}
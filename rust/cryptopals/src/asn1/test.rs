@@ -0,0 +1,316 @@
+extern crate gmp;
+
+use self::gmp::mpz::Mpz;
+use asn1::{DigestAlgorithm, SHA1_OID, SHA256_OID, RSA_ENCRYPTION_OID};
+use asn1::value::{Limits, Oid, Time, Value};
+use error::Error;
+use hex::{bytes_to_hex, hex_to_bytes};
+use pem::pem_decode;
+use util::{rand_bytes, rand_bytes_range, read_fixture};
+
+fn oid_test() {
+    let tests: [(&str, &[u8]); 4] = [
+        ("1.3.14.3.2.26", &SHA1_OID),
+        ("2.16.840.1.101.3.4.2.1", &SHA256_OID),
+        ("1.2.840.113549.1.1.1", &RSA_ENCRYPTION_OID),
+        // first byte of 2.x can be over 127
+        ("2.999.3", &[0x88, 0x37, 0x03]),
+    ];
+    for &(dotted, encoded) in &tests {
+        let oid: Oid = dotted.parse().unwrap();
+        assert_eq!(oid.to_string(), dotted);
+        assert_eq!(oid.to_der_contents(), encoded, "oid {}", dotted);
+        assert_eq!(Oid::from_der_contents(encoded).unwrap(), oid);
+    }
+
+    let big = "1.2.18446744073709551615";
+    let oid: Oid = big.parse().unwrap();
+    assert_eq!(Oid::from_der_contents(&oid.to_der_contents()).unwrap()
+               .to_string(), big);
+
+    for bad in &["", "1", "3.1", "1.40", "1..2", "1.+2", "1.2.", "a.b",
+                 "1.2.18446744073709551616"] {
+        assert!(bad.parse::<Oid>().is_err(), "parsed oid {:?}", bad);
+    }
+    // leading 0x80 isn't minimal, and the last byte can't have the high bit
+    for bad in &[&[0x2a, 0x80, 0x01][..], &[0x2a, 0x86][..], &[][..],
+                 &[0x2a, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+                   0xff, 0x7f][..]] {
+        assert!(Oid::from_der_contents(bad).is_err(), "parsed oid {:?}", bad);
+    }
+}
+
+fn integer_test() {
+    let tests = [
+        (0i64, "020100"),
+        (1, "020101"),
+        (127, "02017f"),
+        (128, "02020080"),
+        (255, "020200ff"),
+        (256, "02020100"),
+        (-1, "0201ff"),
+        (-128, "020180"),
+        (-129, "0202ff7f"),
+        (-256, "0202ff00"),
+        (-257, "0202feff"),
+    ];
+    for &(n, der) in &tests {
+        let value = Value::Integer(Mpz::from(n));
+        assert_eq!(bytes_to_hex(&value.to_der().unwrap()), der,
+                   "encoding {}", n);
+        assert_eq!(Value::from_der(&hex_to_bytes(der).unwrap()).unwrap(),
+                   value, "decoding {}", der);
+    }
+
+    let big = Mpz::one() << 1000;
+    for n in &[big.clone(), -big.clone(), &big - Mpz::one(),
+               -(&big) + Mpz::one()] {
+        let value = Value::Integer(n.clone());
+        assert_eq!(Value::from_der(&value.to_der().unwrap()).unwrap(), value);
+    }
+
+    for bad in &["0200", "02020001", "0202ff80", "020300007f"] {
+        assert!(Value::from_der(&hex_to_bytes(bad).unwrap()).is_err(),
+                "parsed integer {}", bad);
+    }
+}
+
+fn length_test() {
+    let value = Value::OctetString(vec!(0x42u8; 300));
+    let der = value.to_der().unwrap();
+    assert_eq!(&der[..4], &[0x04, 0x82, 0x01, 0x2c]);
+    assert_eq!(Value::from_der(&der).unwrap(), value);
+
+    // short form that should be long, long form that should be short,
+    // leading zero, indefinite, truncated
+    for bad in &["048105", "0481050102030405", "04820005", "0480", "0482",
+                 "04050102", "30"] {
+        assert!(Value::from_der(&hex_to_bytes(bad).unwrap()).is_err(),
+                "parsed {}", bad);
+    }
+}
+
+fn time_test() {
+    let tests = [
+        ("170d3439313233313233353935395a", "2049-12-31 23:59:59 UTC"),
+        ("170d3530303130313030303030305a", "1950-01-01 00:00:00 UTC"),
+        ("180f32303234303232393132333435365a", "2024-02-29 12:34:56 UTC"),
+    ];
+    for &(der, display) in &tests {
+        let der = hex_to_bytes(der).unwrap();
+        let value = Value::from_der(&der).unwrap();
+        assert_eq!(value.as_time().unwrap().to_string(), display);
+        assert_eq!(value.to_der().unwrap(), der);
+    }
+
+    let early = Time::new(2049, 12, 31, 23, 59, 59).unwrap();
+    let late = Time::new(2050, 1, 1, 0, 0, 0).unwrap();
+    assert!(early < late);
+    assert!(Time::new(2023, 2, 29, 0, 0, 0).is_err());
+    assert!(Time::new(2000, 2, 29, 0, 0, 0).is_ok());
+    assert!(Time::new(1900, 2, 29, 0, 0, 0).is_err());
+    assert!(Time::new(2024, 4, 31, 0, 0, 0).is_err());
+    assert!(Time::new(2024, 1, 1, 24, 0, 0).is_err());
+
+    // no seconds, time zones and fractions aren't DER
+    for bad in &["2310220101010101305a", "170b323430313031303030305a",
+                 "17113234303130313030303030302b30313030",
+                 "1811323032343031303130303030302e355a",
+                 "170d3234313330313030303030305a"] {
+        assert!(Value::from_der(&hex_to_bytes(bad).unwrap()).is_err(),
+                "parsed time {}", bad);
+    }
+}
+
+fn string_test() {
+    let tests = [
+        Value::Utf8String("beep boop 🐱".to_string()),
+        Value::PrintableString("Beep Boop (meow)".to_string()),
+        Value::IA5String("beep@boop.meow".to_string()),
+    ];
+    for value in &tests {
+        assert_eq!(Value::from_der(&value.to_der().unwrap()).unwrap(), *value);
+    }
+
+    let bad_printable = Value::PrintableString("beep@boop".to_string());
+    assert!(Value::from_der(&bad_printable.to_der().unwrap()).is_err());
+    assert!(Value::from_der(&[0x0c, 0x02, 0xc3, 0x28]).is_err());
+    assert!(Value::from_der(&[0x16, 0x01, 0x80]).is_err());
+}
+
+fn round_trip_test() {
+    let value = Value::Sequence(vec!(
+        Value::Boolean(true),
+        Value::Boolean(false),
+        Value::Integer(Mpz::from(65537u64)),
+        Value::BitString { unused_bits: 3, bytes: vec!(0x01, 0xf8) },
+        Value::OctetString(rand_bytes(20)),
+        Value::Null,
+        Value::Oid("1.2.840.113549.1.1.11".parse().unwrap()),
+        Value::PrintableString("US".to_string()),
+        Value::UtcTime(Time::new(2024, 1, 2, 3, 4, 5).unwrap()),
+        Value::GeneralizedTime(Time::new(2050, 6, 7, 8, 9, 10).unwrap()),
+        Value::Set(vec!(Value::Integer(Mpz::from(1u64)),
+                        Value::Integer(Mpz::from(2u64)))),
+        Value::explicit(0, &Value::Integer(Mpz::from(2u64))).unwrap(),
+        Value::ContextSpecific {
+            number: 2,
+            constructed: false,
+            contents: "example.com".as_bytes().to_vec(),
+        },
+        Value::Sequence(Vec::new()),
+    ));
+    let der = value.to_der().unwrap();
+    let parsed = Value::from_der(&der).unwrap();
+    assert_eq!(parsed, value);
+    assert_eq!(parsed.to_der().unwrap(), der);
+
+    match parsed.as_values().unwrap()[11] {
+        Value::ContextSpecific { number: 0, constructed: true,
+                                 ref contents } => {
+            assert_eq!(Value::parse_all(contents, &Limits::default()).unwrap(),
+                       vec!(Value::Integer(Mpz::from(2u64))));
+        },
+        ref other => panic!("expected [0] got {:?}", other),
+    };
+
+    // sets come out sorted, and have to go in that way
+    let set = Value::Set(vec!(Value::Integer(Mpz::from(2u64)),
+                              Value::Integer(Mpz::from(1u64))));
+    let der = set.to_der().unwrap();
+    assert_eq!(bytes_to_hex(&der), "3106020101020102");
+    assert!(Value::from_der(&hex_to_bytes("3106020102020101").unwrap())
+            .is_err());
+
+    // unknown tags come through untouched
+    let der = hex_to_bytes("1e0400680069").unwrap();
+    assert_eq!(Value::from_der(&der).unwrap().to_der().unwrap(), der);
+
+    // [31] and up need the high tag number form, which we don't do
+    let high = Value::ContextSpecific {
+        number: 31,
+        constructed: false,
+        contents: Vec::new(),
+    };
+    let unsupported = Error::InvalidDer("high tag numbers not supported");
+    assert_eq!(high.to_der(), Err(unsupported.clone()));
+    assert_eq!(Value::explicit(40, &Value::Null), Err(unsupported.clone()));
+    assert_eq!(Value::Other { tag: 0x1f, contents: Vec::new() }.to_der(),
+               Err(unsupported));
+
+    // bad booleans and bit string padding
+    for bad in &["010101", "01020000", "030201ff", "030108", "030101"] {
+        assert!(Value::from_der(&hex_to_bytes(bad).unwrap()).is_err(),
+                "parsed {}", bad);
+    }
+}
+
+// the hand rolled DigestInfo prefixes should be what the encoder makes
+fn digest_info_test() {
+    let algs = [
+        (DigestAlgorithm::Sha1, "1.3.14.3.2.26"),
+        (DigestAlgorithm::Sha256, "2.16.840.1.101.3.4.2.1"),
+        (DigestAlgorithm::Sha384, "2.16.840.1.101.3.4.2.2"),
+        (DigestAlgorithm::Sha512, "2.16.840.1.101.3.4.2.3"),
+    ];
+    for &(alg, oid) in &algs {
+        let digest = alg.digest("beep boop".as_bytes());
+        let digest_info = Value::Sequence(vec!(
+            Value::Sequence(vec!(Value::Oid(oid.parse().unwrap()),
+                                 Value::Null)),
+            Value::OctetString(digest.clone()),
+        ));
+        let mut expected = alg.digest_info_prefix().to_vec();
+        expected.extend(digest);
+        assert_eq!(digest_info.to_der().unwrap(), expected,
+                   "{:?} DigestInfo", alg);
+    }
+}
+
+fn openssl_round_trip_test() {
+    let fixtures = [
+        ("data/rsa1024_pkcs8.pem", "PRIVATE KEY"),
+        ("data/rsa1024_pkcs1.pem", "RSA PRIVATE KEY"),
+        ("data/rsa1024_pub.pem", "PUBLIC KEY"),
+        ("data/rsa1024_pkcs1_pub.pem", "RSA PUBLIC KEY"),
+    ];
+    for &(filename, label) in &fixtures {
+        let der = pem_decode(label, &read_fixture(filename)).unwrap();
+        let value = Value::from_der(&der).unwrap();
+        assert_eq!(value.to_der().unwrap(), der, "{} round trip", filename);
+    }
+}
+
+fn limits_test() {
+    let mut value = Value::Null;
+    for _ in 0..40 {
+        value = Value::Sequence(vec!(value));
+    }
+    let der = value.to_der().unwrap();
+    assert_eq!(Value::from_der(&der),
+               Err(Error::InvalidDer("nested too deep")));
+    let deep = Limits { max_depth: 64, max_len: 1 << 20 };
+    assert_eq!(Value::from_der_with_limits(&der, &deep).unwrap(), value);
+    assert_eq!(Value::parse_all(&der, &Limits::default()),
+               Err(Error::InvalidDer("nested too deep")));
+    assert_eq!(Value::parse_all(&der, &deep).unwrap(), vec!(value));
+
+    // nesting inside [n] counts too
+    let mut value = Value::Null;
+    for i in 0..40 {
+        value = Value::explicit((i % 31) as u8, &value).unwrap();
+    }
+    assert!(Value::from_der(&value.to_der().unwrap()).is_err());
+
+    let big = Value::OctetString(vec!(0u8; 1000)).to_der().unwrap();
+    let small = Limits { max_depth: 32, max_len: 100 };
+    assert_eq!(Value::from_der_with_limits(&big, &small),
+               Err(Error::InvalidDer("input too long")));
+    assert_eq!(Value::parse_all(&big, &small),
+               Err(Error::InvalidDer("input too long")));
+}
+
+// chopped up and corrupted input should only ever give errors
+fn garbage_test() {
+    let value = Value::Sequence(vec!(
+        Value::Integer(Mpz::from(12345u64)),
+        Value::Oid("1.2.840.113549.1.1.1".parse().unwrap()),
+        Value::explicit(3, &Value::Sequence(vec!(
+            Value::Boolean(true),
+            Value::UtcTime(Time::new(2024, 1, 2, 3, 4, 5).unwrap())))).unwrap(),
+        Value::Set(vec!(Value::PrintableString("beep".to_string()))),
+    ));
+    let der = value.to_der().unwrap();
+    for len in 0..der.len() {
+        assert!(Value::from_der(&der[..len]).is_err(),
+                "parsed truncated der at {} bytes", len);
+    }
+
+    for _ in 0..2000 {
+        let mut mangled = der.clone();
+        let flips = 1 + rand_bytes(1)[0] as usize % 3;
+        for _ in 0..flips {
+            let i = rand_bytes(1)[0] as usize % mangled.len();
+            mangled[i] = rand_bytes(1)[0];
+        }
+        // anything that does parse should re-encode to the same bytes
+        if let Ok(parsed) = Value::from_der(&mangled) {
+            assert_eq!(parsed.to_der().unwrap(), mangled);
+        }
+        let _ = Value::from_der(&rand_bytes_range(0, 64));
+    }
+}
+
+pub fn asn1_test() {
+    oid_test();
+    integer_test();
+    length_test();
+    time_test();
+    string_test();
+    round_trip_test();
+    digest_info_test();
+    openssl_round_trip_test();
+    limits_test();
+    garbage_test();
+    println!("Finished ASN.1 tests");
+}
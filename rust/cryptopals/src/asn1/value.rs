@@ -0,0 +1,519 @@
+extern crate gmp;
+
+use std::fmt;
+use std::str::{self, FromStr};
+
+use self::gmp::mpz::Mpz;
+use asn1::{TagType, CONSTRUCTED, CONTEXT_SPECIFIC, SEQUENCE, SET};
+use asn1::der::{DerReader, encode_tlv, encode_integer, decode_integer};
+use error::{Error, Result};
+
+/// An object identifier as its arcs, e.g. [1, 2, 840, 113549, 1, 1, 1]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Oid {
+    arcs: Vec<u64>,
+}
+
+impl Oid {
+    /// The first arc has to be 0, 1 or 2, and the second under 40 unless
+    /// the first is 2, so the two fit in one number
+    pub fn new(arcs: &[u64]) -> Result<Oid> {
+        if arcs.len() < 2 || arcs[0] > 2 || (arcs[0] < 2 && arcs[1] >= 40) ||
+           arcs[1] > u64::MAX - 80 {
+            return Err(Error::InvalidDer("bad oid arcs"));
+        }
+        Ok(Oid { arcs: arcs.to_vec() })
+    }
+
+    pub fn arcs(&self) -> &[u64] {
+        &self.arcs
+    }
+
+    /// What goes inside the OBJECT IDENTIFIER, the same format as
+    /// asn1::SHA1_OID and friends
+    pub fn to_der_contents(&self) -> Vec<u8> {
+        let mut res = Vec::new();
+        let first = self.arcs[0] * 40 + self.arcs[1];
+        for &arc in [first].iter().chain(&self.arcs[2..]) {
+            // base 128, high bit set on all but the last byte
+            let mut bytes = vec!((arc & 0x7f) as u8);
+            let mut rest = arc >> 7;
+            while rest > 0 {
+                bytes.push(0x80 | (rest & 0x7f) as u8);
+                rest >>= 7;
+            }
+            bytes.reverse();
+            res.extend(bytes);
+        }
+        res
+    }
+
+    pub fn from_der_contents(contents: &[u8]) -> Result<Oid> {
+        if contents.is_empty() {
+            return Err(Error::InvalidDer("empty oid"));
+        }
+
+        let mut nums = Vec::new();
+        let mut num = 0u64;
+        let mut start = true;
+        for &b in contents {
+            if start && b == 0x80 {
+                return Err(Error::InvalidDer("oid arc not minimal"));
+            }
+            if num > u64::MAX >> 7 {
+                return Err(Error::InvalidDer("oid arc too big"));
+            }
+            num = (num << 7) | (b & 0x7f) as u64;
+            start = b & 0x80 == 0;
+            if start {
+                nums.push(num);
+                num = 0;
+            }
+        }
+        if !start {
+            return Err(Error::InvalidDer("truncated oid"));
+        }
+
+        let mut arcs = match nums[0] {
+            n if n < 40 => vec!(0, n),
+            n if n < 80 => vec!(1, n - 40),
+            n => vec!(2, n - 80),
+        };
+        arcs.extend_from_slice(&nums[1..]);
+        Ok(Oid { arcs })
+    }
+}
+
+impl FromStr for Oid {
+    type Err = Error;
+
+    /// Dotted form, e.g. "1.2.840.113549.1.1.1"
+    fn from_str(s: &str) -> Result<Oid> {
+        let mut arcs = Vec::new();
+        for arc in s.split('.') {
+            match arc.parse::<u64>() {
+                // parse takes "+1", we don't
+                Ok(n) if arc.bytes().all(|b| b.is_ascii_digit()) => {
+                    arcs.push(n)
+                },
+                _ => return Err(Error::InvalidDer("bad dotted oid")),
+            }
+        }
+        Oid::new(&arcs)
+    }
+}
+
+impl fmt::Display for Oid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let arcs: Vec<String> = self.arcs.iter().map(|a| a.to_string())
+                                              .collect();
+        write!(f, "{}", arcs.join("."))
+    }
+}
+
+/// A UTCTime or GeneralizedTime, always in UTC to the second since that's
+/// all DER allows. Orders chronologically.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Time {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+fn days_in_month(year: u16, month: u8) -> u8 {
+    match month {
+        2 if year.is_multiple_of(4) &&
+             (!year.is_multiple_of(100) || year.is_multiple_of(400)) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// two digit fields out of a time string
+fn parse_digits(digits: &[u8]) -> Result<u16> {
+    let mut num = 0u16;
+    for &d in digits {
+        if !d.is_ascii_digit() {
+            return Err(Error::InvalidDer("bad time digit"));
+        }
+        num = num * 10 + (d - b'0') as u16;
+    }
+    Ok(num)
+}
+
+impl Time {
+    pub fn new(year: u16, month: u8, day: u8, hour: u8, minute: u8,
+               second: u8) -> Result<Time> {
+        if year > 9999 || !(1..=12).contains(&month) || day < 1 ||
+           day > days_in_month(year, month) || hour > 23 || minute > 59 ||
+           second > 59 {
+            return Err(Error::InvalidDer("time out of range"));
+        }
+        Ok(Time { year, month, day, hour, minute, second })
+    }
+
+    // everything after the year, MMDDHHMMSSZ
+    fn parse_rest(year: u16, rest: &[u8]) -> Result<Time> {
+        if rest.len() != 11 || rest[10] != b'Z' {
+            return Err(Error::InvalidDer("time not in DER form"));
+        }
+        Time::new(year,
+                  parse_digits(&rest[0..2])? as u8,
+                  parse_digits(&rest[2..4])? as u8,
+                  parse_digits(&rest[4..6])? as u8,
+                  parse_digits(&rest[6..8])? as u8,
+                  parse_digits(&rest[8..10])? as u8)
+    }
+
+    /// YYMMDDHHMMSSZ, YY under 50 is 20YY
+    pub fn from_utc_time(contents: &[u8]) -> Result<Time> {
+        if contents.len() != 13 {
+            return Err(Error::InvalidDer("time not in DER form"));
+        }
+        let yy = parse_digits(&contents[..2])?;
+        let year = if yy < 50 { 2000 + yy } else { 1900 + yy };
+        Time::parse_rest(year, &contents[2..])
+    }
+
+    /// YYYYMMDDHHMMSSZ, DER doesn't allow anything else
+    pub fn from_generalized_time(contents: &[u8]) -> Result<Time> {
+        if contents.len() != 15 {
+            return Err(Error::InvalidDer("time not in DER form"));
+        }
+        Time::parse_rest(parse_digits(&contents[..4])?, &contents[4..])
+    }
+
+    /// Only means anything for 1950 through 2049, use GeneralizedTime for
+    /// the rest
+    pub fn to_utc_time(&self) -> String {
+        format!("{:02}{:02}{:02}{:02}{:02}{:02}Z", self.year % 100,
+                self.month, self.day, self.hour, self.minute, self.second)
+    }
+
+    pub fn to_generalized_time(&self) -> String {
+        format!("{:04}{:02}{:02}{:02}{:02}{:02}Z", self.year, self.month,
+                self.day, self.hour, self.minute, self.second)
+    }
+}
+
+impl fmt::Display for Time {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC", self.year,
+               self.month, self.day, self.hour, self.minute, self.second)
+    }
+}
+
+/// Guards against hostile input: nothing bigger than max_len bytes and no
+/// more than max_depth constructed values inside each other
+#[derive(Debug, Clone, Copy)]
+pub struct Limits {
+    pub max_depth: usize,
+    pub max_len: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Limits {
+        Limits { max_depth: 32, max_len: 1 << 20 }
+    }
+}
+
+/// A parsed DER value
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Boolean(bool),
+    Integer(Mpz),
+    BitString { unused_bits: u8, bytes: Vec<u8> },
+    OctetString(Vec<u8>),
+    Null,
+    Oid(Oid),
+    Utf8String(String),
+    PrintableString(String),
+    IA5String(String),
+    UtcTime(Time),
+    GeneralizedTime(Time),
+    Sequence(Vec<Value>),
+    Set(Vec<Value>),
+    // [number], contents left encoded since whether they're one value
+    // (EXPLICIT) or the insides of one (IMPLICIT) depends on the schema.
+    // Only numbers below 31 fit in one tag byte, and like DerReader we
+    // don't do the high tag number form.
+    ContextSpecific { number: u8, constructed: bool, contents: Vec<u8> },
+    // any other tag, kept as is so it round trips
+    Other { tag: u8, contents: Vec<u8> },
+}
+
+fn is_printable(s: &str) -> bool {
+    s.bytes().all(|b| b.is_ascii_alphanumeric() || b" '()+,-./:=?".contains(&b))
+}
+
+impl Value {
+    /// [number] EXPLICIT value
+    pub fn explicit(number: u8, value: &Value) -> Result<Value> {
+        let explicit = Value::ContextSpecific {
+            number,
+            constructed: true,
+            contents: value.to_der()?,
+        };
+        explicit.tag()?;
+        Ok(explicit)
+    }
+
+    /// Fails for tags that need the high tag number form
+    pub fn tag(&self) -> Result<u8> {
+        let tag = match *self {
+            Value::Boolean(_) => TagType::Boolean as u8,
+            Value::Integer(_) => TagType::Integer as u8,
+            Value::BitString { .. } => TagType::BitString as u8,
+            Value::OctetString(_) => TagType::OctetString as u8,
+            Value::Null => TagType::Null as u8,
+            Value::Oid(_) => TagType::OID as u8,
+            Value::Utf8String(_) => TagType::Utf8String as u8,
+            Value::PrintableString(_) => TagType::PrintableString as u8,
+            Value::IA5String(_) => TagType::IA5String as u8,
+            Value::UtcTime(_) => TagType::UtcTime as u8,
+            Value::GeneralizedTime(_) => TagType::GeneralizedTime as u8,
+            Value::Sequence(_) => SEQUENCE,
+            Value::Set(_) => SET,
+            Value::ContextSpecific { number, .. } if number >= 0x1f => {
+                return Err(Error::InvalidDer("high tag numbers not supported"));
+            },
+            Value::ContextSpecific { number, constructed, .. } => {
+                CONTEXT_SPECIFIC | if constructed { CONSTRUCTED } else { 0 } |
+                number
+            },
+            Value::Other { tag, .. } => tag,
+        };
+        if tag & 0x1f == 0x1f {
+            return Err(Error::InvalidDer("high tag numbers not supported"));
+        }
+        Ok(tag)
+    }
+
+    pub fn to_der(&self) -> Result<Vec<u8>> {
+        let contents = match *self {
+            Value::Boolean(b) => vec!(if b { 0xff } else { 0 }),
+            Value::Integer(ref n) => return Ok(encode_integer(n)),
+            Value::BitString { unused_bits, ref bytes } => {
+                let mut contents = vec!(unused_bits);
+                contents.extend_from_slice(bytes);
+                contents
+            },
+            Value::OctetString(ref bytes) => bytes.clone(),
+            Value::Null => Vec::new(),
+            Value::Oid(ref oid) => oid.to_der_contents(),
+            Value::Utf8String(ref s) |
+            Value::PrintableString(ref s) |
+            Value::IA5String(ref s) => s.as_bytes().to_vec(),
+            Value::UtcTime(ref t) => t.to_utc_time().into_bytes(),
+            Value::GeneralizedTime(ref t) => {
+                t.to_generalized_time().into_bytes()
+            },
+            Value::Sequence(ref values) => {
+                values.iter().map(|v| v.to_der()).collect::<Result<Vec<_>>>()?
+                      .concat()
+            },
+            Value::Set(ref values) => {
+                // DER sorts SET OF by encoding
+                let mut encoded = values.iter().map(|v| v.to_der())
+                                        .collect::<Result<Vec<_>>>()?;
+                encoded.sort();
+                encoded.concat()
+            },
+            Value::ContextSpecific { ref contents, .. } |
+            Value::Other { ref contents, .. } => contents.clone(),
+        };
+        Ok(encode_tlv(self.tag()?, &contents))
+    }
+
+    /// Exactly one value with nothing after it, with the default limits
+    pub fn from_der(der: &[u8]) -> Result<Value> {
+        Value::from_der_with_limits(der, &Limits::default())
+    }
+
+    pub fn from_der_with_limits(der: &[u8], limits: &Limits) -> Result<Value> {
+        if der.len() > limits.max_len {
+            return Err(Error::InvalidDer("input too long"));
+        }
+        let mut reader = DerReader::new(der);
+        let value = Value::read(&mut reader, limits, 0)?;
+        reader.finish()?;
+        Ok(value)
+    }
+
+    /// All the values in contents, e.g. inside a ContextSpecific
+    pub fn parse_all(contents: &[u8], limits: &Limits) -> Result<Vec<Value>> {
+        if contents.len() > limits.max_len {
+            return Err(Error::InvalidDer("input too long"));
+        }
+        Value::read_all(contents, limits, 0)
+    }
+
+    fn read_all(contents: &[u8], limits: &Limits,
+                depth: usize) -> Result<Vec<Value>> {
+        if depth > limits.max_depth {
+            return Err(Error::InvalidDer("nested too deep"));
+        }
+        let mut reader = DerReader::new(contents);
+        let mut values = Vec::new();
+        while !reader.is_empty() {
+            values.push(Value::read(&mut reader, limits, depth)?);
+        }
+        Ok(values)
+    }
+
+    fn read(reader: &mut DerReader, limits: &Limits,
+            depth: usize) -> Result<Value> {
+        let (tag, contents) = reader.read_tlv()?;
+
+        if tag & CONTEXT_SPECIFIC != 0 && tag & 0x40 == 0 {
+            let constructed = tag & CONSTRUCTED != 0;
+            if constructed {
+                // only to check it's well formed and not too deep
+                Value::read_all(contents, limits, depth + 1)?;
+            }
+            return Ok(Value::ContextSpecific {
+                number: tag & 0x1f,
+                constructed,
+                contents: contents.to_vec(),
+            });
+        }
+
+        let value = match tag {
+            t if t == TagType::Boolean as u8 => {
+                match contents {
+                    [0] => Value::Boolean(false),
+                    [0xff] => Value::Boolean(true),
+                    _ => return Err(Error::InvalidDer("bad boolean")),
+                }
+            },
+            t if t == TagType::Integer as u8 => {
+                Value::Integer(decode_integer(contents)?)
+            },
+            t if t == TagType::BitString as u8 => {
+                let unused_bits = match contents.first() {
+                    Some(&n) if n < 8 => n,
+                    _ => return Err(Error::InvalidDer("bad bit string")),
+                };
+                let bytes = &contents[1..];
+                // DER wants the unused bits zeroed, and none if empty
+                let last = bytes.last().cloned().unwrap_or(0);
+                if (bytes.is_empty() && unused_bits != 0) ||
+                   last & ((1u8 << unused_bits) - 1) != 0 {
+                    return Err(Error::InvalidDer("bad bit string padding"));
+                }
+                Value::BitString { unused_bits, bytes: bytes.to_vec() }
+            },
+            t if t == TagType::OctetString as u8 => {
+                Value::OctetString(contents.to_vec())
+            },
+            t if t == TagType::Null as u8 => {
+                if !contents.is_empty() {
+                    return Err(Error::InvalidDer("null with contents"));
+                }
+                Value::Null
+            },
+            t if t == TagType::OID as u8 => {
+                Value::Oid(Oid::from_der_contents(contents)?)
+            },
+            t if t == TagType::Utf8String as u8 => {
+                match str::from_utf8(contents) {
+                    Ok(s) => Value::Utf8String(s.to_string()),
+                    Err(_) => return Err(Error::InvalidDer("bad utf8")),
+                }
+            },
+            t if t == TagType::PrintableString as u8 => {
+                match str::from_utf8(contents) {
+                    Ok(s) if is_printable(s) => {
+                        Value::PrintableString(s.to_string())
+                    },
+                    _ => return Err(Error::InvalidDer("bad printable")),
+                }
+            },
+            t if t == TagType::IA5String as u8 => {
+                match str::from_utf8(contents) {
+                    Ok(s) if s.is_ascii() => Value::IA5String(s.to_string()),
+                    _ => return Err(Error::InvalidDer("bad ia5 string")),
+                }
+            },
+            t if t == TagType::UtcTime as u8 => {
+                Value::UtcTime(Time::from_utc_time(contents)?)
+            },
+            t if t == TagType::GeneralizedTime as u8 => {
+                Value::GeneralizedTime(Time::from_generalized_time(contents)?)
+            },
+            SEQUENCE => {
+                Value::Sequence(Value::read_all(contents, limits, depth + 1)?)
+            },
+            SET => {
+                let values = Value::read_all(contents, limits, depth + 1)?;
+                let encoded = values.iter().map(|v| v.to_der())
+                                    .collect::<Result<Vec<_>>>()?;
+                if encoded.windows(2).any(|w| w[0] > w[1]) {
+                    return Err(Error::InvalidDer("set not sorted"));
+                }
+                Value::Set(values)
+            },
+            _ => {
+                if tag & CONSTRUCTED != 0 {
+                    Value::read_all(contents, limits, depth + 1)?;
+                }
+                Value::Other { tag, contents: contents.to_vec() }
+            },
+        };
+        Ok(value)
+    }
+
+    pub fn as_integer(&self) -> Option<&Mpz> {
+        match *self {
+            Value::Integer(ref n) => Some(n),
+            _ => None,
+        }
+    }
+
+    pub fn as_oid(&self) -> Option<&Oid> {
+        match *self {
+            Value::Oid(ref oid) => Some(oid),
+            _ => None,
+        }
+    }
+
+    /// Sequence or set
+    pub fn as_values(&self) -> Option<&[Value]> {
+        match *self {
+            Value::Sequence(ref values) | Value::Set(ref values) => {
+                Some(values)
+            },
+            _ => None,
+        }
+    }
+
+    /// Octet strings and whole byte bit strings
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match *self {
+            Value::OctetString(ref bytes) => Some(bytes),
+            Value::BitString { unused_bits: 0, ref bytes } => Some(bytes),
+            _ => None,
+        }
+    }
+
+    /// Any of the string types
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            Value::Utf8String(ref s) |
+            Value::PrintableString(ref s) |
+            Value::IA5String(ref s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_time(&self) -> Option<Time> {
+        match *self {
+            Value::UtcTime(t) | Value::GeneralizedTime(t) => Some(t),
+            _ => None,
+        }
+    }
+}
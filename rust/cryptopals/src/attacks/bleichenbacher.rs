@@ -0,0 +1,190 @@
+extern crate gmp;
+
+use self::gmp::mpz::Mpz;
+use error::{Error, Result};
+use rsa::PublicKey;
+use rsa::padding::pkcs1v15_encrypt_unpad;
+use util::{bytes_to_mpz, mpz_byte_len, mpz_bytes_zero_pad, randomish_mpz_lt};
+
+/// Bleichenbacher's 1998 attack on PKCS#1 v1.5 encryption padding. The
+/// oracle gets a ciphertext k bytes long (k being the length of n) and
+/// says whether it decrypts to something starting 00 02. An oracle that
+/// checks more of the padding works too, it just takes more queries.
+pub struct Bleichenbacher<F: FnMut(&[u8]) -> bool> {
+    oracle: F,
+    e: Mpz,
+    n: Mpz,
+    k: usize,
+    queries: usize,
+}
+
+// ceil(x / y) and floor(x / y) for y > 0. Mpz division truncates, which
+// is only the one we want for one sign of x.
+fn ceil_div(x: &Mpz, y: &Mpz) -> Mpz {
+    if *x < Mpz::zero() {
+        x / y
+    } else {
+        (x + y - Mpz::one()) / y
+    }
+}
+
+fn floor_div(x: &Mpz, y: &Mpz) -> Mpz {
+    if *x < Mpz::zero() {
+        (x - y + Mpz::one()) / y
+    } else {
+        x / y
+    }
+}
+
+// sorts and merges overlapping intervals
+fn merge_intervals(mut intervals: Vec<(Mpz, Mpz)>) -> Vec<(Mpz, Mpz)> {
+    intervals.sort();
+    let mut merged: Vec<(Mpz, Mpz)> = Vec::new();
+    for (a, b) in intervals {
+        if let Some(last) = merged.last_mut() {
+            if a <= last.1 {
+                if b > last.1 {
+                    last.1 = b;
+                }
+                continue;
+            }
+        }
+        merged.push((a, b));
+    }
+    merged
+}
+
+impl<F: FnMut(&[u8]) -> bool> Bleichenbacher<F> {
+    pub fn new(pub_key: &PublicKey, oracle: F) -> Bleichenbacher<F> {
+        Bleichenbacher {
+            oracle,
+            e: pub_key.e.clone(),
+            n: pub_key.n.clone(),
+            k: mpz_byte_len(&pub_key.n),
+            queries: 0,
+        }
+    }
+
+    /// Number of times the oracle has been asked so far
+    pub fn queries(&self) -> usize {
+        self.queries
+    }
+
+    // whether c * s^e decrypts to something conforming
+    fn conforming(&mut self, c: &Mpz, s: &Mpz) -> bool {
+        let blinded = (c * s.powm(&self.e, &self.n)).modulus(&self.n);
+        self.queries += 1;
+        (self.oracle)(&mpz_bytes_zero_pad(&blinded, self.k))
+    }
+
+    // starting from s, the first s that gives a conforming ciphertext
+    fn search_from(&mut self, c: &Mpz, s: &Mpz) -> Mpz {
+        let mut s = s.clone();
+        while !self.conforming(c, &s) {
+            s += Mpz::one();
+        }
+        s
+    }
+
+    /// Recovers the padded message m for c = m^e mod n, all k bytes of it
+    pub fn decrypt_mpz(&mut self, c: &Mpz) -> Result<Mpz> {
+        if self.k < 11 {
+            return Err(Error::AttackFailed(
+                format!("{} byte modulus too small", self.k)));
+        }
+
+        let b = Mpz::one() << (8 * (self.k - 2));
+        let two_b = &b * Mpz::from(2u64);
+        let three_b = &b * Mpz::from(3u64);
+
+        // step 1: blind until c0 is conforming. c0 = c if it already is,
+        // which it will be for a real ciphertext.
+        let mut s0 = Mpz::one();
+        while !self.conforming(c, &s0) {
+            s0 = randomish_mpz_lt(&self.n);
+            if s0.gcd(&self.n) != Mpz::one() {
+                s0 = Mpz::one();
+            }
+        }
+        let c0 = (c * s0.powm(&self.e, &self.n)).modulus(&self.n);
+
+        let mut intervals = vec!((two_b.clone(), &three_b - Mpz::one()));
+        let mut s = Mpz::zero();
+        let mut i = 1;
+        loop {
+            if i == 1 {
+                // step 2a: smallest s >= n / 3B
+                let start = ceil_div(&self.n, &three_b);
+                s = self.search_from(&c0, &start);
+            } else if intervals.len() > 1 {
+                // step 2b: keep going from the last s
+                s = self.search_from(&c0, &(&s + Mpz::one()));
+            } else {
+                // step 2c: one interval left, walk r so s roughly doubles
+                // each time and halves the interval
+                let (a, b_max) = intervals[0].clone();
+                let mut r = ceil_div(&(Mpz::from(2u64) *
+                                       (&b_max * &s - &two_b)), &self.n);
+                'search: loop {
+                    let rn = &r * &self.n;
+                    let mut s_i = ceil_div(&(&two_b + &rn), &b_max);
+                    let s_max = ceil_div(&(&three_b + &rn), &a);
+                    while s_i < s_max {
+                        if self.conforming(&c0, &s_i) {
+                            s = s_i;
+                            break 'search;
+                        }
+                        s_i += Mpz::one();
+                    }
+                    r += Mpz::one();
+                }
+            }
+
+            // step 3: narrow each interval with every r that fits
+            let mut next = Vec::new();
+            for (a, b_max) in &intervals {
+                let mut r = ceil_div(&(a * &s - &three_b + Mpz::one()),
+                                     &self.n);
+                let r_max = floor_div(&(b_max * &s - &two_b), &self.n);
+                while r <= r_max {
+                    let rn = &r * &self.n;
+                    let lo = ceil_div(&(&two_b + &rn), &s);
+                    let hi = floor_div(&(&three_b - Mpz::one() + &rn), &s);
+                    let lo = if lo > *a { lo } else { a.clone() };
+                    let hi = if hi < *b_max { hi } else { b_max.clone() };
+                    if lo <= hi {
+                        next.push((lo, hi));
+                    }
+                    r += Mpz::one();
+                }
+            }
+            if next.is_empty() {
+                return Err(Error::AttackFailed(
+                    format!("no intervals left after {} rounds", i)));
+            }
+            intervals = merge_intervals(next);
+
+            // step 4
+            if intervals.len() == 1 && intervals[0].0 == intervals[0].1 {
+                let s0_inv = match s0.invert(&self.n) {
+                    Some(inv) => inv,
+                    None => {
+                        return Err(Error::AttackFailed(
+                            "blinding value not invertible".to_string()));
+                    },
+                };
+                return Ok((&intervals[0].0 * s0_inv).modulus(&self.n));
+            }
+            i += 1;
+        }
+    }
+
+    /// Recovers and unpads the plaintext for a k byte ciphertext
+    pub fn decrypt(&mut self, ciphertext: &[u8]) -> Result<Vec<u8>> {
+        if ciphertext.len() != self.k {
+            return Err(Error::BadCiphertextLength(ciphertext.len()));
+        }
+        let m = self.decrypt_mpz(&bytes_to_mpz(ciphertext))?;
+        pkcs1v15_encrypt_unpad(&mpz_bytes_zero_pad(&m, self.k))
+    }
+}
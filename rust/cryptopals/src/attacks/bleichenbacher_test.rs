@@ -0,0 +1,50 @@
+extern crate gmp;
+
+use self::gmp::mpz::Mpz;
+use attacks::bleichenbacher::Bleichenbacher;
+use rsa::new_keypair;
+use util::{bytes_to_mpz, assert_slice_cmp};
+
+// challenges 47 and 48, the oracle only checks for 00 02
+fn bleichenbacher_test(bit_len: usize) {
+    let (pub_key, priv_key) = new_keypair(bit_len);
+    let k = pub_key.byte_len();
+    let msg = "kick it, CC".as_bytes();
+    let ciphertext = pub_key.pkcs1v15_encrypt(msg).unwrap();
+    println!("bleichenbacher test with {} byte n", k);
+
+    let two_b = Mpz::from(2u64) << (8 * (k - 2));
+    let three_b = Mpz::from(3u64) << (8 * (k - 2));
+    let mut attack = Bleichenbacher::new(&pub_key, |c: &[u8]| {
+        let m = priv_key.decrypt_mpz(&bytes_to_mpz(c)).unwrap();
+        m >= two_b && m < three_b
+    });
+    let decrypted = attack.decrypt(&ciphertext).unwrap();
+    assert_slice_cmp("bleichenbacher decrypt", msg, &decrypted);
+    println!("bleichenbacher decrypted {} byte n in {} queries", k,
+             attack.queries());
+}
+
+// the real decryption, padding length and separator and all. takes more
+// queries so only do the small one, with n just over a byte boundary:
+// 00 02 comes up about B / n of the time, 1 in 2^8 or so here rather
+// than 1 in 2^16 for a 256 bit n.
+fn bleichenbacher_strict_oracle_test() {
+    let (pub_key, priv_key) = new_keypair(249);
+    let msg = "beep".as_bytes();
+    let ciphertext = pub_key.pkcs1v15_encrypt(msg).unwrap();
+
+    let mut attack = Bleichenbacher::new(&pub_key, |c: &[u8]| {
+        priv_key.pkcs1v15_decrypt(c).is_ok()
+    });
+    let decrypted = attack.decrypt(&ciphertext).unwrap();
+    assert_slice_cmp("bleichenbacher strict decrypt", msg, &decrypted);
+    println!("bleichenbacher strict oracle took {} queries", attack.queries());
+}
+
+pub fn bleichenbacher_tests() {
+    bleichenbacher_test(256);
+    bleichenbacher_test(768);
+    bleichenbacher_strict_oracle_test();
+    println!("Finished Bleichenbacher tests");
+}
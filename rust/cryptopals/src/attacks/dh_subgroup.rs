@@ -0,0 +1,118 @@
+extern crate gmp;
+extern crate rand;
+
+use self::gmp::mpz::Mpz;
+use self::rand::Rng;
+use dh::ff_dhe_derive_keys;
+use error::{Error, Result};
+use mac::hmac_sha256;
+use util::{constant_time_eq, crt, random_mpz_below};
+
+/// The distinct primes under bound that divide n, by trial division
+pub fn small_factors(n: &Mpz, bound: u64) -> Vec<u64> {
+    let mut n = n.clone();
+    let mut factors = Vec::new();
+    for r in 2..bound {
+        // composites can't divide once their primes are gone
+        if !(&n % r).is_zero() {
+            continue;
+        }
+        factors.push(r);
+        while (&n % r).is_zero() {
+            n /= r;
+        }
+    }
+    factors
+}
+
+/// An element of order r mod p, for a prime r dividing p - 1: something
+/// random raised to (p - 1) / r, as long as that isn't 1, or 0 from
+/// drawing 0 in the first place
+pub fn confinement_element<R: Rng>(rng: &mut R, p: &Mpz, r: u64) -> Mpz {
+    let exp = (p - Mpz::one()) / r;
+    loop {
+        let h = random_mpz_below(rng, p).powm(&exp, p);
+        if h != Mpz::one() && !h.is_zero() {
+            return h;
+        }
+    }
+}
+
+// h has order r, so the responder's h^x is one of h^0 ... h^(r - 1), and
+// whichever one's MAC key gives the tag is x mod r
+fn residue(h: &Mpz, r: u64, p: &Mpz, context: &[u8], msg: &[u8],
+           tag: &[u8]) -> Option<u64> {
+    let mut shared = Mpz::one();
+    for k in 0..r {
+        let keys = ff_dhe_derive_keys(&shared, p, context);
+        if constant_time_eq(&hmac_sha256(&keys.mac_key, msg), tag) {
+            return Some(k);
+        }
+        shared = (&shared * h).modulus(p);
+    }
+    None
+}
+
+/// Small subgroup confinement (challenge 57). For each small prime r
+/// dividing j = (p - 1) / q, send the oracle an element of order r in
+/// place of a public, brute force x mod r from the tag it comes back
+/// with, and CRT the lot together. Stops once the product of the r's
+/// passes q. Returns (x mod m, m); the oracle takes a public and gives
+/// (message, tag) with the MAC key from ff_dhe_derive_keys under context,
+/// and any it refuses are skipped.
+pub fn subgroup_residues<R, O>(p: &Mpz, q: &Mpz, factor_bound: u64,
+                               context: &[u8], rng: &mut R,
+                               mut oracle: O) -> Result<(Mpz, Mpz)>
+        where R: Rng,
+              O: FnMut(&Mpz) -> Result<(Vec<u8>, Vec<u8>)> {
+    let p_1 = p - Mpz::one();
+    if !(&p_1 % q).is_zero() {
+        return Err(Error::AttackFailed("q doesn't divide p - 1".to_string()));
+    }
+
+    let mut residues = Vec::new();
+    let mut modulus = Mpz::one();
+    for r in small_factors(&(p_1 / q), factor_bound) {
+        if (q % r).is_zero() {
+            // r has to be coprime to q for x mod r to tell us anything new
+            continue;
+        }
+        let h = confinement_element(rng, p, r);
+        let (msg, tag) = match oracle(&h) {
+            Ok(response) => response,
+            Err(_) => continue,
+        };
+        let x_r = match residue(&h, r, p, context, &msg, &tag) {
+            Some(x_r) => x_r,
+            None => return Err(Error::AttackFailed(
+                format!("no residue mod {} matched the tag", r))),
+        };
+        residues.push((Mpz::from(x_r), Mpz::from(r)));
+        modulus *= r;
+        if modulus > *q {
+            break;
+        }
+    }
+    // the r's are distinct primes so this only fails if something's
+    // badly wrong
+    crt(&residues).ok_or_else(|| {
+        Error::AttackFailed("residues' moduli aren't coprime".to_string())
+    })
+}
+
+/// Pohlig-Hellman against the responder, for when j has enough small
+/// factors to pin down all of x. The result is x mod q, which is as good
+/// as x since g has order q.
+pub fn pohlig_hellman<R, O>(p: &Mpz, q: &Mpz, factor_bound: u64,
+                            context: &[u8], rng: &mut R,
+                            oracle: O) -> Result<Mpz>
+        where R: Rng,
+              O: FnMut(&Mpz) -> Result<(Vec<u8>, Vec<u8>)> {
+    let (x, m) = subgroup_residues(p, q, factor_bound, context, rng,
+                                   oracle)?;
+    if m <= *q {
+        return Err(Error::AttackFailed(
+            format!("only have x mod {}, which is less than q", m)));
+    }
+    Ok(x.modulus(q))
+}
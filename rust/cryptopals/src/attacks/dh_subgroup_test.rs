@@ -0,0 +1,96 @@
+extern crate gmp;
+
+use std::cell::Cell;
+
+use self::gmp::mpz::Mpz;
+use attacks::dh_subgroup::{small_factors, confinement_element,
+                           subgroup_residues, pohlig_hellman};
+use dh::group::DHGroup;
+use dh::responder::{DHResponder, DH_RESPONDER_CONTEXT};
+use dh::test::smooth_test_group;
+use error::Error;
+use util::{random_mpz_below, seeded_rng};
+
+const FACTOR_BOUND: u64 = 1 << 12;
+
+// the same group minus q, so the responder only checks the range of the
+// publics it gets
+fn unchecked(group: &DHGroup) -> DHGroup {
+    DHGroup::new(&group.p, &group.g, None).unwrap()
+}
+
+fn small_factors_test() {
+    let group = smooth_test_group();
+    let q = group.q.unwrap();
+    let j = (&group.p - Mpz::one()) / &q;
+    let factors = small_factors(&j, FACTOR_BOUND);
+    // the 14 it was built from, plus 2, 3, 5 and 1453 from the rest of j
+    assert_eq!(factors, vec!(2, 3, 5, 307, 419, 443, 743, 907, 1063, 1453,
+                             1867, 1951, 2039, 2711, 3581, 3659, 3677, 3823));
+    assert_eq!(small_factors(&Mpz::from(2u64 * 2 * 9 * 7 * 101), 100),
+               vec!(2, 3, 7));
+
+    let mut rng = seeded_rng(1);
+    for &r in &factors[..6] {
+        let h = confinement_element(&mut rng, &group.p, r);
+        assert!(h != Mpz::one());
+        assert_eq!(h.powm(&Mpz::from(r), &group.p), Mpz::one());
+    }
+    // mod 7 a draw of 0 comes up often, and the only order 2 element is 6
+    for _ in 0..32 {
+        assert_eq!(confinement_element(&mut rng, &Mpz::from(7u64), 2),
+                   Mpz::from(6u64));
+    }
+}
+
+fn pohlig_hellman_test() {
+    let group = smooth_test_group();
+    let q = group.q.clone().unwrap();
+    let mut rng = seeded_rng(2);
+
+    for _ in 0..2 {
+        let x = random_mpz_below(&mut rng, &q);
+        let responder = DHResponder::new(unchecked(&group), &x);
+        assert_eq!(responder.public(), group.g.powm(&x, &group.p));
+
+        let queries = Cell::new(0);
+        let recovered = pohlig_hellman(&group.p, &q, FACTOR_BOUND,
+                                       DH_RESPONDER_CONTEXT, &mut rng,
+                                       |h: &Mpz| {
+            queries.set(queries.get() + 1);
+            responder.respond(h)
+        }).unwrap();
+        assert_eq!(recovered, x);
+        // 2's element is p - 1, which gets refused, and 3 * 5 * ... * 3823
+        // passes q before the end of the list
+        assert!(queries.get() < 18, "{} queries", queries.get());
+    }
+
+    // a responder that knows q refuses every one of them
+    let x = random_mpz_below(&mut rng, &q);
+    let responder = DHResponder::new(group.clone(), &x);
+    let (residue, modulus) = subgroup_residues(
+        &group.p, &q, FACTOR_BOUND, DH_RESPONDER_CONTEXT, &mut rng,
+        |h: &Mpz| responder.respond(h)).unwrap();
+    assert_eq!((residue, modulus), (Mpz::zero(), Mpz::one()));
+    match pohlig_hellman(&group.p, &q, FACTOR_BOUND, DH_RESPONDER_CONTEXT,
+                         &mut rng, |h: &Mpz| responder.respond(h)) {
+        Err(Error::AttackFailed(_)) => {},
+        other => panic!("expected the attack to fail, got {:?}", other),
+    }
+
+    // with a low bound there's only some of x
+    let responder = DHResponder::new(unchecked(&group), &x);
+    let (residue, modulus) = subgroup_residues(
+        &group.p, &q, 1000, DH_RESPONDER_CONTEXT, &mut rng,
+        |h: &Mpz| responder.respond(h)).unwrap();
+    assert_eq!(modulus, Mpz::from(3u64 * 5 * 307 * 419 * 443 * 743 * 907));
+    assert_eq!(residue, x.modulus(&modulus));
+
+    println!("Finished Pohlig-Hellman tests");
+}
+
+pub fn dh_subgroup_tests() {
+    small_factors_test();
+    pohlig_hellman_test();
+}
@@ -0,0 +1,165 @@
+use error::{Error, Result};
+
+// nothing sane has blocks bigger than this
+const MAX_BLOCK_SIZE: usize = 256;
+
+/// What the attack had to figure out on the way, plus how many times it
+/// asked the oracle
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ECBAttackStats {
+    pub block_size: usize,
+    pub prefix_len: usize,
+    pub secret_len: usize,
+    pub queries: usize,
+}
+
+/// Byte-at-a-time ECB decryption (challenges 12 and 14) against an oracle
+/// that returns ECB(prefix || input || secret) for a fixed prefix and
+/// secret, either of which might be empty.
+pub struct ECBByteAtATime<F: FnMut(&[u8]) -> Vec<u8>> {
+    oracle: F,
+    queries: usize,
+}
+
+impl<F: FnMut(&[u8]) -> Vec<u8>> ECBByteAtATime<F> {
+    pub fn new(oracle: F) -> ECBByteAtATime<F> {
+        ECBByteAtATime {
+            oracle,
+            queries: 0,
+        }
+    }
+
+    /// Number of times the oracle has been asked so far
+    pub fn queries(&self) -> usize {
+        self.queries
+    }
+
+    fn query(&mut self, input: &[u8]) -> Vec<u8> {
+        self.queries += 1;
+        (self.oracle)(input)
+    }
+
+    /// Returns (block_size, prefix_len + secret_len)
+    pub fn detect_block_size(&mut self) -> Result<(usize, usize)> {
+        // keep adding bytes until the padding spills over into a new
+        // block. if hidden_len % bs == bs - 1, adding 1 byte gives a whole
+        // new block, if hidden_len % bs == bs - 2, adding 2 does, etc.
+        let start_len = self.query(&[]).len();
+        let mut input = Vec::new();
+        for i in 1..(MAX_BLOCK_SIZE + 1) {
+            input.push(0u8);
+            let n = self.query(&input).len();
+            if n > start_len {
+                let block_size = n - start_len;
+                return Ok((block_size, n - block_size - i));
+            }
+        }
+        Err(Error::AttackFailed(String::from("couldn't find block size")))
+    }
+
+    /// Same input blocks give the same output blocks. Three blocks of input
+    /// means at least two line up no matter how long the prefix is.
+    pub fn is_ecb(&mut self, block_size: usize) -> bool {
+        let ciphertext = self.query(&vec!(0u8; block_size * 3));
+        let blocks: Vec<&[u8]> = ciphertext.chunks(block_size).collect();
+        blocks.windows(2).any(|w| w[0] == w[1])
+    }
+
+    pub fn detect_prefix_len(&mut self, block_size: usize) -> Result<usize> {
+        // the first block that changes with our input has the end of the
+        // prefix in it
+        let zero = self.query(&[0u8]);
+        let one = self.query(&[1u8]);
+        let first_diff = match zero.chunks(block_size)
+                                   .zip(one.chunks(block_size))
+                                   .position(|(a, b)| a != b) {
+            Some(index) => index,
+            None => {
+                return Err(Error::AttackFailed(
+                    String::from("input never changed the ciphertext")));
+            },
+        };
+
+        // fill that block up with 0s until the byte after stops mattering,
+        // the number of 0s it took is how much of the block was free
+        let start = first_diff * block_size;
+        for fill in 0..(block_size + 1) {
+            let mut zero_input = vec!(0u8; fill);
+            let mut one_input = zero_input.clone();
+            zero_input.push(0u8);
+            one_input.push(1u8);
+            let zero = self.query(&zero_input);
+            let one = self.query(&one_input);
+            if zero[start..start + block_size] ==
+                    one[start..start + block_size] {
+                return Ok(start + block_size - fill);
+            }
+        }
+        Err(Error::AttackFailed(String::from("couldn't find prefix length")))
+    }
+
+    /// Figures out everything it needs then decrypts the secret
+    pub fn decrypt_suffix(&mut self) -> Result<(Vec<u8>, ECBAttackStats)> {
+        let (block_size, hidden_len) = self.detect_block_size()?;
+        if !self.is_ecb(block_size) {
+            return Err(Error::AttackFailed(String::from("oracle isn't ecb")));
+        }
+        let prefix_len = self.detect_prefix_len(block_size)?;
+        let secret_len = hidden_len - prefix_len;
+        let secret = self.decrypt_known(block_size, prefix_len,
+                                        secret_len)?;
+
+        let stats = ECBAttackStats {
+            block_size,
+            prefix_len,
+            secret_len,
+            queries: self.queries,
+        };
+        Ok((secret, stats))
+    }
+
+    /// Decrypts the secret when the block size and prefix length are
+    /// already known
+    pub fn decrypt_known(&mut self, block_size: usize, prefix_len: usize,
+                         secret_len: usize) -> Result<Vec<u8>> {
+        // pad the prefix out to a whole block so our input starts on a
+        // block boundary at skip_len
+        let pad_len = (block_size - prefix_len % block_size) % block_size;
+        let skip_len = prefix_len + pad_len;
+
+        let mut secret: Vec<u8> = Vec::with_capacity(secret_len);
+        for i in 0..secret_len {
+            // shift the secret over so byte i is the last one in its block
+            // and everything in front of it is known
+            let fill_len = pad_len + block_size - 1 - (i % block_size);
+            let ciphertext = self.query(&vec!(0u8; fill_len));
+            let target = skip_len + (i / block_size) * block_size;
+            let target_block = &ciphertext[target..target + block_size];
+
+            // known is the block_size - 1 bytes in front of byte i
+            let mut known = vec!(0u8; block_size - 1);
+            known.extend_from_slice(&secret);
+            let known = &known[known.len() - (block_size - 1)..];
+
+            // all 256 guesses fit in one query, one block each
+            let mut guesses = vec!(0u8; pad_len);
+            for b in 0..256 {
+                guesses.extend_from_slice(known);
+                guesses.push(b as u8);
+            }
+            let dictionary = self.query(&guesses);
+
+            let found = dictionary[skip_len..skip_len + 256 * block_size]
+                            .chunks(block_size)
+                            .position(|block| block == target_block);
+            match found {
+                Some(b) => secret.push(b as u8),
+                None => {
+                    return Err(Error::AttackFailed(
+                        format!("no match for secret byte {}", i)));
+                },
+            };
+        }
+        Ok(secret)
+    }
+}
@@ -0,0 +1,78 @@
+use aes::{AESCipher, AES_BLOCK_SIZE};
+use aes::cbc::AESCipherCBC;
+use aes::ecb::AESCipherECB;
+use attacks::ecb_byte_at_a_time::ECBByteAtATime;
+use error::Error;
+use pkcs7::pkcs7_pad;
+use util::{rand_key, rand_bytes, rand_bytes_range, assert_slice_cmp};
+use xor::slice_xor;
+
+fn aes_ecb_byte_at_a_time_test() {
+    let key = rand_key();
+    println!("ECB byte at a time test with key {:?}", key);
+    let cipher = AESCipherECB::new(&key).unwrap();
+
+    for &(prefix_len, secret_len) in &[(0, 0), (0, 1), (1, 16), (15, 17),
+                                       (16, 40), (17, 5), (33, 64)] {
+        let prefix = rand_bytes(prefix_len);
+        let secret = rand_bytes(secret_len);
+        let mut attack = ECBByteAtATime::new(|input: &[u8]| {
+            let mut plaintext = prefix.clone();
+            plaintext.extend_from_slice(input);
+            plaintext.extend_from_slice(&secret);
+            cipher.pad_and_encrypt(&plaintext)
+        });
+
+        let (decrypted, stats) = attack.decrypt_suffix().unwrap();
+        assert_slice_cmp("ecb byte at a time secret", &secret, &decrypted);
+        assert_eq!(stats.block_size, AES_BLOCK_SIZE);
+        assert_eq!(stats.prefix_len, prefix_len);
+        assert_eq!(stats.secret_len, secret_len);
+        assert_eq!(stats.queries, attack.queries());
+        // 2 per secret byte, the rest is a handful for detection
+        assert!(stats.queries <= 2 * secret_len + 2 * AES_BLOCK_SIZE + 24,
+                "ecb byte at a time took {} queries", stats.queries);
+    }
+}
+
+// "ecb" with D(c) = c ^ key and 8 byte blocks so nothing depends on
+// AES_BLOCK_SIZE
+fn toy_ecb_byte_at_a_time_test() {
+    let bs = 8;
+    let key = rand_bytes(bs);
+    let prefix = rand_bytes_range(0, 20);
+    let secret = "beep boop meow meow".as_bytes();
+    let mut attack = ECBByteAtATime::new(|input: &[u8]| {
+        let mut plaintext = prefix.clone();
+        plaintext.extend_from_slice(input);
+        plaintext.extend_from_slice(secret);
+        let mut ciphertext = Vec::new();
+        for block in pkcs7_pad(&plaintext, bs).chunks(bs) {
+            ciphertext.extend(slice_xor(block, &key));
+        }
+        ciphertext
+    });
+
+    let (decrypted, stats) = attack.decrypt_suffix().unwrap();
+    assert_slice_cmp("toy ecb byte at a time secret", secret, &decrypted);
+    assert_eq!(stats.block_size, bs);
+    assert_eq!(stats.prefix_len, prefix.len());
+}
+
+fn not_ecb_test() {
+    let (cipher, _) = AESCipherCBC::new_rand_iv(&rand_key()).unwrap();
+    let mut attack = ECBByteAtATime::new(|input: &[u8]| {
+        cipher.pad_and_encrypt(input)
+    });
+    match attack.decrypt_suffix() {
+        Err(Error::AttackFailed(_)) => {},
+        other => panic!("ecb byte at a time accepted cbc {:?}", other),
+    };
+}
+
+pub fn ecb_byte_at_a_time_test() {
+    toy_ecb_byte_at_a_time_test();
+    not_ecb_test();
+    aes_ecb_byte_at_a_time_test();
+    println!("Finished ECB byte at a time tests");
+}
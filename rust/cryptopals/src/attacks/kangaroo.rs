@@ -0,0 +1,263 @@
+extern crate gmp;
+
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+
+use self::gmp::mpz::Mpz;
+use error::{Error, Result};
+
+// how many different traps the serial kangaroo lays before giving up
+const SERIAL_ATTEMPTS: u64 = 4;
+
+/// The jump function: from y a kangaroo jumps by sizes[y mod k], i.e.
+/// multiplies y by g^sizes[y mod k]. The mean jump is what matters, too
+/// small and the kangaroos take forever, too big and they jump over each
+/// other's trails.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Jumps {
+    sizes: Vec<Mpz>,
+}
+
+impl Jumps {
+    /// There has to be at least one jump and they all have to be > 0
+    pub fn new(sizes: &[Mpz]) -> Result<Jumps> {
+        if sizes.is_empty() {
+            return Err(Error::AttackFailed("no jumps".to_string()));
+        }
+        if sizes.iter().any(|s| *s <= Mpz::zero()) {
+            return Err(Error::AttackFailed("jumps must be > 0".to_string()));
+        }
+        Ok(Jumps { sizes: sizes.to_vec() })
+    }
+
+    /// 1, 2, 4, ..., 2^(k - 1), the f(y) = 2^(y mod k) of challenge 58
+    pub fn powers_of_two(k: usize) -> Result<Jumps> {
+        let sizes: Vec<Mpz> = (0..k).map(|i| Mpz::one() << i).collect();
+        Jumps::new(&sizes)
+    }
+
+    /// Powers of two with a mean of about kangaroos * sqrt(width) / 4,
+    /// the usual advice. For one tame and one wild that's sqrt(width) / 2.
+    pub fn for_interval(width: &Mpz, kangaroos: usize) -> Result<Jumps> {
+        let target = width.sqrt() * kangaroos as u64 / 4u64;
+        let mut k = 1;
+        while ((Mpz::one() << k) - Mpz::one()) / (k as u64) < target {
+            k += 1;
+        }
+        Jumps::powers_of_two(k)
+    }
+
+    pub fn sizes(&self) -> &[Mpz] {
+        &self.sizes
+    }
+
+    pub fn mean(&self) -> Mpz {
+        let total = self.sizes.iter().fold(Mpz::zero(), |acc, s| acc + s);
+        total / self.sizes.len() as u64
+    }
+}
+
+// the jumps along with g to each of them, shared by every kangaroo
+struct Herd<'a> {
+    p: &'a Mpz,
+    jumps: &'a Jumps,
+    steps: Vec<Mpz>,
+}
+
+impl<'a> Herd<'a> {
+    fn new(g: &'a Mpz, p: &'a Mpz, jumps: &'a Jumps) -> Herd<'a> {
+        let steps = jumps.sizes.iter().map(|s| g.powm(s, p)).collect();
+        Herd { p, jumps, steps }
+    }
+
+    fn hop(&self, y: &mut Mpz, distance: &mut Mpz) {
+        let k = self.steps.len() as u64;
+        let i = Option::<u64>::from(&(&*y % k)).unwrap() as usize;
+        // in place, y is never negative so % is the same as modulus
+        *y *= &self.steps[i];
+        *y %= self.p;
+        *distance += &self.jumps.sizes[i];
+    }
+}
+
+fn check_interval(a: &Mpz, b: &Mpz) -> Result<Mpz> {
+    if a > b || *a < Mpz::zero() {
+        return Err(Error::AttackFailed(format!("bad interval [{}, {}]", a, b)));
+    }
+    Ok(b - a)
+}
+
+fn not_found(a: &Mpz, b: &Mpz) -> Error {
+    Error::AttackFailed(format!("no log in [{}, {}] found", a, b))
+}
+
+// hop counts have to fit in a u64, which they always do for an interval
+// anyone could search anyway
+fn hop_count(hops: &Mpz) -> Result<u64> {
+    Option::<u64>::from(hops).ok_or_else(|| {
+        Error::AttackFailed("interval too wide for a kangaroo".to_string())
+    })
+}
+
+/// Pollard's kangaroo (challenge 58): x in [a, b] with g^x = y mod p, in
+/// about 2 sqrt(b - a) multiplications. A tame kangaroo starts at g^b and
+/// takes 4 times the mean jump hops, then waits there. A wild one starts
+/// at y and hops the same way, and if it ever lands on the tame one's
+/// trail it follows it into the trap, and the distances give x. It can
+/// hop past the trap and miss, so it tries a few traps before failing.
+pub fn kangaroo(g: &Mpz, y: &Mpz, p: &Mpz, a: &Mpz, b: &Mpz,
+                jumps: &Jumps) -> Result<Mpz> {
+    check_interval(a, b)?;
+    let herd = Herd::new(g, p, jumps);
+    let mean = jumps.mean();
+    let tame_hops = hop_count(&(&mean * 4u64))?;
+
+    for attempt in 0..SERIAL_ATTEMPTS {
+        // each attempt starts the tame kangaroo a bit further on, so it
+        // takes a different trail
+        let start = b + &mean * attempt;
+        let mut tame = g.powm(&start, p);
+        let mut tame_distance = Mpz::zero();
+        for _ in 0..tame_hops {
+            herd.hop(&mut tame, &mut tame_distance);
+        }
+
+        let mut wild = y.modulus(p);
+        let mut wild_distance = Mpz::zero();
+        let limit = &start - a + &tame_distance;
+        while wild_distance <= limit {
+            if wild == tame {
+                return Ok(&start + &tame_distance - &wild_distance);
+            }
+            herd.hop(&mut wild, &mut wild_distance);
+        }
+    }
+    Err(not_found(a, b))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Tame,
+    Wild,
+}
+
+// where each kangaroo's trail hit a distinguished point, and the exponent
+// that point is at for a tame one, or minus x for a wild one
+type Traps = HashMap<Mpz, (Kind, Mpz)>;
+
+struct Kangaroo {
+    kind: Kind,
+    y: Mpz,
+    // the tame exponent of y, or how far past x a wild y is
+    exponent: Mpz,
+}
+
+impl Kangaroo {
+    fn new(kind: Kind, base: &Mpz, start: &Mpz, g: &Mpz, p: &Mpz) -> Kangaroo {
+        let y = (base * g.powm(start, p)).modulus(p);
+        Kangaroo { kind, y, exponent: start.clone() }
+    }
+}
+
+/// The parallel version (van Oorschot and Wiener): one kangaroo per
+/// thread, half of them tame starting around the middle of [a, b] and
+/// half wild starting around y, all at different offsets. Any time one
+/// lands on a distinguished point, an element whose bottom bits are all
+/// zero, it's recorded; when a wild and a tame both hit the same point
+/// their exponents give x. Two of the same kind meeting means they'll
+/// walk together from then on, so the newcomer starts over somewhere
+/// else. Jumps should have a mean of about threads sqrt(b - a) / 4,
+/// which is what Jumps::for_interval(width, threads) gives.
+pub fn kangaroo_parallel(g: &Mpz, y: &Mpz, p: &Mpz, a: &Mpz, b: &Mpz,
+                         jumps: &Jumps, threads: usize) -> Result<Mpz> {
+    if threads < 2 {
+        return Err(Error::AttackFailed(
+            "need a tame and a wild kangaroo".to_string()));
+    }
+    let width = check_interval(a, b)?;
+    let herd = Herd::new(g, p, jumps);
+    let target = y.modulus(p);
+    let kangaroos = threads as u64;
+    let mid = a + (&width >> 1);
+
+    // distinguished points about every sqrt(width) / 32 kangaroos hops,
+    // each kangaroo hopping about 2 sqrt(width) / kangaroos times
+    let trail_bits = width.bit_length() / 2;
+    let kangaroo_bits = 64 - (kangaroos - 1).leading_zeros() as usize;
+    let dp_bits = trail_bits.saturating_sub(kangaroo_bits + 5);
+    let dp_mod = Mpz::one() << dp_bits;
+    let max_hops = hop_count(
+        &((width.sqrt() * 16u64) / kangaroos + (&dp_mod * 16u64)))?;
+
+    // starts spaced out so kangaroos of a kind don't share trails
+    let spacing = {
+        let s = jumps.mean() / kangaroos;
+        if s.is_zero() { Mpz::one() } else { s }
+    };
+
+    let traps: Mutex<Traps> = Mutex::new(HashMap::new());
+    let found: Mutex<Option<Mpz>> = Mutex::new(None);
+    let done = AtomicBool::new(false);
+
+    let run = |mut kangaroo: Kangaroo, mut restarts: u64| {
+        let one = Mpz::one();
+        for _ in 0..max_hops {
+            if done.load(Ordering::Relaxed) {
+                return;
+            }
+            herd.hop(&mut kangaroo.y, &mut kangaroo.exponent);
+            if !(&kangaroo.y % &dp_mod).is_zero() {
+                continue;
+            }
+
+            let mut traps = traps.lock().unwrap();
+            match traps.entry(kangaroo.y.clone()) {
+                Entry::Vacant(v) => {
+                    v.insert((kangaroo.kind, kangaroo.exponent.clone()));
+                },
+                Entry::Occupied(o) => {
+                    let &(kind, ref exponent) = o.get();
+                    if kind != kangaroo.kind {
+                        let x = match kind {
+                            Kind::Tame => exponent - &kangaroo.exponent,
+                            Kind::Wild => &kangaroo.exponent - exponent,
+                        };
+                        if x >= Mpz::zero() && g.powm(&x, p) == target {
+                            *found.lock().unwrap() = Some(x);
+                            done.store(true, Ordering::Relaxed);
+                            return;
+                        }
+                    }
+                    // stuck on someone else's trail, go somewhere new
+                    restarts += kangaroos;
+                    let offset = &spacing * restarts + &one;
+                    kangaroo = match kangaroo.kind {
+                        Kind::Tame => Kangaroo::new(Kind::Tame, &one,
+                                                    &(&mid + &offset), g, p),
+                        Kind::Wild => Kangaroo::new(Kind::Wild, y, &offset,
+                                                    g, p),
+                    };
+                },
+            }
+        }
+    };
+
+    thread::scope(|s| {
+        for t in 0..kangaroos {
+            let run = &run;
+            let offset = &spacing * t;
+            let kangaroo = if t % 2 == 0 {
+                Kangaroo::new(Kind::Tame, &Mpz::one(), &(&mid + &offset), g, p)
+            } else {
+                Kangaroo::new(Kind::Wild, y, &offset, g, p)
+            };
+            s.spawn(move || run(kangaroo, t));
+        }
+    });
+
+    let x = found.into_inner().unwrap();
+    x.ok_or_else(|| not_found(a, b))
+}
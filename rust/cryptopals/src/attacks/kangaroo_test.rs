@@ -0,0 +1,143 @@
+extern crate gmp;
+extern crate rand;
+
+use std::time::Instant;
+
+use self::gmp::mpz::Mpz;
+use attacks::dh_subgroup::subgroup_residues;
+use attacks::kangaroo::{kangaroo, kangaroo_parallel, Jumps};
+use dh::group::{DHGroup, NAMED_GROUPS};
+use dh::responder::{DHResponder, DH_RESPONDER_CONTEXT};
+use dh::test::smooth_test_group;
+use error::Error;
+use util::{random_mpz_bits, random_mpz_below, seeded_rng};
+
+const THREADS: usize = 4;
+
+fn jumps_test() {
+    let jumps = Jumps::powers_of_two(4).unwrap();
+    assert_eq!(jumps.sizes(), &[Mpz::from(1u64), Mpz::from(2u64),
+                                Mpz::from(4u64), Mpz::from(8u64)]);
+    assert_eq!(jumps.mean(), Mpz::from(3u64));
+
+    // sqrt(2^40) / 2 = 2^19 for a pair, twice that for four kangaroos
+    let width = Mpz::one() << 40;
+    let pair = Jumps::for_interval(&width, 2).unwrap().mean();
+    assert!(pair >= Mpz::one() << 19 && pair < Mpz::one() << 20, "{}", pair);
+    let four = Jumps::for_interval(&width, 4).unwrap().mean();
+    assert!(four >= Mpz::one() << 20 && four < Mpz::one() << 21, "{}", four);
+
+    assert!(kangaroo(&Mpz::from(2u64), &Mpz::from(4u64), &Mpz::from(11u64),
+                     &Mpz::from(3u64), &Mpz::one(), &jumps).is_err());
+
+    // bad arguments are errors rather than panics
+    let bad = |r: Result<Jumps, Error>| match r {
+        Err(Error::AttackFailed(_)) => {},
+        other => panic!("expected bad jumps, got {:?}", other),
+    };
+    bad(Jumps::new(&[]));
+    bad(Jumps::new(&[Mpz::one(), Mpz::zero()]));
+    bad(Jumps::powers_of_two(0));
+    let (g, y, p) = (Mpz::from(2u64), Mpz::from(4u64), Mpz::from(11u64));
+    let (a, b) = (Mpz::zero(), Mpz::from(10u64));
+    assert!(kangaroo_parallel(&g, &y, &p, &a, &b, &jumps, 1).is_err());
+    let huge = Jumps::new(&[Mpz::one() << 70]).unwrap();
+    assert!(kangaroo(&g, &y, &p, &a, &b, &huge).is_err());
+}
+
+// a 40 bit x somewhere in [a, a + 2^40]
+fn interval<R: rand::Rng>(rng: &mut R, a: &Mpz) -> (Mpz, Mpz, Mpz) {
+    let width = Mpz::one() << 40;
+    let x = a + random_mpz_below(rng, &(&width + Mpz::one()));
+    (a.clone(), a + width, x)
+}
+
+fn kangaroo_serial_test() {
+    let mut rng = seeded_rng(1);
+    let group = smooth_test_group();
+    let jumps = Jumps::for_interval(&(Mpz::one() << 40), 2).unwrap();
+
+    let (a, b, x) = interval(&mut rng, &(Mpz::one() << 80));
+    let y = group.g.powm(&x, &group.p);
+    let start = Instant::now();
+    let found = kangaroo(&group.g, &y, &group.p, &a, &b, &jumps).unwrap();
+    assert_eq!(found, x);
+    println!("kangaroo: 40 bit log in {:?}", start.elapsed());
+
+    // the ends of a smaller interval, with the 2^(y mod k) jumps of the
+    // challenge picked by hand
+    let jumps = Jumps::powers_of_two(14).unwrap();
+    let (a, b) = (Mpz::from(1000u64), Mpz::from(1000u64) + (Mpz::one() << 24));
+    for x in &[a.clone(), b.clone()] {
+        let y = group.g.powm(x, &group.p);
+        assert_eq!(kangaroo(&group.g, &y, &group.p, &a, &b, &jumps),
+                   Ok(x.clone()));
+    }
+
+    // and something that isn't there at all
+    let y = group.g.powm(&(&b + (Mpz::one() << 30)), &group.p);
+    match kangaroo(&group.g, &y, &group.p, &a, &b, &jumps) {
+        Err(Error::AttackFailed(_)) => {},
+        other => panic!("expected no log, got {:?}", other),
+    }
+
+    println!("Finished kangaroo tests");
+}
+
+fn kangaroo_parallel_test() {
+    let mut rng = seeded_rng(2);
+    let width = Mpz::one() << 40;
+    let jumps = Jumps::for_interval(&width, THREADS).unwrap();
+
+    // ffdhe2048 takes a while with 2048 bit multiplications
+    for group in &[smooth_test_group(), DHGroup::named(NAMED_GROUPS[6])] {
+        let a = random_mpz_bits(&mut rng, 64);
+        let (a, b, x) = interval(&mut rng, &a);
+        let y = group.g.powm(&x, &group.p);
+        let start = Instant::now();
+        let found = kangaroo_parallel(&group.g, &y, &group.p, &a, &b, &jumps,
+                                      THREADS).unwrap();
+        assert_eq!(found, x);
+        println!("parallel kangaroo: 40 bit log mod a {} bit p in {:?}",
+                 group.p.bit_length(), start.elapsed());
+    }
+
+    println!("Finished parallel kangaroo tests");
+}
+
+// challenge 58: the responder's group doesn't have enough small subgroups
+// for all of x, so get x = n mod r from them and kangaroo the rest. With
+// x = n + m r, y g^-n = (g^r)^m and m is at most q / r.
+fn subgroup_kangaroo_test() {
+    let mut rng = seeded_rng(3);
+    let group = smooth_test_group();
+    let q = group.q.clone().unwrap();
+    let x = random_mpz_below(&mut rng, &q);
+    let unchecked = DHGroup::new(&group.p, &group.g, None).unwrap();
+    let responder = DHResponder::new(unchecked, &x);
+    let y = responder.public();
+
+    // factors up to 1951 give a 91 bit r, leaving 37 bits of m
+    let (n, r) = subgroup_residues(&group.p, &q, 1952, DH_RESPONDER_CONTEXT,
+                                   &mut rng, |h: &Mpz| responder.respond(h))
+        .unwrap();
+    assert!(r < q);
+    let g_r = group.g.powm(&r, &group.p);
+    let g_n_inv = group.g.powm(&n, &group.p).invert(&group.p).unwrap();
+    let y_m = (&y * g_n_inv).modulus(&group.p);
+    let max_m = &q / &r;
+
+    let jumps = Jumps::for_interval(&max_m, THREADS).unwrap();
+    let m = kangaroo_parallel(&g_r, &y_m, &group.p, &Mpz::zero(), &max_m,
+                              &jumps, THREADS).unwrap();
+    assert_eq!(&n + &m * &r, x);
+
+    println!("Finished subgroup and kangaroo tests");
+}
+
+pub fn kangaroo_tests() {
+    jumps_test();
+    kangaroo_serial_test();
+    kangaroo_parallel_test();
+    subgroup_kangaroo_test();
+}
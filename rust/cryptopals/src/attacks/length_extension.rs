@@ -0,0 +1,36 @@
+use merkle_damgard::DigestIsState;
+
+/// mac is H(key || orig) for a key we don't know but whose length we
+/// guessed. Returns (orig || glue padding || suffix, its mac), and the
+/// forged mac is right whenever the key length was.
+pub fn length_extend<H: DigestIsState>(mac: &[u8], key_len: usize,
+                                       orig: &[u8],
+                                       suffix: &[u8]) -> (Vec<u8>, Vec<u8>) {
+    // the hash already went through key || orig || glue to get mac, so
+    // start from there and the final padding comes out right on its own
+    let glue = H::padding((key_len + orig.len()) as u64);
+    let hashed_len = key_len + orig.len() + glue.len();
+    let mut hash = H::import_state(mac, hashed_len as u64);
+    hash.update(suffix);
+
+    let mut msg = orig.to_vec();
+    msg.extend_from_slice(&glue);
+    msg.extend_from_slice(suffix);
+    (msg, hash.finish())
+}
+
+/// Tries every key length up to max_key_len until verify takes the
+/// forgery. Returns (key_len, msg, mac).
+pub fn length_extend_any_key_len<H, F>(mac: &[u8], orig: &[u8],
+                                       suffix: &[u8], max_key_len: usize,
+                                       mut verify: F)
+                                       -> Option<(usize, Vec<u8>, Vec<u8>)>
+        where H: DigestIsState, F: FnMut(&[u8], &[u8]) -> bool {
+    for key_len in 0..(max_key_len + 1) {
+        let (msg, forged) = length_extend::<H>(mac, key_len, orig, suffix);
+        if verify(&msg, &forged) {
+            return Some((key_len, msg, forged));
+        }
+    }
+    None
+}
@@ -0,0 +1,109 @@
+use attacks::length_extension::length_extend_any_key_len;
+use mac::{cat_mac, cat_mac_verify, md_pad};
+use md4::Md4;
+use merkle_damgard::{MerkleDamgard, DigestIsState};
+use sha1::Sha1;
+use sha2::{Sha224, Sha256, Sha384, Sha512};
+use util::rand_bytes_range;
+
+fn length_extension_test<H: DigestIsState>(name: &str) {
+    let orig = "comment1=cooking%20MCs;userdata=foo;comment2=%20like%20a%20pound%20of%20bacon";
+    let suffix = ";admin=true";
+
+    let key = rand_bytes_range(1, 64);
+    println!("{} length extension test with key len {}", name, key.len());
+    let mac = cat_mac::<H>(&key, orig.as_bytes());
+
+    let found = length_extend_any_key_len::<H, _>(
+        &mac, orig.as_bytes(), suffix.as_bytes(), 64,
+        |msg: &[u8], forged: &[u8]| cat_mac_verify::<H>(&key, msg, forged));
+    let (key_len, msg, forged) = match found {
+        Some(result) => result,
+        None => panic!("{} length extension found no key length", name),
+    };
+    assert_eq!(key_len, key.len(), "{} length extension key len", name);
+    assert!(msg.ends_with(suffix.as_bytes()));
+    assert_eq!(cat_mac::<H>(&key, &msg), forged);
+}
+
+// exporting the state after msg || padding gets the state hashing msg
+// alone ended on, and padding always fills out the last block
+fn export_test<H: MerkleDamgard>(name: &str) {
+    let msg = "beep boop";
+    let padded = md_pad::<H>(msg.as_bytes());
+
+    let mut hash = H::new();
+    hash.update(&padded);
+    let (state, len) = hash.export_state();
+    assert_eq!(len as usize, padded.len(), "{} export_state len", name);
+    // for the untruncated hashes the state is just the digest
+    if state.len() == H::DIGEST_LENGTH {
+        assert_eq!(state, H::hash(msg.as_bytes()), "{} export_state", name);
+    }
+
+    // export only has whole blocks in it
+    hash.update(b"meow");
+    let (_, len) = hash.export_state();
+    assert_eq!(len as usize, padded.len(), "{} export_state len", name);
+
+    for msg_len in 0..(H::BLOCK_SIZE as u64 * 2) {
+        let pad_len = H::padding(msg_len).len() as u64;
+        assert!((msg_len + pad_len).is_multiple_of(H::BLOCK_SIZE as u64),
+                "{} padding for {} bytes is {}", name, msg_len, pad_len);
+    }
+}
+
+// exporting partway through and importing that into a fresh hash should
+// carry on as if nothing happened, truncated or not
+fn import_test<H: MerkleDamgard>(name: &str) {
+    let msg: Vec<u8> = (0..(H::BLOCK_SIZE * 3 + 5)).map(|b| b as u8).collect();
+    let split = H::BLOCK_SIZE * 2;
+
+    let mut hash = H::new();
+    hash.update(&msg[..split]);
+    let (state, len) = hash.export_state();
+    assert_eq!(len as usize, split, "{} export_state len", name);
+
+    let mut hash = H::import_state(&state, len);
+    hash.update(&msg[split..]);
+    assert_eq!(hash.finish(), H::hash(&msg), "{} import test failed", name);
+}
+
+// hashing msg || padding then picking up from the digest should be the
+// same as just hashing everything
+fn fixate_test<H: DigestIsState>(name: &str) {
+    let msgs = ["beep boop", "meow"];
+    let mut padded = md_pad::<H>(msgs[0].as_bytes());
+    let padded_len = padded.len();
+    padded.extend_from_slice(msgs[1].as_bytes());
+    let expected = H::hash(&padded);
+
+    let first = H::hash(msgs[0].as_bytes());
+    let mut hash = H::import_state(&first, padded_len as u64);
+    hash.update(msgs[1].as_bytes());
+    assert_eq!(hash.finish(), expected, "{} fixate test failed", name);
+}
+
+pub fn length_extension_tests() {
+    export_test::<Sha1>("sha1");
+    export_test::<Md4>("md4");
+    export_test::<Sha224>("sha224");
+    export_test::<Sha256>("sha256");
+    export_test::<Sha384>("sha384");
+    export_test::<Sha512>("sha512");
+    import_test::<Sha1>("sha1");
+    import_test::<Md4>("md4");
+    import_test::<Sha224>("sha224");
+    import_test::<Sha256>("sha256");
+    import_test::<Sha384>("sha384");
+    import_test::<Sha512>("sha512");
+    fixate_test::<Sha1>("sha1");
+    fixate_test::<Md4>("md4");
+    fixate_test::<Sha256>("sha256");
+    fixate_test::<Sha512>("sha512");
+    length_extension_test::<Sha1>("sha1");
+    length_extension_test::<Md4>("md4");
+    length_extension_test::<Sha256>("sha256");
+    length_extension_test::<Sha512>("sha512");
+    println!("Finished length extension tests");
+}
@@ -0,0 +1,37 @@
+pub mod bleichenbacher;
+mod bleichenbacher_test;
+pub mod dh_subgroup;
+mod dh_subgroup_test;
+pub mod ecb_byte_at_a_time;
+mod ecb_byte_at_a_time_test;
+pub mod kangaroo;
+mod kangaroo_test;
+pub mod length_extension;
+mod length_extension_test;
+pub mod padding_oracle;
+mod padding_oracle_test;
+pub mod rsa_parity;
+mod rsa_parity_test;
+pub mod weak_rsa;
+mod weak_rsa_test;
+
+use self::bleichenbacher_test::bleichenbacher_tests;
+use self::dh_subgroup_test::dh_subgroup_tests;
+use self::ecb_byte_at_a_time_test::ecb_byte_at_a_time_test;
+use self::kangaroo_test::kangaroo_tests;
+use self::length_extension_test::length_extension_tests;
+use self::padding_oracle_test::padding_oracle_test;
+use self::rsa_parity_test::rsa_parity_tests;
+use self::weak_rsa_test::weak_rsa_tests;
+
+pub fn attacks_test() {
+    padding_oracle_test();
+    ecb_byte_at_a_time_test();
+    length_extension_tests();
+    bleichenbacher_tests();
+    rsa_parity_tests();
+    weak_rsa_tests();
+    dh_subgroup_tests();
+    kangaroo_tests();
+    println!("Finished attacks tests");
+}
@@ -0,0 +1,143 @@
+use aes::AES_BLOCK_SIZE;
+use error::{Error, Result};
+use pkcs7::{pkcs7_pad, pkcs7_unpad_copy};
+use util::rand_bytes;
+use xor::slice_xor;
+
+/// CBC padding oracle attack against anything that will tell us whether
+/// (iv, ciphertext) decrypts to valid pkcs7 padding. Never needs the key.
+pub struct PaddingOracle<F: FnMut(&[u8], &[u8]) -> bool> {
+    oracle: F,
+    block_size: usize,
+    queries: usize,
+}
+
+impl<F: FnMut(&[u8], &[u8]) -> bool> PaddingOracle<F> {
+    pub fn new(oracle: F) -> PaddingOracle<F> {
+        PaddingOracle::with_block_size(oracle, AES_BLOCK_SIZE)
+    }
+
+    pub fn with_block_size(oracle: F, block_size: usize) -> PaddingOracle<F> {
+        // pkcs7 stores the pad length in a byte, and we need a byte in
+        // front of the last one to disambiguate
+        assert!(block_size > 1 && block_size < 256,
+                "bad block size for pkcs7 {}", block_size);
+        PaddingOracle {
+            oracle,
+            block_size,
+            queries: 0,
+        }
+    }
+
+    /// Number of times the oracle has been asked so far
+    pub fn queries(&self) -> usize {
+        self.queries
+    }
+
+    fn query(&mut self, iv: &[u8], block: &[u8]) -> bool {
+        self.queries += 1;
+        (self.oracle)(iv, block)
+    }
+
+    /// Recovers D(block), i.e. the block decrypted before it's xor'ed with
+    /// the previous ciphertext block.
+    pub fn intermediate_block(&mut self, block: &[u8]) -> Result<Vec<u8>> {
+        let bs = self.block_size;
+        if block.len() != bs {
+            return Err(Error::BadCiphertextLength(block.len()));
+        }
+
+        // we control the iv, so p = D(block) ^ iv. brute force the last
+        // byte until the padding is [1], then set it up for [2, 2] and
+        // brute force the byte before, etc.
+        let mut intermediate = vec!(0u8; bs);
+        let mut iv = vec!(0u8; bs);
+        for i in (0..bs).rev() {
+            let pad = (bs - i) as u8;
+            for j in (i + 1)..bs {
+                iv[j] = intermediate[j] ^ pad;
+            }
+
+            let mut found = None;
+            for b in 0..256 {
+                iv[i] = b as u8;
+                if !self.query(&iv, block) {
+                    continue;
+                }
+                if i == bs - 1 && !self.confirm_last_byte(&iv, block) {
+                    continue;
+                }
+                found = Some(b as u8);
+                break;
+            }
+
+            match found {
+                Some(b) => intermediate[i] = b ^ pad,
+                None => {
+                    return Err(Error::AttackFailed(
+                        format!("no valid padding for byte {}", i)));
+                },
+            };
+        }
+        Ok(intermediate)
+    }
+
+    // valid padding on the last byte is usually [1], but it could be
+    // [2, 2] etc. if the byte in front happens to line up. messing with
+    // that byte only keeps the padding valid when it's really [1].
+    fn confirm_last_byte(&mut self, iv: &[u8], block: &[u8]) -> bool {
+        let mut tweaked = iv.to_vec();
+        tweaked[self.block_size - 2] ^= 0xffu8;
+        self.query(&tweaked, block)
+    }
+
+    /// Plaintext for block, padding and all
+    pub fn decrypt_block(&mut self, prev_block: &[u8],
+                         block: &[u8]) -> Result<Vec<u8>> {
+        if prev_block.len() != self.block_size {
+            return Err(Error::BadCiphertextLength(prev_block.len()));
+        }
+        Ok(slice_xor(&self.intermediate_block(block)?, prev_block))
+    }
+
+    /// Decrypts the whole ciphertext and strips the padding
+    pub fn decrypt(&mut self, iv: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>> {
+        let bs = self.block_size;
+        if iv.len() != bs {
+            return Err(Error::BadIVLength(iv.len()));
+        }
+        if ciphertext.is_empty() || !ciphertext.len().is_multiple_of(bs) {
+            return Err(Error::BadCiphertextLength(ciphertext.len()));
+        }
+
+        let mut padded = Vec::with_capacity(ciphertext.len());
+        let mut prev_block = iv;
+        for block in ciphertext.chunks(bs) {
+            padded.extend(self.decrypt_block(prev_block, block)?);
+            prev_block = block;
+        }
+        pkcs7_unpad_copy(&padded, bs)
+    }
+
+    /// Forges (iv, ciphertext) that decrypts to plaintext. Starts from a
+    /// random last block and works backwards, each intermediate tells us
+    /// what the ciphertext block in front of it has to be.
+    pub fn encrypt(&mut self,
+                   plaintext: &[u8]) -> Result<(Vec<u8>, Vec<u8>)> {
+        let bs = self.block_size;
+        let padded = pkcs7_pad(plaintext, bs);
+
+        let mut blocks = vec!(rand_bytes(bs));
+        for plain_block in padded.chunks(bs).rev() {
+            let intermediate = {
+                let block = &blocks[blocks.len() - 1];
+                self.intermediate_block(block)?
+            };
+            blocks.push(slice_xor(&intermediate, plain_block));
+        }
+
+        blocks.reverse();
+        let iv = blocks.remove(0);
+        Ok((iv, blocks.concat()))
+    }
+}
@@ -0,0 +1,105 @@
+use aes::{AESCipher, AES_BLOCK_SIZE};
+use aes::cbc::AESCipherCBC;
+use attacks::padding_oracle::PaddingOracle;
+use error::Error;
+use pkcs7::pkcs7_unpad;
+use util::{rand_key, rand_bytes, assert_slice_cmp};
+use xor::slice_xor;
+
+fn aes_cbc_padding_oracle_decrypt_test(plaintext: &[u8]) {
+    let key = rand_key();
+    let iv = rand_bytes(AES_BLOCK_SIZE);
+    let cipher = AESCipherCBC::new(&key, &iv).unwrap();
+    let ciphertext = cipher.pad_and_encrypt(plaintext);
+    println!("padding oracle decrypt {:?} with key {:?}", plaintext, key);
+
+    let mut attack = PaddingOracle::new(|iv: &[u8], ciphertext: &[u8]| {
+        pkcs7_unpad(&cipher.decrypt_iv(iv, ciphertext),
+                    AES_BLOCK_SIZE).is_ok()
+    });
+    let decrypted = attack.decrypt(&iv, &ciphertext).unwrap();
+    assert_slice_cmp("padding oracle decrypt", plaintext, &decrypted);
+
+    // at most 256 guesses per byte plus one check per block
+    let blocks = ciphertext.len() / AES_BLOCK_SIZE;
+    assert!(attack.queries() <= blocks * (256 * AES_BLOCK_SIZE + 256),
+            "padding oracle took {} queries", attack.queries());
+}
+
+fn aes_cbc_padding_oracle_encrypt_test() {
+    let key = rand_key();
+    let cipher = AESCipherCBC::new(&key, &[0u8; AES_BLOCK_SIZE]).unwrap();
+    println!("padding oracle encrypt with key {:?}", key);
+
+    let mut attack = PaddingOracle::new(|iv: &[u8], ciphertext: &[u8]| {
+        pkcs7_unpad(&cipher.decrypt_iv(iv, ciphertext),
+                    AES_BLOCK_SIZE).is_ok()
+    });
+    let forged = "comment1=cooking%20MCs;admin=true;comment2=bacon";
+    let (iv, ciphertext) = attack.encrypt(forged.as_bytes()).unwrap();
+    let queries = attack.queries();
+
+    let decrypted = cipher.decrypt_iv(&iv, &ciphertext);
+    assert_slice_cmp("padding oracle encrypt", forged.as_bytes(),
+                     pkcs7_unpad(&decrypted, AES_BLOCK_SIZE).unwrap());
+    println!("padding oracle forged {} bytes in {} queries",
+             forged.len(), queries);
+}
+
+// not every cbc decryptor is aes, D(c) = c ^ key with 8 byte blocks is
+// enough to check nothing depends on AES_BLOCK_SIZE
+fn toy_cbc_padding_oracle_test() {
+    let bs = 8;
+    let key = rand_bytes(bs);
+    let oracle = |iv: &[u8], block: &[u8]| {
+        let plain = slice_xor(&slice_xor(block, &key), iv);
+        pkcs7_unpad(&plain, bs).is_ok()
+    };
+
+    // line D(block) up so a zero iv byte in front of the last one decrypts
+    // to 2, [2, 2] is then valid padding for the last byte too
+    let mut block = rand_bytes(bs);
+    block[bs - 2] = key[bs - 2] ^ 2;
+    let mut attack = PaddingOracle::with_block_size(oracle, bs);
+    let intermediate = attack.intermediate_block(&block).unwrap();
+    assert_slice_cmp("toy padding oracle intermediate",
+                     &slice_xor(&block, &key), &intermediate);
+
+    assert_eq!(attack.decrypt(&[0u8; 8], &[0u8; 12]),
+               Err(Error::BadCiphertextLength(12)));
+    assert_eq!(attack.decrypt(&[0u8; 7], &[0u8; 8]),
+               Err(Error::BadIVLength(7)));
+
+    // an oracle that never says yes can't be attacked
+    let mut attack = PaddingOracle::with_block_size(|_: &[u8], _: &[u8]| {
+        false
+    }, bs);
+    match attack.decrypt(&[0u8; 8], &[0u8; 8]) {
+        Err(Error::AttackFailed(_)) => {},
+        other => panic!("padding oracle expected failure got {:?}", other),
+    };
+    assert_eq!(attack.queries(), 256);
+}
+
+pub fn padding_oracle_test() {
+    toy_cbc_padding_oracle_test();
+    aes_cbc_padding_oracle_encrypt_test();
+    let plaintexts = [
+        "1234567890123\x03\x036", // test disambiguating [1] vs [3, 3, 3]
+        "\x16\x16\x16\x16\x16\x16\x16\x16\x16\x16\x16\x16\x16\x16\x16",
+        "MDAwMDAwTm93IHRoYXQgdGhlIHBhcnR5IGlzIGp1bXBpbmc=",
+        "MDAwMDAxV2l0aCB0aGUgYmFzcyBraWNrZWQgaW4gYW5kIHRoZSBWZWdhJ3MgYXJlIHB1bXBpbic=",
+        "MDAwMDAyUXVpY2sgdG8gdGhlIHBvaW50LCB0byB0aGUgcG9pbnQsIG5vIGZha2luZw==",
+        "MDAwMDAzQ29va2luZyBNQydzIGxpa2UgYSBwb3VuZCBvZiBiYWNvbg==",
+        "MDAwMDA0QnVybmluZyAnZW0sIGlmIHlvdSBhaW4ndCBxdWljayBhbmQgbmltYmxl",
+        "MDAwMDA1SSBnbyBjcmF6eSB3aGVuIEkgaGVhciBhIGN5bWJhbA==",
+        "MDAwMDA2QW5kIGEgaGlnaCBoYXQgd2l0aCBhIHNvdXBlZCB1cCB0ZW1wbw==",
+        "MDAwMDA3SSdtIG9uIGEgcm9sbCwgaXQncyB0aW1lIHRvIGdvIHNvbG8=",
+        "MDAwMDA4b2xsaW4nIGluIG15IGZpdmUgcG9pbnQgb2g=",
+        "MDAwMDA5aXRoIG15IHJhZy10b3AgZG93biBzbyBteSBoYWlyIGNhbiBibG93",
+    ];
+    for plaintext in &plaintexts {
+        aes_cbc_padding_oracle_decrypt_test(plaintext.as_bytes());
+    }
+    println!("Finished padding oracle tests");
+}
@@ -0,0 +1,53 @@
+extern crate gmp;
+
+use self::gmp::mpz::Mpz;
+use rsa::PublicKey;
+use util::{bytes_to_mpz, mpz_bytes, mpz_byte_len, mpz_bytes_zero_pad};
+
+/// RSA parity oracle attack (challenge 46). Doubling the plaintext by
+/// multiplying the ciphertext by 2^e tells us from the parity whether 2m
+/// wrapped around n, i.e. which half of the current range m is in. The
+/// oracle gets k byte ciphertexts and says whether they decrypt to
+/// something even.
+pub fn rsa_parity_decrypt<F>(pub_key: &PublicKey, ciphertext: &[u8],
+                             is_even: F) -> Vec<u8>
+        where F: FnMut(&[u8]) -> bool {
+    rsa_parity_decrypt_with_progress(pub_key, ciphertext, is_even,
+                                     |_, _| {})
+}
+
+/// Same as rsa_parity_decrypt, calling progress(bit, upper bound) after
+/// each query. Printing the upper bound shows the plaintext appearing
+/// from the left.
+pub fn rsa_parity_decrypt_with_progress<F, P>(pub_key: &PublicKey,
+                                              ciphertext: &[u8],
+                                              mut is_even: F,
+                                              mut progress: P) -> Vec<u8>
+        where F: FnMut(&[u8]) -> bool,
+              P: FnMut(usize, &Mpz) {
+    let n = &pub_key.n;
+    let k = mpz_byte_len(n);
+    let bits = n.bit_length();
+    let double = Mpz::from(2u64).powm(&pub_key.e, n);
+
+    // m is in [n * lo / 2^i, n * (lo + 1) / 2^i), kept as integers so
+    // nothing gets lost to rounding
+    let mut lo = Mpz::zero();
+    let mut c = bytes_to_mpz(ciphertext);
+    for i in 1..bits + 1 {
+        c = (&c * &double).modulus(n);
+        lo = &lo << 1;
+        if !is_even(&mpz_bytes_zero_pad(&c, k)) {
+            // 2m wrapped, so odd now, m was in the top half
+            lo += Mpz::one();
+        }
+
+        let hi = (n * (&lo + Mpz::one())) >> i;
+        progress(i, &hi);
+    }
+
+    // the range is now less than 1 wide, m is the integer in it
+    let den = Mpz::one() << bits;
+    let m = (n * &lo + &den - Mpz::one()) / &den;
+    mpz_bytes(&m)
+}
@@ -0,0 +1,63 @@
+extern crate gmp;
+
+use self::gmp::mpz::Mpz;
+use attacks::rsa_parity::{rsa_parity_decrypt,
+                          rsa_parity_decrypt_with_progress};
+use base64::base64_decode;
+use rsa::new_keypair;
+use util::{bytes_to_mpz, mpz_bytes, rand_bytes, assert_slice_cmp};
+
+fn rsa_parity_test() {
+    let (pub_key, priv_key) = new_keypair(1024);
+    let msg = base64_decode(concat!(
+        "VGhhdCdzIHdoeSBJIGZvdW5kIHlvdSBkb24ndCBwbGF5IGFyb3VuZCB3aXRo",
+        "IHRoZSBGdW5reSBDb2xkIE1lZGluYQ==")).unwrap();
+    let ciphertext = pub_key.encrypt(&msg);
+
+    let mut queries = 0;
+    let mut last_hi: Option<Mpz> = None;
+    let decrypted = rsa_parity_decrypt_with_progress(
+        &pub_key, &ciphertext,
+        |c: &[u8]| {
+            queries += 1;
+            priv_key.decrypt_mpz(&bytes_to_mpz(c)).unwrap().modulus(
+                &Mpz::from(2u64)) == Mpz::zero()
+        },
+        |bit: usize, hi: &Mpz| {
+            if let Some(ref last) = last_hi {
+                assert!(hi <= last, "rsa parity bound went up at {}", bit);
+            }
+            if bit.is_multiple_of(128) {
+                println!("rsa parity bit {}: {:?}", bit,
+                         String::from_utf8_lossy(&mpz_bytes(hi)));
+            }
+            last_hi = Some(hi.clone());
+        });
+    assert_slice_cmp("rsa parity decrypt", &msg, &decrypted);
+    assert_eq!(queries, pub_key.n.bit_length());
+    assert_eq!(last_hi.map(|hi| mpz_bytes(&hi)), Some(msg));
+}
+
+// plaintexts right up against n are where rounding would bite
+fn rsa_parity_edge_test() {
+    let (pub_key, priv_key) = new_keypair(512);
+    let n_minus_one = &pub_key.n - Mpz::one();
+    for m in &[Mpz::one(), Mpz::from(2u64), n_minus_one.clone(),
+               &n_minus_one - Mpz::one(),
+               bytes_to_mpz(&rand_bytes(32))] {
+        let ciphertext = pub_key.encrypt(&mpz_bytes(m));
+        let decrypted = rsa_parity_decrypt(&pub_key, &ciphertext,
+                                           |c: &[u8]| {
+            priv_key.decrypt_mpz(&bytes_to_mpz(c)).unwrap().modulus(
+                &Mpz::from(2u64)) == Mpz::zero()
+        });
+        assert_eq!(bytes_to_mpz(&decrypted), *m,
+                   "rsa parity decrypt failed for {}", m);
+    }
+}
+
+pub fn rsa_parity_tests() {
+    rsa_parity_test();
+    rsa_parity_edge_test();
+    println!("Finished RSA parity oracle tests");
+}
@@ -0,0 +1,131 @@
+extern crate gmp;
+
+use self::gmp::mpz::Mpz;
+use error::{Error, Result};
+use rsa::{PublicKey, PrivateKey};
+
+// Some(root) if n is a perfect square
+fn exact_sqrt(n: &Mpz) -> Option<Mpz> {
+    if *n < Mpz::zero() {
+        return None;
+    }
+    let root = n.sqrt();
+    if &root * &root == *n { Some(root) } else { None }
+}
+
+// the private key once we know p divides n
+fn key_from_factor(pub_key: &PublicKey, p: &Mpz) -> Result<PrivateKey> {
+    let q = &pub_key.n / p;
+    if *p <= Mpz::one() || &q * p != pub_key.n {
+        return Err(Error::AttackFailed(format!("{} isn't a factor", p)));
+    }
+    PrivateKey::from_primes(&pub_key.e, p, &q)
+}
+
+/// Wiener's attack: if d < n^(1/4) / 3 then k/d, where ed = 1 + k phi,
+/// is one of the convergents of the continued fraction of e/n. For each
+/// convergent guess phi = (ed - 1) / k and see if x^2 - (n - phi + 1)x + n
+/// has p and q as its roots.
+pub fn wiener(pub_key: &PublicKey) -> Result<PrivateKey> {
+    let (e, n) = (&pub_key.e, &pub_key.n);
+    let four = Mpz::from(4u64);
+
+    // h/k convergents of e/n, starting from the usual 0/1 and 1/0
+    let (mut h_prev, mut h) = (Mpz::zero(), Mpz::one());
+    let (mut k_prev, mut k) = (Mpz::one(), Mpz::zero());
+    let (mut num, mut den) = (e.clone(), n.clone());
+    while !den.is_zero() {
+        let a = &num / &den;
+        let rem = &num - &a * &den;
+        num = den;
+        den = rem;
+
+        let h_next = &a * &h + &h_prev;
+        let k_next = &a * &k + &k_prev;
+        h_prev = h;
+        h = h_next;
+        k_prev = k;
+        k = k_next;
+
+        // h is the guess for k and k the guess for d
+        if h.is_zero() {
+            continue;
+        }
+        let ed_1 = e * &k - Mpz::one();
+        if !(&ed_1 % &h).is_zero() {
+            continue;
+        }
+        let phi = &ed_1 / &h;
+        let s = n - &phi + Mpz::one();
+        let root = match exact_sqrt(&(&s * &s - &four * n)) {
+            Some(root) => root,
+            None => continue,
+        };
+        let p = (&s + &root) >> 1;
+        if &p * ((&s - &root) >> 1) == *n {
+            return key_from_factor(pub_key, &p);
+        }
+    }
+    Err(Error::AttackFailed("no convergent gave a factorization".to_string()))
+}
+
+/// Bernstein's batch GCD: every modulus that shares a prime with any of
+/// the others, in about the time of multiplying them all together.
+/// Builds a product tree, pushes the product back down as remainders mod
+/// each n^2, and gcd(n, (P mod n^2) / n) at each leaf is what n shares
+/// with the rest. Results line up with keys, None for the ones that
+/// don't share anything (or are exact duplicates of another).
+pub fn batch_gcd(keys: &[PublicKey]) -> Vec<Option<PrivateKey>> {
+    if keys.is_empty() {
+        return Vec::new();
+    }
+
+    let leaves: Vec<Mpz> = keys.iter().map(|k| k.n.clone()).collect();
+    let mut tree = vec!(leaves);
+    while tree.last().unwrap().len() > 1 {
+        let next = tree.last().unwrap().chunks(2).map(|pair| {
+            pair.iter().fold(Mpz::one(), |acc, n| acc * n)
+        }).collect();
+        tree.push(next);
+    }
+
+    let mut rems = tree.pop().unwrap();
+    while let Some(level) = tree.pop() {
+        rems = level.iter().enumerate().map(|(i, n)| {
+            rems[i / 2].modulus(&(n * n))
+        }).collect();
+    }
+
+    keys.iter().zip(rems).map(|(key, rem)| {
+        let mut g = (&rem / &key.n).gcd(&key.n);
+        if g == key.n {
+            // shares both primes, so with more than one other modulus;
+            // pick them apart one at a time
+            g = keys.iter().map(|other| other.n.gcd(&key.n))
+                    .find(|g| *g != Mpz::one() && *g != key.n)?;
+        }
+        if g == Mpz::one() {
+            return None;
+        }
+        key_from_factor(key, &g).ok()
+    }).collect()
+}
+
+/// Fermat's method: n = a^2 - b^2 = (a + b)(a - b), so walk a up from
+/// sqrt(n) until a^2 - n is a square. Takes about (p - q)^2 / 8 sqrt(n)
+/// steps, so only gets anywhere when p and q are close together.
+pub fn fermat_factor(pub_key: &PublicKey,
+                     max_steps: usize) -> Result<PrivateKey> {
+    let n = &pub_key.n;
+    let mut a = n.sqrt();
+    if &a * &a != *n {
+        a += Mpz::one();
+    }
+    for _ in 0..max_steps {
+        if let Some(b) = exact_sqrt(&(&a * &a - n)) {
+            return key_from_factor(pub_key, &(&a - &b));
+        }
+        a += Mpz::one();
+    }
+    Err(Error::AttackFailed(format!("no factor in {} steps", max_steps)))
+}
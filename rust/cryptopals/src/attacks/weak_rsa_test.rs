@@ -0,0 +1,119 @@
+extern crate gmp;
+extern crate rand;
+
+use self::gmp::mpz::Mpz;
+use self::rand::XorShiftRng;
+use attacks::weak_rsa::{wiener, batch_gcd, fermat_factor};
+use rsa::{PublicKey, PrivateKey};
+use util::{random_prime, random_rsa_prime, random_mpz_bits, seeded_rng,
+           PrimalityTest};
+
+const E: u64 = 65537;
+
+fn rsa_prime(rng: &mut XorShiftRng, bits: usize) -> Mpz {
+    random_rsa_prime(rng, bits, &Mpz::from(E), PrimalityTest::BailliePsw)
+}
+
+fn key_from_primes(p: &Mpz, q: &Mpz) -> (PublicKey, PrivateKey) {
+    let priv_key = PrivateKey::from_primes(&Mpz::from(E), p, q).unwrap();
+    (priv_key.public_key().unwrap(), priv_key)
+}
+
+// a key with a d_bits long d, and e whatever that makes it
+fn small_d_key(rng: &mut XorShiftRng, bits: usize,
+               d_bits: usize) -> (PublicKey, Mpz) {
+    loop {
+        let p = rsa_prime(rng, bits / 2);
+        let q = rsa_prime(rng, bits / 2);
+        let phi = (&p - Mpz::one()) * (&q - Mpz::one());
+        let mut d = random_mpz_bits(rng, d_bits);
+        d.setbit(d_bits - 1);
+        if let Some(e) = d.invert(&phi) {
+            return (PublicKey::new(&e, &(&p * &q)), d);
+        }
+    }
+}
+
+fn wiener_test() {
+    let mut rng = seeded_rng(1);
+    for &bits in &[256, 1024, 2048] {
+        // comfortably under n^(1/4) / 3
+        let (pub_key, d) = small_d_key(&mut rng, bits, bits / 4 - 8);
+        let priv_key = wiener(&pub_key).unwrap();
+        assert_eq!(priv_key.d, d);
+        assert_eq!(priv_key.public_key(), Some(pub_key.clone()));
+    }
+
+    // a normal key has a d about as big as n
+    let (pub_key, _) = key_from_primes(&rsa_prime(&mut rng, 512),
+                                       &rsa_prime(&mut rng, 512));
+    assert!(wiener(&pub_key).is_err());
+
+    // and a d just over n^(1/4) / 3, about 254 bits for a 1024 bit n, is
+    // out of reach too
+    let (pub_key, _) = small_d_key(&mut rng, 1024, 1024 / 4 + 8);
+    assert!(wiener(&pub_key).is_err());
+
+    println!("Finished Wiener tests");
+}
+
+fn batch_gcd_test() {
+    let mut rng = seeded_rng(2);
+    let primes: Vec<Mpz> = (0..10).map(|_| rsa_prime(&mut rng, 256))
+                                  .collect();
+
+    // 1 shares a prime with 0, 3 with 2, 4 with both 2 and 3 so gcd with
+    // the product is all of it, 5 doesn't share anything, and 7 is 6 over
+    // again which gcd can't split
+    let pairs = [(0, 1), (0, 2), (3, 4), (3, 5), (4, 5), (6, 7), (8, 9),
+                 (8, 9)];
+    let expected = [true, true, true, true, true, false, false, false];
+    let keys: Vec<(PublicKey, PrivateKey)> = pairs.iter().map(|&(i, j)| {
+        key_from_primes(&primes[i], &primes[j])
+    }).collect();
+    let pub_keys: Vec<PublicKey> = keys.iter().map(|k| k.0.clone())
+                                       .collect();
+
+    let found = batch_gcd(&pub_keys);
+    assert_eq!(found.len(), keys.len());
+    for (i, found) in found.iter().enumerate() {
+        match *found {
+            Some(ref key) => {
+                assert!(expected[i], "key {} shouldn't be factored", i);
+                assert_eq!(key.d, keys[i].1.d);
+            },
+            None => assert!(!expected[i], "key {} should be factored", i),
+        }
+    }
+
+    assert!(batch_gcd(&[]).is_empty());
+    assert_eq!(batch_gcd(&pub_keys[..1]), vec!(None));
+
+    println!("Finished batch GCD tests");
+}
+
+fn fermat_test() {
+    let mut rng = seeded_rng(3);
+    for &bits in &[512, 1024, 2048] {
+        // q is the next prime after p plus a bit
+        let p = rsa_prime(&mut rng, bits / 2);
+        let gap = random_mpz_bits(&mut rng, bits / 4);
+        let q = (&p + gap).nextprime();
+        let (pub_key, priv_key) = key_from_primes(&p, &q);
+        let found = fermat_factor(&pub_key, 1000).unwrap();
+        assert_eq!(found.d, priv_key.d);
+    }
+
+    let p = random_prime(&mut rng, 512, PrimalityTest::BailliePsw);
+    let q = random_prime(&mut rng, 512, PrimalityTest::BailliePsw);
+    let (pub_key, _) = key_from_primes(&p, &q);
+    assert!(fermat_factor(&pub_key, 1000).is_err());
+
+    println!("Finished Fermat factorization tests");
+}
+
+pub fn weak_rsa_tests() {
+    wiener_test();
+    batch_gcd_test();
+    fermat_test();
+}
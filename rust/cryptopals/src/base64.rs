@@ -2,6 +2,7 @@ use std::fs::File;
 use std::io::BufReader;
 use std::io::prelude::*;
 use std::vec::Vec;
+use error::{Error, Result};
 use hex::bytes_to_hex;
 
 const BASE64_VAL_CHAR: [char; 64] = [
@@ -81,7 +82,7 @@ pub fn base64_encode(buf : &[u8]) -> String {
     s
 }
 
-fn base64_char_to_byte(c: u8) -> u8 {
+fn base64_char_to_byte(c: u8) -> Result<u8> {
     const UPPER_A: u8 = 'A' as u8;
     const UPPER_Z: u8 = 'Z' as u8;
     const LOWER_A: u8 = 'a' as u8;
@@ -96,36 +97,45 @@ fn base64_char_to_byte(c: u8) -> u8 {
         DIGIT_0...DIGIT_9 => (c - DIGIT_0) + 52,
         PLUS => 62,
         SLASH => 63,
-        // XXX: should error handle better
-        _ => {
-            panic!("got unexpected base64 byte {}", c as char);
-        }
+        _ => return Err(Error::InvalidBase64(c as char)),
     };
-    res
+    Ok(res)
 }
 
-pub fn base64_decode(s: &str) -> Vec<u8> {
-    assert!(s.len() % 4 == 0);
+pub fn base64_decode(s: &str) -> Result<Vec<u8>> {
     const EQUALS: u8 = '=' as u8;
+    let bytes = s.as_bytes();
+    if !bytes.len().is_multiple_of(4) {
+        // report the char that's hanging off the end
+        return Err(Error::InvalidBase64(bytes[bytes.len() - 1] as char));
+    }
 
     let mut vec: Vec<u8> = Vec::new();
-    for chunk in s.as_bytes().chunks(4) {
-        let b0 = base64_char_to_byte(chunk[0]);
-        let b1 = base64_char_to_byte(chunk[1]);
+    let num_chunks = bytes.len() / 4;
+    for (i, chunk) in bytes.chunks(4).enumerate() {
+        let b0 = base64_char_to_byte(chunk[0])?;
+        let b1 = base64_char_to_byte(chunk[1])?;
         vec.push((b0 << 2) | ((b1 >> 4) & 0b11));
 
+        // padding is only allowed at the very end
+        if (chunk[2] == EQUALS || chunk[3] == EQUALS) && i != num_chunks - 1 {
+            return Err(Error::InvalidBase64(EQUALS as char));
+        }
+
         if chunk[2] != EQUALS {
-            let b2 = base64_char_to_byte(chunk[2]);
+            let b2 = base64_char_to_byte(chunk[2])?;
             vec.push(((b1 & 0b1111) << 4) | ((b2 >> 2) & 0b1111));
 
-            // XXX: error if 4th is EQUALS but 3rd is not
             if chunk[3] != EQUALS {
-                let b3 = base64_char_to_byte(chunk[3]);
+                let b3 = base64_char_to_byte(chunk[3])?;
                 vec.push(((b2 & 0b11) << 6) | b3);
             }
+        } else if chunk[3] != EQUALS {
+            // 3rd is EQUALS but 4th is not
+            return Err(Error::InvalidBase64(chunk[3] as char));
         }
     }
-    vec
+    Ok(vec)
 }
 
 pub fn base64_decode_file(filename: &str) -> Vec<u8> {
@@ -141,7 +151,10 @@ pub fn base64_decode_file(filename: &str) -> Vec<u8> {
         Err(e) => { panic!("{}", e); },
     };
 
-    base64_decode(contents.replace("\n", "").trim())
+    match base64_decode(contents.replace("\n", "").trim()) {
+        Ok(decoded) => decoded,
+        Err(e) => { panic!("{}: {}", filename, e); },
+    }
 }
 
 pub fn base64_test() {
@@ -156,7 +169,7 @@ pub fn base64_test() {
             panic!("ERROR base64 encoding");
         }
 
-        let decoded = base64_decode(t.encoded);
+        let decoded = base64_decode(t.encoded).unwrap();
         if decoded != t.bytes {
             println!("ERROR base64 decoding {}", t.encoded);
             println!("  expected {:?}", t.bytes);
@@ -164,5 +177,11 @@ pub fn base64_test() {
             panic!("ERROR base64 decoding");
         }
     }
+
+    let bad = ["Eg=", "E!==", "Eg==Eg==", "E=g=", "rzU=rzU="];
+    for s in &bad {
+        assert!(base64_decode(s).is_err(),
+                "ERROR base64 decoded bad input {}", s);
+    }
     println!("Finished base64 tests");
 }
@@ -1,10 +1,14 @@
+pub mod group;
+pub mod responder;
 pub mod test;
 
 extern crate gmp;
 
 use self::gmp::mpz::Mpz;
 use aes::AES_BLOCK_SIZE;
-use util::mpz_bytes;
+use mac::{hkdf_extract, hkdf_expand};
+use sha2::Sha256;
+use util::{mpz_bytes_zero_pad, mpz_byte_len};
 
 // finite-field diffie-hellman private to public
 pub fn ff_dhe_public(private: &Mpz, generator: &Mpz, prime: &Mpz) -> Mpz {
@@ -18,22 +22,42 @@ pub fn ff_dhe_shared(private: &Mpz, peer_public: &Mpz, prime: &Mpz) -> Mpz {
     peer_public.powm(private, prime)
 }
 
-// aes128
-pub fn ff_dhe_aes_key_adjust(raw_key: &Mpz) -> Vec<u8> {
-    let mut key = mpz_bytes(raw_key);
-    if key.len() <= AES_BLOCK_SIZE {
-        // probably bad but just zeropad
-        for _ in key.len()..AES_BLOCK_SIZE {
-            key.push(0u8);
-        }
-        key
-    } else {
-        (&key[..AES_BLOCK_SIZE]).to_vec()
+pub const DH_ENC_KEY_LEN: usize = AES_BLOCK_SIZE;
+pub const DH_MAC_KEY_LEN: usize = 32;
+
+const ENC_KEY_LABEL: &[u8] = b"cryptopals dh enc";
+const MAC_KEY_LABEL: &[u8] = b"cryptopals dh mac";
+
+/// An aes128 key and an hmac-sha256 key for one session
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DHKeys {
+    pub enc_key: Vec<u8>,
+    pub mac_key: Vec<u8>,
+}
+
+/// Session keys from a shared secret. The secret is zero padded to the
+/// length of p so it's the same width whatever its value, put through
+/// HKDF-SHA256, then expanded once per key with its own label followed by
+/// context, which should tie the keys to whatever they're for (a protocol
+/// name, the publics, ...).
+pub fn ff_dhe_derive_keys(shared: &Mpz, prime: &Mpz,
+                          context: &[u8]) -> DHKeys {
+    let secret = mpz_bytes_zero_pad(shared, mpz_byte_len(prime));
+    let prk = hkdf_extract::<Sha256>(&[], &secret);
+    let expand = |label: &[u8], len| {
+        let mut info = label.to_vec();
+        info.extend_from_slice(context);
+        // both lengths are well under the limit
+        hkdf_expand::<Sha256>(&prk, &info, len).unwrap()
+    };
+    DHKeys {
+        enc_key: expand(ENC_KEY_LABEL, DH_ENC_KEY_LEN),
+        mac_key: expand(MAC_KEY_LABEL, DH_MAC_KEY_LEN),
     }
 }
 
-pub fn ff_dhe_shared_aes_key(private: &Mpz,
-                             peer_public: &Mpz,
-                             prime: &Mpz) -> Vec<u8> {
-    ff_dhe_aes_key_adjust(&ff_dhe_shared(private, peer_public, prime))
+pub fn ff_dhe_shared_keys(private: &Mpz, peer_public: &Mpz, prime: &Mpz,
+                          context: &[u8]) -> DHKeys {
+    ff_dhe_derive_keys(&ff_dhe_shared(private, peer_public, prime), prime,
+                       context)
 }
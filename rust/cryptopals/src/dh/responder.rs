@@ -0,0 +1,38 @@
+extern crate gmp;
+
+use self::gmp::mpz::Mpz;
+use dh::group::DHGroup;
+use error::Result;
+use mac::hmac_sha256;
+
+pub const DH_RESPONDER_MSG: &[u8] = b"crazy flamboyant for the rap enjoyment";
+pub const DH_RESPONDER_CONTEXT: &[u8] = b"dh responder";
+
+/// The other end of challenge 57: a long-term private key x, and for any
+/// public h it's sent, a message and its HMAC-SHA256 under the MAC key
+/// derived from h^x. How careful it is about h is up to the group; without
+/// q only the range gets checked, which lets small order elements through.
+#[derive(Debug, Clone)]
+pub struct DHResponder {
+    group: DHGroup,
+    private: Mpz,
+}
+
+impl DHResponder {
+    pub fn new(group: DHGroup, private: &Mpz) -> DHResponder {
+        DHResponder { group, private: private.clone() }
+    }
+
+    pub fn public(&self) -> Mpz {
+        self.group.public(&self.private)
+    }
+
+    /// (message, tag)
+    pub fn respond(&self, peer_public: &Mpz) -> Result<(Vec<u8>, Vec<u8>)> {
+        let keys = self.group.derive_keys(&self.private, peer_public,
+                                          DH_RESPONDER_CONTEXT)?;
+        let msg = DH_RESPONDER_MSG.to_vec();
+        let tag = hmac_sha256(&keys.mac_key, &msg);
+        Ok((msg, tag))
+    }
+}
@@ -6,8 +6,13 @@ use std::thread;
 use self::gmp::mpz::Mpz;
 use aes::AESCipher;
 use aes::cbc::AESCipherCBC;
-use dh::{ff_dhe_public, ff_dhe_shared, ff_dhe_shared_aes_key,
-         ff_dhe_aes_key_adjust};
+use dh::{ff_dhe_public, ff_dhe_shared, ff_dhe_shared_keys,
+         ff_dhe_derive_keys, DHKeys, DH_ENC_KEY_LEN, DH_MAC_KEY_LEN};
+use dh::group::{DHGroup, NAMED_GROUPS};
+use error::Error;
+use hex::hex_to_bytes;
+use mac::hmac_sha256;
+use util::{constant_time_eq, seeded_rng};
 
 type DHEPeer = fn (Sender<SimMsg>, Receiver<SimMsg>);
 type DHEMitm = fn (Sender<SimMsg>, Receiver<SimMsg>,
@@ -54,6 +59,24 @@ enum MITMType{
     ReplaceGWithPMinus1,
 }
 
+/// A 512 bit p = j q + 1 for a 128 bit q, where j is 2 times 14 distinct
+/// primes between 2^8 and 2^12 times something else that isn't smooth.
+/// g has order q. Small enough subgroups to confine a public to and
+/// enough of them to get all of an exponent mod q.
+pub fn smooth_test_group() -> DHGroup {
+    let p = Mpz::from_str_radix(concat!(
+        "81201930496acb08d82dd8307997d90533a1fd413dbf8a0d6448b5e3f6b7c1cf",
+        "b4a9a36152a09acaece8a9cfaabba075db69472686b744ce647ef615dcee7f31"),
+        16).unwrap();
+    let g = Mpz::from_str_radix(concat!(
+        "1a0dbf02759453fd38c509626f4e8d30784a7e6cbb19cec389011f4ddbc20e51",
+        "3ee24a5f63f38ef7e439b3b696f91fb3917fd7aac3232cc42622e924ad1c5401"),
+        16).unwrap();
+    let q = Mpz::from_str_radix("9af4974096f5b0ee5e2502420ae59647",
+                                16).unwrap();
+    DHGroup::new(&p, &g, Some(&q)).unwrap()
+}
+
 fn ff_dhe_test() {
     let test_vectors = [
         // hex values
@@ -89,19 +112,38 @@ fn ff_dhe_test() {
     }
 }
 
-fn dhe_sim_send(tx: &Sender<SimMsg>, key: &[u8], plaintext: &[u8]) {
-    let (cipher, iv) = AESCipherCBC::new_rand_iv(key);
+// encrypt then mac, over the iv and the ciphertext
+fn dhe_sim_tag(keys: &DHKeys, iv: &[u8], ciphertext: &[u8]) -> Vec<u8> {
+    let mut input = iv.to_vec();
+    input.extend_from_slice(ciphertext);
+    hmac_sha256(&keys.mac_key, &input)
+}
+
+fn dhe_sim_send(tx: &Sender<SimMsg>, keys: &DHKeys, plaintext: &[u8]) {
+    let (cipher, iv) = AESCipherCBC::new_rand_iv(&keys.enc_key).unwrap();
     let ciphertext = cipher.pad_and_encrypt(plaintext);
+    let tag = dhe_sim_tag(keys, &iv, &ciphertext);
     tx.send(SimMsg::Encrypted(ciphertext)).unwrap();
     tx.send(SimMsg::Plain(iv.clone())).unwrap();
+    tx.send(SimMsg::Plain(tag)).unwrap();
+}
+
+// None if the tag doesn't check out
+fn dhe_sim_open(keys: &DHKeys, ciphertext: &[u8], iv: &[u8],
+                tag: &[u8]) -> Option<Vec<u8>> {
+    if !constant_time_eq(&dhe_sim_tag(keys, iv, ciphertext), tag) {
+        return None;
+    }
+    let cipher = AESCipherCBC::new(&keys.enc_key, iv).unwrap();
+    Some(cipher.decrypt_and_unpad(ciphertext).unwrap())
 }
 
-fn dhe_sim_recv(rx: &Receiver<SimMsg>, key: &[u8]) -> Vec<u8> {
+fn dhe_sim_recv(rx: &Receiver<SimMsg>, keys: &DHKeys) -> Vec<u8> {
     let ciphertext = rx.recv().unwrap().expect_encrypted();
     let iv = rx.recv().unwrap().expect_plain();
+    let tag = rx.recv().unwrap().expect_plain();
 
-    let cipher = AESCipherCBC::new(&key, &iv);
-    cipher.decrypt_and_unpad(&ciphertext)
+    dhe_sim_open(keys, &ciphertext, &iv, &tag).expect("bad dhe sim tag")
 }
 
 fn dhe_sim_a(tx: Sender<SimMsg>, rx: Receiver<SimMsg>) {
@@ -119,13 +161,13 @@ fn dhe_sim_a(tx: Sender<SimMsg>, rx: Receiver<SimMsg>) {
     // 2. get B's public and generate shared key
     let pub_b = rx.recv().unwrap().expect_exchange();
 
-    let key = ff_dhe_shared_aes_key(&priv_a, &pub_b, &p);
+    let keys = ff_dhe_shared_keys(&priv_a, &pub_b, &p, DHE_SIM_CONTEXT);
 
     // 3. encrypt and send message
-    dhe_sim_send(&tx, &key, DHE_SIM_MSG.as_bytes());
+    dhe_sim_send(&tx, &keys, DHE_SIM_MSG.as_bytes());
 
     // 4. receive echo'd message
-    let plaintext = dhe_sim_recv(&rx, &key);
+    let plaintext = dhe_sim_recv(&rx, &keys);
     assert!(&plaintext as &[u8] == DHE_SIM_MSG.as_bytes());
 }
 
@@ -144,74 +186,57 @@ fn dhe_sim_b(tx: Sender<SimMsg>, rx: Receiver<SimMsg>) {
     tx.send(SimMsg::Exchange(pub_b)).unwrap();
 
     // 3. derive shared key
-    let key = ff_dhe_shared_aes_key(&priv_b, &pub_a, &p);
+    let keys = ff_dhe_shared_keys(&priv_b, &pub_a, &p, DHE_SIM_CONTEXT);
 
     // 4. receive and decrypt message
-    let plaintext = dhe_sim_recv(&rx, &key);
+    let plaintext = dhe_sim_recv(&rx, &keys);
     assert!(&plaintext as &[u8] == DHE_SIM_MSG.as_bytes());
 
     // 5. encrypt and send back message
-    dhe_sim_send(&tx, &key, &plaintext);
+    dhe_sim_send(&tx, &keys, &plaintext);
 }
 
 fn mitm_msg(tx: Sender<SimMsg>, rx: Receiver<SimMsg>, p: &Mpz,
             mitm_type: MITMType) {
     let ciphertext = rx.recv().unwrap().expect_encrypted();
     let iv = rx.recv().unwrap().expect_plain();
-
-    match mitm_type {
-        MITMType::ReplacePubs => {
-            // if we swap p for B then we get (p ^ a) % p which is always 0
-            let cipher = AESCipherCBC::new(&[0u8; 16], &iv);
-            let plaintext = cipher.decrypt_and_unpad(&ciphertext);
-            assert!(&plaintext as &[u8] == DHE_SIM_MSG.as_bytes());
-        },
-        MITMType::ReplaceGWith1 => {
-            // if g == 1, A == 1 and B == 1 => key == 1
-            let key = ff_dhe_aes_key_adjust(&Mpz::one());
-            let cipher = AESCipherCBC::new(&key, &iv);
-            let plaintext = cipher.decrypt_and_unpad(&ciphertext);
-            assert!(&plaintext as &[u8] == DHE_SIM_MSG.as_bytes());
-        },
-        MITMType::ReplaceGWithP => {
-            // if g == p, then p ^ anything === 0 mod p
-            let cipher = AESCipherCBC::new(&[0u8; 16], &iv);
-            let plaintext = cipher.decrypt_and_unpad(&ciphertext);
-            assert!(&plaintext as &[u8] == DHE_SIM_MSG.as_bytes());
-        },
-        MITMType::ReplaceGWithPMinus1 => {
-            // if g == p - 1, (p - 1) ^ x mod p is a binomial, e.g.
-            // (p - 1)^2 = p^2 - 2p + 1
-            // (p - 1)^3 = p^3 - 3p^2 + 3p - 1
-            // (p - 1)^4 = p^4 - 4p^3 + 6p^2 - 4p + 1
-            // so with a hand-wavy argument, when the exponent is even,
-            // we can subtract p's away until there's 1 leftover.
-            // when the exponent is odd, we'll have p - 1 leftover.
-            // so from A and B we can know if a and b are even or odd.
-            // if a and b have different parity => a * b is even
-            // a and b are even => a * b is even
-            // a and b are odd => a * b is odd
-            // XXX: too lazy to do this, just brute force
-            let keys = [
-                ff_dhe_aes_key_adjust(&Mpz::one()),
-                ff_dhe_aes_key_adjust(p),
-            ];
-            let mut matched = false;
-            for key in &keys {
-                let cipher = AESCipherCBC::new(key, &iv);
-                let plaintext = cipher.decrypt_and_unpad(&ciphertext);
-                if &plaintext as &[u8] == DHE_SIM_MSG.as_bytes() {
-                    matched = true;
-                    break;
-                }
-            }
-            assert!(matched);
-        },
-        _ => {},
+    let tag = rx.recv().unwrap().expect_plain();
+
+    // the shared secrets it could be, which go through the same kdf as
+    // everyone else
+    let guesses = match mitm_type {
+        MITMType::Passthrough => vec!(),
+        // if we swap p for B then we get (p ^ a) % p which is always 0
+        MITMType::ReplacePubs => vec!(Mpz::zero()),
+        // if g == 1, A == 1 and B == 1 => key == 1
+        MITMType::ReplaceGWith1 => vec!(Mpz::one()),
+        // if g == p, then p ^ anything === 0 mod p
+        MITMType::ReplaceGWithP => vec!(Mpz::zero()),
+        // if g == p - 1, (p - 1) ^ x mod p is a binomial, e.g.
+        // (p - 1)^2 = p^2 - 2p + 1
+        // (p - 1)^3 = p^3 - 3p^2 + 3p - 1
+        // (p - 1)^4 = p^4 - 4p^3 + 6p^2 - 4p + 1
+        // so with a hand-wavy argument, when the exponent is even,
+        // we can subtract p's away until there's 1 leftover.
+        // when the exponent is odd, we'll have p - 1 leftover.
+        // so from A and B we can know if a and b are even or odd.
+        // if a and b have different parity => a * b is even
+        // a and b are even => a * b is even
+        // a and b are odd => a * b is odd
+        // XXX: too lazy to do this, just try both and see which mac checks
+        MITMType::ReplaceGWithPMinus1 => vec!(Mpz::one(), p - Mpz::one()),
     };
+    if !guesses.is_empty() {
+        let plaintext = guesses.iter().filter_map(|shared| {
+            let keys = ff_dhe_derive_keys(shared, p, DHE_SIM_CONTEXT);
+            dhe_sim_open(&keys, &ciphertext, &iv, &tag)
+        }).next().expect("mitm couldn't open the message");
+        assert!(&plaintext as &[u8] == DHE_SIM_MSG.as_bytes());
+    }
 
     tx.send(SimMsg::Encrypted(ciphertext)).unwrap();
     tx.send(SimMsg::Plain(iv)).unwrap();
+    tx.send(SimMsg::Plain(tag)).unwrap();
 }
 
 fn dhe_sim_mitm(a_tx: Sender<SimMsg>, a_rx: Receiver<SimMsg>,
@@ -261,11 +286,11 @@ fn dhe_negotiate_a(tx: Sender<SimMsg>, rx: Receiver<SimMsg>) {
 
     tx.send(SimMsg::Exchange(ff_dhe_public(&priv_a, &g, &p))).unwrap();
     let pub_b = rx.recv().unwrap().expect_exchange();
-    let key = ff_dhe_shared_aes_key(&priv_a, &pub_b, &p);
+    let keys = ff_dhe_shared_keys(&priv_a, &pub_b, &p, DHE_SIM_CONTEXT);
 
-    dhe_sim_send(&tx, &key, DHE_SIM_MSG.as_bytes());
+    dhe_sim_send(&tx, &keys, DHE_SIM_MSG.as_bytes());
 
-    let plaintext = dhe_sim_recv(&rx, &key);
+    let plaintext = dhe_sim_recv(&rx, &keys);
     assert!(&plaintext as &[u8] == DHE_SIM_MSG.as_bytes());
 }
 
@@ -282,12 +307,12 @@ fn dhe_negotiate_b(tx: Sender<SimMsg>, rx: Receiver<SimMsg>) {
 
     let pub_a = rx.recv().unwrap().expect_exchange();
     tx.send(SimMsg::Exchange(ff_dhe_public(&priv_b, &g, &p))).unwrap();
-    let key = ff_dhe_shared_aes_key(&priv_b, &pub_a, &p);
+    let keys = ff_dhe_shared_keys(&priv_b, &pub_a, &p, DHE_SIM_CONTEXT);
 
-    let plaintext = dhe_sim_recv(&rx, &key);
+    let plaintext = dhe_sim_recv(&rx, &keys);
     assert!(&plaintext as &[u8] == DHE_SIM_MSG.as_bytes());
 
-    dhe_sim_send(&tx, &key, &plaintext);
+    dhe_sim_send(&tx, &keys, &plaintext);
 }
 
 fn dhe_negotiate_mitm(a_tx: Sender<SimMsg>, a_rx: Receiver<SimMsg>,
@@ -323,6 +348,7 @@ fn dhe_negotiate_mitm(a_tx: Sender<SimMsg>, a_rx: Receiver<SimMsg>,
 }
 
 const DHE_SIM_MSG: &'static str = "beep boop meow";
+const DHE_SIM_CONTEXT: &[u8] = b"dhe sim";
 fn dhe_mitm_test(func_a: DHEPeer, func_b: DHEPeer, func_m: DHEMitm,
                  mitm_type: MITMType) {
     let (a_tx, ma_rx) = channel();
@@ -347,11 +373,130 @@ fn dhe_mitm_test(func_a: DHEPeer, func_b: DHEPeer, func_m: DHEMitm,
     thread_m.join().unwrap();
 }
 
+fn dh_named_group_test() {
+    let mut rng = seeded_rng(1);
+    for &named in &NAMED_GROUPS {
+        let group = DHGroup::named(named);
+        let bits: usize = named.name().trim_start_matches(|c: char| {
+            !c.is_ascii_digit()
+        }).parse().unwrap();
+        assert_eq!(group.p.bit_length(), bits, "{}", named.name());
+        assert_eq!(group.g, Mpz::from(2u64));
+        let q = group.q.clone().unwrap();
+        assert_eq!(&q * Mpz::from(2u64) + Mpz::one(), group.p);
+        assert_eq!(group.g.powm(&q, &group.p), Mpz::one());
+
+        // the big ones are slow enough as it is
+        if bits > 3072 {
+            continue;
+        }
+        let priv_a = group.random_private(&mut rng);
+        let priv_b = group.random_private(&mut rng);
+        let pub_a = group.public(&priv_a);
+        let pub_b = group.public(&priv_b);
+        assert_eq!(group.shared(&priv_a, &pub_b).unwrap(),
+                   group.shared(&priv_b, &pub_a).unwrap());
+    }
+
+    // the parameter checks agree they're safe primes, at least for the
+    // ones that don't take forever
+    for &named in &NAMED_GROUPS[..3] {
+        let group = DHGroup::named(named);
+        assert_eq!(DHGroup::new(&group.p, &group.g, group.q.as_ref()),
+                   Ok(group.clone()));
+    }
+
+    println!("Finished DH named group tests");
+}
+
+fn dh_validation_test() {
+    // the g's the negotiation mitm swaps in all get refused
+    let p = Mpz::from(0x71u64);
+    assert!(DHGroup::new(&p, &Mpz::from(2u64), None).is_ok());
+    for g in &[Mpz::zero(), Mpz::one(), &p - Mpz::one(), p.clone(),
+               &p + Mpz::one()] {
+        assert_eq!(DHGroup::new(&p, g, None),
+                   Err(Error::InvalidDHParameter("degenerate generator")));
+    }
+    assert!(DHGroup::new(&Mpz::from(0x70u64), &Mpz::from(2u64),
+                         None).is_err());
+
+    // 23 = 2 * 11 + 1, and 2 has order 11
+    let p = Mpz::from(23u64);
+    let q = Mpz::from(11u64);
+    let group = DHGroup::new(&p, &Mpz::from(2u64), Some(&q)).unwrap();
+    assert!(DHGroup::new(&p, &Mpz::from(5u64), Some(&q)).is_err());
+    assert!(DHGroup::new(&p, &Mpz::from(2u64),
+                         Some(&Mpz::from(7u64))).is_err());
+
+    // and the publics the mitm swaps in, p in particular
+    let out_of_range = Err(Error::InvalidDHParameter("public out of range"));
+    for y in &[Mpz::zero(), Mpz::one(), &p - Mpz::one(), p.clone(),
+               &p + Mpz::one()] {
+        assert_eq!(group.validate_public(y), out_of_range);
+        assert_eq!(group.shared(&Mpz::from(3u64), y).map(|_| ()),
+                   out_of_range);
+    }
+
+    // 4 = 2^2 is in g's subgroup, 5 isn't, which only shows with q known
+    assert!(group.validate_public(&Mpz::from(4u64)).is_ok());
+    assert_eq!(group.validate_public(&Mpz::from(5u64)),
+               Err(Error::InvalidDHParameter("public not in the subgroup")));
+    let no_q = DHGroup::new(&p, &Mpz::from(2u64), None).unwrap();
+    assert!(no_q.validate_public(&Mpz::from(5u64)).is_ok());
+
+    // same for a real group: p - 2 = -2 isn't a square mod p
+    let group = DHGroup::named(NAMED_GROUPS[0]);
+    assert!(group.validate_public(&(&group.p - Mpz::from(2u64))).is_err());
+    assert!(group.validate_public(&Mpz::from(4u64)).is_ok());
+
+    println!("Finished DH validation tests");
+}
+
+fn dh_key_derivation_test() {
+    // 5 padded out to the length of 65537 is 00 00 05, checked against
+    // python's cryptography hkdf
+    let keys = ff_dhe_derive_keys(&Mpz::from(5u64), &Mpz::from(65537u64),
+                                  b"test");
+    assert_eq!(keys.enc_key,
+               hex_to_bytes("341e227b7b29e16a018ca90a72da4810").unwrap());
+    assert_eq!(keys.mac_key, hex_to_bytes(concat!(
+        "c89c7e1e6a74f66e9f29b86e54622623",
+        "4fd375e7f2c6182be44a0a61bbe1d514")).unwrap());
+
+    let mut rng = seeded_rng(2);
+    let group = DHGroup::named(NAMED_GROUPS[0]);
+    let priv_a = group.random_private(&mut rng);
+    let priv_b = group.random_private(&mut rng);
+    let pub_a = group.public(&priv_a);
+    let pub_b = group.public(&priv_b);
+    let keys_a = group.derive_keys(&priv_a, &pub_b, b"ctx").unwrap();
+    let keys_b = group.derive_keys(&priv_b, &pub_a, b"ctx").unwrap();
+    assert_eq!(keys_a, keys_b);
+    assert_eq!(keys_a.enc_key.len(), DH_ENC_KEY_LEN);
+    assert_eq!(keys_a.mac_key.len(), DH_MAC_KEY_LEN);
+    assert!(keys_a.enc_key[..] != keys_a.mac_key[..DH_ENC_KEY_LEN]);
+
+    // a different context is a different pair of keys
+    let other = group.derive_keys(&priv_a, &pub_b, b"other").unwrap();
+    assert!(other.enc_key != keys_a.enc_key);
+    assert!(other.mac_key != keys_a.mac_key);
+
+    // and the peer's public still gets checked first
+    assert_eq!(group.derive_keys(&priv_a, &Mpz::one(), b"ctx"),
+               Err(Error::InvalidDHParameter("public out of range")));
+
+    println!("Finished DH key derivation tests");
+}
+
 pub fn dh_test() {
     let n = Mpz::new();
     println!("rust-gmp big zero {:?}", n);
 
     ff_dhe_test();
+    dh_named_group_test();
+    dh_validation_test();
+    dh_key_derivation_test();
 
     // XXX: figure out closures into threads one day...maybe when
     // FnBox or Box<FnOnce> works?
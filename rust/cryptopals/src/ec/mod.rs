@@ -0,0 +1,326 @@
+pub mod montgomery;
+pub mod test;
+
+extern crate gmp;
+
+use self::gmp::mpz::Mpz;
+use error::{Error, Result};
+use util::baillie_psw;
+
+/// A point on a short Weierstrass curve, or the point at infinity which
+/// is the identity
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Point {
+    Infinity,
+    Affine(Mpz, Mpz),
+}
+
+/// (X, Y, Z) stands for the affine (X / Z^2, Y / Z^3), and Z = 0 for
+/// infinity. Adding and doubling these takes no inversions, just the one
+/// at the end to get back to affine.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JacobianPoint {
+    pub x: Mpz,
+    pub y: Mpz,
+    pub z: Mpz,
+}
+
+impl JacobianPoint {
+    pub fn infinity() -> JacobianPoint {
+        JacobianPoint { x: Mpz::one(), y: Mpz::one(), z: Mpz::zero() }
+    }
+
+    pub fn is_infinity(&self) -> bool {
+        self.z.is_zero()
+    }
+
+    fn cswap(swap: bool, a: &mut JacobianPoint, b: &mut JacobianPoint) {
+        cswap(swap, &mut a.x, &mut b.x);
+        cswap(swap, &mut a.y, &mut b.y);
+        cswap(swap, &mut a.z, &mut b.z);
+    }
+
+    fn cmov(&mut self, flag: bool, src: &JacobianPoint) {
+        cmov(flag, &mut self.x, &src.x);
+        cmov(flag, &mut self.y, &src.y);
+        cmov(flag, &mut self.z, &src.z);
+    }
+}
+
+// all ones (-1 in two's complement, which is how gmp does bit ops on
+// negatives) if flag is set, otherwise all zeros
+fn mask(flag: bool) -> Mpz {
+    -Mpz::from(flag as u64)
+}
+
+/// Swaps a and b if swap is set without branching on it, the cswap of RFC
+/// 7748: a mask of all ones or all zeros ANDed with a ^ b, and that XORed
+/// into both
+pub fn cswap(swap: bool, a: &mut Mpz, b: &mut Mpz) {
+    let dummy = mask(swap) & (&*a ^ &*b);
+    *a ^= &dummy;
+    *b ^= &dummy;
+}
+
+// dst = src if flag is set, the same way
+fn cmov(flag: bool, dst: &mut Mpz, src: &Mpz) {
+    let dummy = mask(flag) & (&*dst ^ src);
+    *dst ^= dummy;
+}
+
+/// y^2 = x^3 + a x + b mod p, with a base point g of order n
+#[derive(Debug, Clone, PartialEq)]
+pub struct WeierstrassCurve {
+    pub p: Mpz,
+    pub a: Mpz,
+    pub b: Mpz,
+    pub g: Point,
+    pub n: Mpz,
+}
+
+const P256_P: &str =
+    "ffffffff00000001000000000000000000000000ffffffffffffffffffffffff";
+const P256_B: &str =
+    "5ac635d8aa3a93e7b3ebbd55769886bc651d06b0cc53b0f63bce3c3e27d2604b";
+const P256_GX: &str =
+    "6b17d1f2e12c4247f8bce6e563a440f277037d812deb33a0f4a13945d898c296";
+const P256_GY: &str =
+    "4fe342e2fe1a7f9b8ee7eb4a7c0f9e162bce33576b315ececbb6406837bf51f5";
+const P256_N: &str =
+    "ffffffff00000000ffffffffffffffffbce6faada7179e84f3b9cac2fc632551";
+
+fn from_hex(hex: &str) -> Mpz {
+    Mpz::from_str_radix(hex, 16).unwrap()
+}
+
+impl WeierstrassCurve {
+    /// NIST P-256 from FIPS 186-4, a = -3
+    pub fn p256() -> WeierstrassCurve {
+        let p = from_hex(P256_P);
+        WeierstrassCurve {
+            a: &p - Mpz::from(3u64),
+            p,
+            b: from_hex(P256_B),
+            g: Point::Affine(from_hex(P256_GX), from_hex(P256_GY)),
+            n: from_hex(P256_N),
+        }
+    }
+
+    /// Checks the parameters: p prime, the curve not singular, g on it
+    /// and n g the identity
+    pub fn new(p: &Mpz, a: &Mpz, b: &Mpz, g: &Point,
+               n: &Mpz) -> Result<WeierstrassCurve> {
+        if *p <= Mpz::from(3u64) || !baillie_psw(p) {
+            return Err(Error::InvalidCurve("p isn't prime"));
+        }
+        let curve = WeierstrassCurve {
+            p: p.clone(),
+            a: a.modulus(p),
+            b: b.modulus(p),
+            g: g.clone(),
+            n: n.clone(),
+        };
+        // 4 a^3 + 27 b^2 = 0 means x^3 + a x + b has a repeated root
+        let disc = Mpz::from(4u64) * curve.a.powm(&Mpz::from(3u64), p)
+                 + Mpz::from(27u64) * &curve.b * &curve.b;
+        if curve.reduce(disc).is_zero() {
+            return Err(Error::InvalidCurve("singular curve"));
+        }
+        if *g == Point::Infinity || !curve.is_on_curve(g) {
+            return Err(Error::InvalidCurve("base point isn't on the curve"));
+        }
+        // the ladder relies on points having order n, so check it the
+        // plain way
+        if *n <= Mpz::one()
+                || curve.scalar_mul_affine(n, g) != Point::Infinity {
+            return Err(Error::InvalidCurve("base point doesn't have order n"));
+        }
+        Ok(curve)
+    }
+
+    fn reduce(&self, x: Mpz) -> Mpz {
+        x.modulus(&self.p)
+    }
+
+    fn inverse(&self, x: &Mpz) -> Mpz {
+        x.invert(&self.p).expect("no inverse mod p")
+    }
+
+    pub fn is_on_curve(&self, point: &Point) -> bool {
+        match *point {
+            Point::Infinity => true,
+            Point::Affine(ref x, ref y) => {
+                if *x < Mpz::zero() || *x >= self.p
+                        || *y < Mpz::zero() || *y >= self.p {
+                    return false;
+                }
+                let rhs = x * x * x + &self.a * x + &self.b;
+                self.reduce(y * y - rhs).is_zero()
+            },
+        }
+    }
+
+    pub fn negate(&self, point: &Point) -> Point {
+        match *point {
+            Point::Infinity => Point::Infinity,
+            Point::Affine(ref x, ref y) => {
+                Point::Affine(x.clone(), self.reduce(-y))
+            },
+        }
+    }
+
+    /// Chord and tangent addition in affine coordinates, one inversion
+    pub fn add(&self, p1: &Point, p2: &Point) -> Point {
+        let (x1, y1, x2, y2) = match (p1, p2) {
+            (Point::Infinity, _) => return p2.clone(),
+            (_, Point::Infinity) => return p1.clone(),
+            (Point::Affine(x1, y1), Point::Affine(x2, y2)) => {
+                (x1, y1, x2, y2)
+            },
+        };
+        if x1 == x2 {
+            // either the same point or each other's negatives
+            return if y1 == y2 { self.double(p1) } else { Point::Infinity };
+        }
+        let slope = self.reduce((y2 - y1) * self.inverse(&(x2 - x1)));
+        let x3 = self.reduce(&slope * &slope - x1 - x2);
+        let y3 = self.reduce(slope * (x1 - &x3) - y1);
+        Point::Affine(x3, y3)
+    }
+
+    pub fn double(&self, point: &Point) -> Point {
+        let (x, y) = match *point {
+            Point::Infinity => return Point::Infinity,
+            Point::Affine(ref x, ref y) => (x, y),
+        };
+        if y.is_zero() {
+            // a vertical tangent, points of order 2
+            return Point::Infinity;
+        }
+        let num = Mpz::from(3u64) * x * x + &self.a;
+        let slope = self.reduce(num * self.inverse(&(y << 1)));
+        let x3 = self.reduce(&slope * &slope - (x << 1));
+        let y3 = self.reduce(slope * (x - &x3) - y);
+        Point::Affine(x3, y3)
+    }
+
+    pub fn to_jacobian(&self, point: &Point) -> JacobianPoint {
+        match *point {
+            Point::Infinity => JacobianPoint::infinity(),
+            Point::Affine(ref x, ref y) => JacobianPoint {
+                x: x.clone(),
+                y: y.clone(),
+                z: Mpz::one(),
+            },
+        }
+    }
+
+    pub fn to_affine(&self, point: &JacobianPoint) -> Point {
+        if point.is_infinity() {
+            return Point::Infinity;
+        }
+        let z_inv = self.inverse(&point.z);
+        let z_inv2 = self.reduce(&z_inv * &z_inv);
+        let x = self.reduce(&point.x * &z_inv2);
+        let y = self.reduce(&point.y * z_inv2 * z_inv);
+        Point::Affine(x, y)
+    }
+
+    /// dbl-1998-cmo-2 from the explicit formulas database, for any a.
+    /// Infinity and the points of order 2 (y = 0) both come out with
+    /// Z3 = 2 Y Z = 0, so there's nothing to special case.
+    pub fn jacobian_double(&self, point: &JacobianPoint) -> JacobianPoint {
+        let (x, y, z) = (&point.x, &point.y, &point.z);
+        let xx = self.reduce(x * x);
+        let yy = self.reduce(y * y);
+        let yyyy = self.reduce(&yy * &yy);
+        let zz = self.reduce(z * z);
+        let s = self.reduce(Mpz::from(4u64) * x * &yy);
+        let m = self.reduce(Mpz::from(3u64) * &xx + &self.a * &zz * &zz);
+        let x3 = self.reduce(&m * &m - (&s << 1));
+        let y3 = self.reduce(m * (s - &x3) - Mpz::from(8u64) * yyyy);
+        let z3 = self.reduce((y * z) << 1);
+        JacobianPoint { x: x3, y: y3, z: z3 }
+    }
+
+    /// add-1998-cmo-2 from the explicit formulas database. P + -P comes
+    /// out as infinity on its own (H = 0 so Z3 = 0), but P + P and either
+    /// side being infinity don't, so those answers are worked out every
+    /// time too and picked with masks rather than branches.
+    pub fn jacobian_add(&self, p1: &JacobianPoint,
+                        p2: &JacobianPoint) -> JacobianPoint {
+        let z1z1 = self.reduce(&p1.z * &p1.z);
+        let z2z2 = self.reduce(&p2.z * &p2.z);
+        let u1 = self.reduce(&p1.x * &z2z2);
+        let u2 = self.reduce(&p2.x * &z1z1);
+        let s1 = self.reduce(&p1.y * &p2.z * &z2z2);
+        let s2 = self.reduce(&p2.y * &p1.z * &z1z1);
+        let h = self.reduce(u2 - &u1);
+        let r = self.reduce(s2 - &s1);
+        let hh = self.reduce(&h * &h);
+        let hhh = self.reduce(&h * &hh);
+        let v = self.reduce(u1 * hh);
+        let x3 = self.reduce(&r * &r - &hhh - (&v << 1));
+        let y3 = self.reduce(&r * (v - &x3) - s1 * hhh);
+        let z3 = self.reduce(&p1.z * &p2.z * &h);
+        let mut sum = JacobianPoint { x: x3, y: y3, z: z3 };
+
+        let same = h.is_zero() & r.is_zero();
+        sum.cmov(same, &self.jacobian_double(p1));
+        sum.cmov(p2.is_infinity(), p1);
+        sum.cmov(p1.is_infinity(), p2);
+        sum
+    }
+
+    /// k point for a point of order dividing n, with a Montgomery ladder in
+    /// Jacobian coordinates. k is taken mod n and then n or 2n added, so
+    /// it's always exactly one bit longer than n with the top bit set: the
+    /// ladder starts from (point, 2 point) rather than infinity and takes
+    /// the same number of steps for every k. Each step is a masked swap,
+    /// an add and a double, none of which branch on k. Mpz arithmetic
+    /// itself isn't constant time though, so gmp can still leak some
+    /// timing.
+    pub fn scalar_mul(&self, k: &Mpz, point: &Point) -> Point {
+        let bits = self.n.bit_length();
+        // k + n is in [n, 2n), so if it's short of bits + 1 bits then
+        // k + 2n has exactly that many
+        let k_n = k.modulus(&self.n) + &self.n;
+        let short = !k_n.tstbit(bits);
+        let k = k_n + &self.n * Mpz::from(short as u64);
+
+        // r1 - r0 = point the whole way through
+        let mut r0 = self.to_jacobian(point);
+        let mut r1 = self.jacobian_double(&r0);
+        let mut swap = false;
+        for i in (0..bits).rev() {
+            let bit = k.tstbit(i);
+            JacobianPoint::cswap(swap ^ bit, &mut r0, &mut r1);
+            swap = bit;
+            r1 = self.jacobian_add(&r0, &r1);
+            r0 = self.jacobian_double(&r0);
+        }
+        JacobianPoint::cswap(swap, &mut r0, &mut r1);
+        self.to_affine(&r0)
+    }
+
+    /// k g
+    pub fn base_mul(&self, k: &Mpz) -> Point {
+        self.scalar_mul(k, &self.g)
+    }
+
+    /// Plain double and add in affine coordinates, the slow obvious way
+    /// to check the ladder against
+    pub fn scalar_mul_affine(&self, k: &Mpz, point: &Point) -> Point {
+        if *k < Mpz::zero() {
+            return self.scalar_mul_affine(&-k, &self.negate(point));
+        }
+        let mut result = Point::Infinity;
+        for i in (0..k.bit_length()).rev() {
+            result = self.double(&result);
+            if k.tstbit(i) {
+                result = self.add(&result, point);
+            }
+        }
+        result
+    }
+}
@@ -0,0 +1,139 @@
+extern crate gmp;
+
+use self::gmp::mpz::Mpz;
+use ec::cswap;
+use util::{bytes_to_mpz, mpz_bytes_zero_pad};
+
+pub const X25519_LEN: usize = 32;
+
+/// B v^2 = u^3 + A u^2 + u mod p. Only u is ever needed for Diffie-Hellman,
+/// and the x-only ladder never looks at v, so B doesn't come into it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MontgomeryCurve {
+    pub p: Mpz,
+    pub a: Mpz,
+    // (A - 2) / 4, which the ladder uses in place of A
+    pub a24: Mpz,
+    pub base_u: Mpz,
+    // of the subgroup the base point generates
+    pub order: Mpz,
+    pub cofactor: u64,
+    // how many bits of scalar the ladder walks through
+    pub bits: usize,
+}
+
+impl MontgomeryCurve {
+    /// Curve25519 from RFC 7748: p = 2^255 - 19, A = 486662, u = 9
+    pub fn curve25519() -> MontgomeryCurve {
+        let p = (Mpz::one() << 255) - Mpz::from(19u64);
+        let order = (Mpz::one() << 252) + Mpz::from_str_radix(
+            "27742317777372353535851937790883648493", 10).unwrap();
+        MontgomeryCurve {
+            p,
+            a: Mpz::from(486662u64),
+            a24: Mpz::from(121665u64),
+            base_u: Mpz::from(9u64),
+            order,
+            cofactor: 8,
+            bits: 255,
+        }
+    }
+
+    fn reduce(&self, x: Mpz) -> Mpz {
+        x.modulus(&self.p)
+    }
+
+    /// u(k P) from u(P), the ladder from RFC 7748 section 5. It always
+    /// takes self.bits steps and swaps with a mask rather than branching
+    /// on the bits of k. x25519 clamps the top bit on, so x_2 only starts
+    /// out as the identity; as with the Weierstrass ladder, Mpz isn't
+    /// constant time underneath. The identity comes out as 0.
+    pub fn ladder(&self, k: &Mpz, u: &Mpz) -> Mpz {
+        let x_1 = u.modulus(&self.p);
+        let (mut x_2, mut z_2) = (Mpz::one(), Mpz::zero());
+        let (mut x_3, mut z_3) = (x_1.clone(), Mpz::one());
+        let mut swap = false;
+
+        for t in (0..self.bits).rev() {
+            let k_t = k.tstbit(t);
+            cswap(swap ^ k_t, &mut x_2, &mut x_3);
+            cswap(swap ^ k_t, &mut z_2, &mut z_3);
+            swap = k_t;
+
+            let a = &x_2 + &z_2;
+            let aa = self.reduce(&a * &a);
+            let b = &x_2 - &z_2;
+            let bb = self.reduce(&b * &b);
+            let e = &aa - &bb;
+            let c = &x_3 + &z_3;
+            let d = &x_3 - &z_3;
+            let da = self.reduce(d * a);
+            let cb = self.reduce(c * b);
+            let sum = &da + &cb;
+            let diff = da - cb;
+            x_3 = self.reduce(&sum * &sum);
+            z_3 = self.reduce(&x_1 * &diff * &diff);
+            z_2 = self.reduce(&e * (&aa + &self.a24 * &e));
+            x_2 = self.reduce(aa * bb);
+        }
+        cswap(swap, &mut x_2, &mut x_3);
+        cswap(swap, &mut z_2, &mut z_3);
+
+        // z_2^(p - 2) is 0 for the identity, rather than failing
+        let z_inv = z_2.powm(&(&self.p - Mpz::from(2u64)), &self.p);
+        self.reduce(x_2 * z_inv)
+    }
+
+    /// u(k B) for the base point
+    pub fn base_ladder(&self, k: &Mpz) -> Mpz {
+        self.ladder(k, &self.base_u)
+    }
+}
+
+fn decode_le(bytes: &[u8]) -> Mpz {
+    let mut be = bytes.to_vec();
+    be.reverse();
+    bytes_to_mpz(&be)
+}
+
+fn encode_le(n: &Mpz) -> [u8; X25519_LEN] {
+    let mut out = [0u8; X25519_LEN];
+    for (dst, src) in out.iter_mut()
+                         .zip(mpz_bytes_zero_pad(n, X25519_LEN).iter().rev()) {
+        *dst = *src;
+    }
+    out
+}
+
+/// Scalars are clamped: a multiple of the cofactor 8 so small subgroup
+/// points go to the identity, and the top bit set so the ladder's length
+/// doesn't depend on the scalar
+pub fn x25519_decode_scalar(scalar: &[u8; X25519_LEN]) -> Mpz {
+    let mut clamped = *scalar;
+    clamped[0] &= 248;
+    clamped[31] &= 127;
+    clamped[31] |= 64;
+    decode_le(&clamped)
+}
+
+/// u coordinates are little endian with the unused top bit ignored
+pub fn x25519_decode_u(u: &[u8; X25519_LEN]) -> Mpz {
+    let mut masked = *u;
+    masked[31] &= 127;
+    decode_le(&masked)
+}
+
+/// The X25519 function of RFC 7748
+pub fn x25519(scalar: &[u8; X25519_LEN],
+              u: &[u8; X25519_LEN]) -> [u8; X25519_LEN] {
+    let curve = MontgomeryCurve::curve25519();
+    let k = x25519_decode_scalar(scalar);
+    encode_le(&curve.ladder(&k, &x25519_decode_u(u)))
+}
+
+/// The public key for a private scalar, X25519 of the base point 9
+pub fn x25519_public(scalar: &[u8; X25519_LEN]) -> [u8; X25519_LEN] {
+    let curve = MontgomeryCurve::curve25519();
+    let k = x25519_decode_scalar(scalar);
+    encode_le(&curve.base_ladder(&k))
+}
@@ -0,0 +1,248 @@
+extern crate gmp;
+
+use self::gmp::mpz::Mpz;
+use ec::{WeierstrassCurve, Point};
+use ec::montgomery::{MontgomeryCurve, x25519, x25519_public, X25519_LEN};
+use error::Error;
+use hex::hex_to_bytes;
+use util::{random_mpz_below, seeded_rng};
+
+fn affine(x: &str, y: &str) -> Point {
+    Point::Affine(Mpz::from_str_radix(x, 16).unwrap(),
+                  Mpz::from_str_radix(y, 16).unwrap())
+}
+
+fn bytes32(hex: &str) -> [u8; X25519_LEN] {
+    let mut out = [0u8; X25519_LEN];
+    out.copy_from_slice(&hex_to_bytes(hex).unwrap());
+    out
+}
+
+// y^2 = x^3 + 2x + 3 mod 97, small enough to check everything against
+// everything
+fn small_curve_points() -> (WeierstrassCurve, Vec<Point>) {
+    let p = Mpz::from(97u64);
+    let mut points = vec!(Point::Infinity);
+    for x in 0..97u64 {
+        for y in 0..97u64 {
+            if (y * y) % 97 == (x * x * x + 2 * x + 3) % 97 {
+                points.push(Point::Affine(Mpz::from(x), Mpz::from(y)));
+            }
+        }
+    }
+    let order = Mpz::from(points.len() as u64);
+    let curve = WeierstrassCurve::new(&p, &Mpz::from(2u64), &Mpz::from(3u64),
+                                      &points[1], &order).unwrap();
+    (curve, points)
+}
+
+fn small_curve_test() {
+    let (curve, points) = small_curve_points();
+    // Hasse: |#E - (p + 1)| <= 2 sqrt(p)
+    assert!(points.len() >= 98 - 20 && points.len() <= 98 + 20);
+    let order = curve.n.clone();
+
+    for p1 in &points {
+        assert!(curve.is_on_curve(p1));
+        assert_eq!(curve.scalar_mul(&order, p1), Point::Infinity);
+        assert_eq!(curve.add(p1, &curve.negate(p1)), Point::Infinity);
+        let j1 = curve.to_jacobian(p1);
+        assert_eq!(curve.to_affine(&curve.jacobian_double(&j1)),
+                   curve.double(p1));
+        for p2 in &points {
+            let sum = curve.add(p1, p2);
+            assert!(curve.is_on_curve(&sum));
+            assert_eq!(sum, curve.add(p2, p1));
+            let j2 = curve.to_jacobian(p2);
+            assert_eq!(curve.to_affine(&curve.jacobian_add(&j1, &j2)), sum);
+        }
+    }
+
+    // associativity on a few
+    for (i, p1) in points.iter().enumerate().take(10) {
+        let p2 = &points[(i * 7 + 3) % points.len()];
+        let p3 = &points[(i * 13 + 5) % points.len()];
+        assert_eq!(curve.add(&curve.add(p1, p2), p3),
+                   curve.add(p1, &curve.add(p2, p3)));
+    }
+
+    // the ladder against double and add, including negative k
+    let g = curve.g.clone();
+    for k in -5..250i64 {
+        let k = Mpz::from(k);
+        assert_eq!(curve.scalar_mul(&k, &g), curve.scalar_mul_affine(&k, &g),
+                   "{} g", k);
+    }
+
+    println!("Finished small curve tests");
+}
+
+fn curve_validation_test() {
+    let (curve, points) = small_curve_points();
+    let p = curve.p.clone();
+    let (a, b, g, n) = (curve.a, curve.b, curve.g, curve.n);
+    let invalid = |s| Err(Error::InvalidCurve(s));
+
+    // y^2 = x^3 has a cusp
+    assert_eq!(WeierstrassCurve::new(&p, &Mpz::zero(), &Mpz::zero(), &g, &n),
+               invalid("singular curve"));
+    assert_eq!(WeierstrassCurve::new(&Mpz::from(91u64), &a, &b, &g, &n),
+               invalid("p isn't prime"));
+    let off = Point::Affine(Mpz::from(1u64), Mpz::from(1u64));
+    assert!(!points.contains(&off));
+    assert_eq!(WeierstrassCurve::new(&p, &a, &b, &off, &n),
+               invalid("base point isn't on the curve"));
+    assert_eq!(WeierstrassCurve::new(&p, &a, &b, &Point::Infinity, &n),
+               invalid("base point isn't on the curve"));
+    assert_eq!(WeierstrassCurve::new(&p, &a, &b, &g, &(&n + Mpz::one())),
+               invalid("base point doesn't have order n"));
+
+    let p256 = WeierstrassCurve::p256();
+    assert_eq!(WeierstrassCurve::new(&p256.p, &p256.a, &p256.b, &p256.g,
+                                     &p256.n),
+               Ok(p256));
+
+    println!("Finished curve validation tests");
+}
+
+fn p256_test() {
+    let curve = WeierstrassCurve::p256();
+    assert!(curve.is_on_curve(&curve.g));
+    assert_eq!(curve.base_mul(&curve.n), Point::Infinity);
+    assert_eq!(curve.base_mul(&Mpz::zero()), Point::Infinity);
+
+    // from python's cryptography
+    let vectors = [
+        ("1",
+         "6b17d1f2e12c4247f8bce6e563a440f277037d812deb33a0f4a13945d898c296",
+         "4fe342e2fe1a7f9b8ee7eb4a7c0f9e162bce33576b315ececbb6406837bf51f5"),
+        ("2",
+         "7cf27b188d034f7e8a52380304b51ac3c08969e277f21b35a60b48fc47669978",
+         "07775510db8ed040293d9ac69f7430dbba7dade63ce982299e04b79d227873d1"),
+        ("3",
+         "5ecbe4d1a6330a44c8f7ef951d4bf165e6c6b721efada985fb41661bc6e7fd6c",
+         "8734640c4998ff7e374b06ce1a64a2ecd82ab036384fb83d9a79b127a27d5032"),
+        ("c51e4753afdec1e6b6c6a5b992f43f8dd0c7a8933072708b6522468b2ffb06fd",
+         "942c9f408ead9d82d34a1b9a6a827ebe3e2ddf782b448d23be1b6143988ccef4",
+         "8c9eaf6c0d14d992fc63bad3e2496be2eee61cb5b97f65f428ca94a5d0ee19a1"),
+        ("ffffffff00000000ffffffffffffffffbce6faada7179e84f3b9cac2fc632550",
+         "6b17d1f2e12c4247f8bce6e563a440f277037d812deb33a0f4a13945d898c296",
+         "b01cbd1c01e58065711814b583f061e9d431cca994cea1313449bf97c840ae0a"),
+    ];
+    for &(k, x, y) in &vectors {
+        let k = Mpz::from_str_radix(k, 16).unwrap();
+        let expected = affine(x, y);
+        assert_eq!(curve.base_mul(&k), expected, "{} g", k);
+        assert_eq!(curve.scalar_mul_affine(&k, &curve.g), expected);
+    }
+    // (n - 1) g = -g
+    assert_eq!(curve.base_mul(&(&curve.n - Mpz::one())),
+               curve.negate(&curve.g));
+
+    // k1 g + k2 g = (k1 + k2) g, and diffie-hellman works out
+    let mut rng = seeded_rng(1);
+    for _ in 0..4 {
+        let k1 = random_mpz_below(&mut rng, &curve.n);
+        let k2 = random_mpz_below(&mut rng, &curve.n);
+        let (p1, p2) = (curve.base_mul(&k1), curve.base_mul(&k2));
+        assert_eq!(curve.add(&p1, &p2), curve.base_mul(&(&k1 + &k2)));
+        assert_eq!(curve.scalar_mul(&k1, &p2), curve.scalar_mul(&k2, &p1));
+    }
+
+    println!("Finished P-256 tests");
+}
+
+// RFC 7748 section 5.2 and 6.1
+fn x25519_test() {
+    let vectors = [
+        ("a546e36bf0527c9d3b16154b82465edd62144c0ac1fc5a18506a2244ba449ac4",
+         "e6db6867583030db3594c1a424b15f7c726624ec26b3353b10a903a6d0ab1c4c",
+         "c3da55379de9c6908e94ea4df28d084f32eccf03491c71f754b4075577a28552"),
+        ("4b66e9d4d1b4673c5ad22691957d6af5c11b6421e0ea01d42ca4169e7918ba0d",
+         "e5210f12786811d3f4b7959d0538ae2c31dbe7106fc03c3efc4cd549c715a493",
+         "95cbde9476e8907d7aade45cb4b873f88b595a68799fa152e6f8f7647aac7957"),
+    ];
+    for &(scalar, u, expected) in &vectors {
+        assert_eq!(x25519(&bytes32(scalar), &bytes32(u)), bytes32(expected));
+    }
+
+    // k = u = 9, then k, u = x25519(k, u), k
+    let mut k = [0u8; X25519_LEN];
+    k[0] = 9;
+    let mut u = k;
+    for i in 1..1001 {
+        let next = x25519(&k, &u);
+        u = k;
+        k = next;
+        if i == 1 {
+            assert_eq!(k, bytes32(concat!(
+                "422c8e7a6227d7bca1350b3e2bb7279f",
+                "7897b87bb6854b783c60e80311ae3079")));
+        }
+    }
+    assert_eq!(k, bytes32(concat!(
+        "684cf59ba83309552800ef566f2f4d3c",
+        "1c3887c49360e3875f2eb94d99532c51")));
+
+    let alice = bytes32(concat!(
+        "77076d0a7318a57d3c16c17251b26645",
+        "df4c2f87ebc0992ab177fba51db92c2a"));
+    let bob = bytes32(concat!(
+        "5dab087e624a8a4b79e17f8b83800ee6",
+        "6f3bb1292618b6fd1c2f8b27ff88e0eb"));
+    let alice_pub = x25519_public(&alice);
+    let bob_pub = x25519_public(&bob);
+    assert_eq!(alice_pub, bytes32(concat!(
+        "8520f0098930a754748b7ddcb43ef75a",
+        "0dbf3a0d26381af4eba4a98eaa9b4e6a")));
+    assert_eq!(bob_pub, bytes32(concat!(
+        "de9edb7d7b7dc1b4d35b61c2ece43537",
+        "3f8343c85b78674dadfc7e146f882b4f")));
+    let shared = bytes32(concat!(
+        "4a5d9d5ba4ce2de1728e3bf480350f25",
+        "e07e21c947d19e3376f09b3c1e161742"));
+    assert_eq!(x25519(&alice, &bob_pub), shared);
+    assert_eq!(x25519(&bob, &alice_pub), shared);
+
+    println!("Finished X25519 tests");
+}
+
+fn montgomery_ladder_test() {
+    let curve = MontgomeryCurve::curve25519();
+    let mut rng = seeded_rng(2);
+
+    // the base point has prime order, and the identity comes out as 0
+    assert_eq!(curve.base_ladder(&curve.order), Mpz::zero());
+    assert_eq!(curve.base_ladder(&Mpz::zero()), Mpz::zero());
+    assert_eq!(curve.base_ladder(&Mpz::one()), curve.base_u);
+    let order_1 = &curve.order + Mpz::one();
+    assert_eq!(curve.base_ladder(&order_1), curve.base_u);
+
+    // k1 (k2 B) = k2 (k1 B) = (k1 k2) B
+    for _ in 0..4 {
+        let k1 = random_mpz_below(&mut rng, &curve.order);
+        let k2 = random_mpz_below(&mut rng, &curve.order);
+        let u1 = curve.base_ladder(&k1);
+        let u2 = curve.base_ladder(&k2);
+        assert_eq!(curve.ladder(&k1, &u2), curve.ladder(&k2, &u1));
+        let k1k2 = (&k1 * &k2).modulus(&curve.order);
+        assert_eq!(curve.ladder(&k1, &u2), curve.base_ladder(&k1k2));
+    }
+
+    // u = 0 is the point of order 2, so clamped scalars, multiples of 8,
+    // send it to the identity
+    let zero = [0u8; X25519_LEN];
+    assert_eq!(x25519(&bytes32(concat!(
+        "a546e36bf0527c9d3b16154b82465edd",
+        "62144c0ac1fc5a18506a2244ba449ac4")), &zero), zero);
+
+    println!("Finished Montgomery ladder tests");
+}
+
+pub fn ec_test() {
+    small_curve_test();
+    curve_validation_test();
+    p256_test();
+    x25519_test();
+    montgomery_ladder_test();
+}
@@ -0,0 +1,87 @@
+use std::error;
+use std::fmt;
+use std::result;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    InvalidPadding,
+    InvalidHex(char),
+    InvalidBase64(char),
+    // AES only does 16, 24 and 32 byte keys
+    BadKeyLength(usize),
+    BadIVLength(usize),
+    // e.g. cbc or ecb that isn't a multiple of the block size
+    BadCiphertextLength(usize),
+    // e.g. rsa padding that won't fit in the modulus or more hkdf output
+    // than it can give, holds the max length
+    MessageTooLong(usize),
+    MalformedQuery(String),
+    // e.g. rsa primes that don't make a key, or a key missing parts
+    InvalidKey(&'static str),
+    // asn.1 that doesn't parse, or isn't the shape we expected
+    InvalidDer(&'static str),
+    // e.g. a pem file with the wrong BEGIN line
+    InvalidPem(String),
+    // the decrypted plaintext is in here for challenge 27
+    InvalidAscii(Vec<u8>),
+    // an x.509 certificate or chain that parses but doesn't check out
+    InvalidCertificate(String),
+    // diffie-hellman parameters or a peer's public that would give a weak
+    // or predictable shared secret
+    InvalidDHParameter(&'static str),
+    // elliptic curve parameters that don't make a curve, or a base point
+    // that isn't on it
+    InvalidCurve(&'static str),
+    // a CRT result that didn't re-encrypt to the input, e.g. a glitch in
+    // one of the exponentiations
+    FaultDetected,
+    AuthenticationFailed,
+    // deliberately doesn't say what part of the signature was wrong
+    InvalidSignature,
+    // an attack ran out of guesses, e.g. the oracle never said yes
+    AttackFailed(String),
+    NotImplemented(&'static str),
+}
+
+pub type Result<T> = result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::InvalidPadding => write!(f, "invalid padding"),
+            Error::InvalidHex(c) => write!(f, "invalid hex char {:?}", c),
+            Error::InvalidBase64(c) => {
+                write!(f, "invalid base64 char {:?}", c)
+            },
+            Error::BadKeyLength(len) => write!(f, "bad key length {}", len),
+            Error::BadIVLength(len) => write!(f, "bad iv length {}", len),
+            Error::BadCiphertextLength(len) => {
+                write!(f, "bad ciphertext length {}", len)
+            },
+            Error::MessageTooLong(max) => {
+                write!(f, "message too long, max is {} bytes", max)
+            },
+            Error::MalformedQuery(ref s) => {
+                write!(f, "malformed query string: {}", s)
+            },
+            Error::InvalidKey(s) => write!(f, "invalid key: {}", s),
+            Error::InvalidDer(s) => write!(f, "invalid der: {}", s),
+            Error::InvalidPem(ref s) => write!(f, "invalid pem: {}", s),
+            Error::InvalidAscii(ref v) => write!(f, "invalid ascii {:?}", v),
+            Error::InvalidCertificate(ref s) => {
+                write!(f, "invalid certificate: {}", s)
+            },
+            Error::InvalidDHParameter(s) => {
+                write!(f, "invalid dh parameter: {}", s)
+            },
+            Error::InvalidCurve(s) => write!(f, "invalid curve: {}", s),
+            Error::FaultDetected => write!(f, "fault detected"),
+            Error::AuthenticationFailed => write!(f, "authentication failed"),
+            Error::InvalidSignature => write!(f, "invalid signature"),
+            Error::AttackFailed(ref s) => write!(f, "attack failed: {}", s),
+            Error::NotImplemented(s) => write!(f, "{} not implemented", s),
+        }
+    }
+}
+
+impl error::Error for Error {}
@@ -1,3 +1,5 @@
+use error::{Error, Result};
+
 const NIBBLE_CHAR: [char; 16] = [
     '0', '1', '2', '3', '4',
     '5', '6', '7', '8', '9',
@@ -13,8 +15,8 @@ pub fn bytes_to_hex(buf: &[u8]) -> String {
     s
 }
 
-fn byte_to_nibble(c: u8) -> u8 {
-    match c as char {
+fn byte_to_nibble(c: u8) -> Result<u8> {
+    let nibble = match c as char {
         '0' => 0,
         '1' => 1,
         '2' => 2,
@@ -31,23 +33,22 @@ fn byte_to_nibble(c: u8) -> u8 {
         'd' | 'D' => 13,
         'e' | 'E' => 14,
         'f' | 'F' => 15,
-        _ => 255,
-    }
+        _ => return Err(Error::InvalidHex(c as char)),
+    };
+    Ok(nibble)
 }
 
-pub fn hex_to_bytes(s: &str) -> Vec<u8> {
-    // XXX: return reasonable errors
+pub fn hex_to_bytes(s: &str) -> Result<Vec<u8>> {
     let mut vec = Vec::with_capacity((s.len() + 1) / 2);
     let mut slice = s.as_bytes();
 
     if s.len() % 2 != 0 {
-        vec.push(byte_to_nibble(slice[0]));
+        vec.push(byte_to_nibble(slice[0])?);
         slice = &slice[1..]
     };
 
     for pair in slice.chunks(2) {
-        // XXX: return errors
-        vec.push((byte_to_nibble(pair[0]) << 4) + byte_to_nibble(pair[1]));
+        vec.push((byte_to_nibble(pair[0])? << 4) + byte_to_nibble(pair[1])?);
     }
-    vec
+    Ok(vec)
 }
@@ -1,19 +1,31 @@
 pub mod aes;
 pub mod asn1;
+pub mod attacks;
 pub mod base64;
 pub mod bytes;
 pub mod charfreq;
 pub mod dh;
+pub mod ec;
+pub mod error;
 pub mod hex;
 pub mod mac;
 pub mod mac_test;
+pub mod md4;
+pub mod merkle_damgard;
 pub mod mt19937;
 pub mod mt19937_test;
+pub mod pem;
 pub mod pkcs7;
+pub mod poly;
 pub mod rsa;
 pub mod sha1;
+pub mod sha2;
 pub mod srp;
 pub mod ssv;
 pub mod url;
 pub mod util;
+pub mod util_test;
+pub mod x509;
 pub mod xor;
+
+pub use error::{Error, Result};
@@ -1,11 +1,24 @@
-extern crate sha2;
-
-use self::sha2::{Sha256, Digest as SHA2Digest};
-
-// oops used different sha1 and sha2 unfortunately
+use error::{Error, Result};
+use md4::{Md4, Digest as MD4Digest};
+use merkle_damgard::MerkleDamgard;
 use sha1::{Sha1, Digest as SHA1Digest};
+use sha2::Sha256;
+use util::constant_time_eq;
 use xor::slice_xor;
 
+/// H(key || message), which is what length extension breaks
+pub fn cat_mac<H: MerkleDamgard>(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut hash = H::new();
+    hash.update(key);
+    hash.update(message);
+    hash.finish()
+}
+
+pub fn cat_mac_verify<H: MerkleDamgard>(key: &[u8], message: &[u8],
+                                        mac: &[u8]) -> bool {
+    constant_time_eq(&cat_mac::<H>(key, message), mac)
+}
+
 pub fn sha1_cat_mac_digest(key: &[u8], message: &[u8]) -> SHA1Digest {
     let mut v = key.to_vec();
     v.extend_from_slice(message);
@@ -25,63 +38,62 @@ pub fn sha1_cat_mac_verify(key: &[u8], message: &[u8],
     sha1_cat_mac_digest(key, message).data.state == hmac.data.state
 }
 
-pub fn sha1_pad(msg: &[u8]) -> Vec<u8> {
+pub fn md4_cat_mac_digest(key: &[u8], message: &[u8]) -> MD4Digest {
+    let mut v = key.to_vec();
+    v.extend_from_slice(message);
+
+    let mut hash = Md4::new();
+    hash.update(&v);
+    hash.digest()
+}
+
+pub fn md4_cat_mac(key: &[u8], message: &[u8]) -> Vec<u8> {
+    md4_cat_mac_digest(key, message).bytes().to_vec()
+}
+
+pub fn md4_cat_mac_verify(key: &[u8], message: &[u8],
+                          mac: &MD4Digest) -> bool {
+    md4_cat_mac_digest(key, message).data.state == mac.data.state
+}
+
+/// msg plus whatever padding H would add to it
+pub fn md_pad<H: MerkleDamgard>(msg: &[u8]) -> Vec<u8> {
     let mut padded = msg.to_vec();
-    sha1_pad_extend(&mut padded);
+    md_pad_extend::<H>(&mut padded);
     padded
 }
 
+pub fn md_pad_extend<H: MerkleDamgard>(msg: &mut Vec<u8>) {
+    let pad = H::padding(msg.len() as u64);
+    msg.extend_from_slice(&pad);
+}
+
+pub fn sha1_pad(msg: &[u8]) -> Vec<u8> {
+    md_pad::<Sha1>(msg)
+}
+
 pub fn sha1_pad_extend(msg: &mut Vec<u8>) {
-    let mut pad = [0u8; 64];
-    let len = msg.len();
-    let rem = len % 64;
-
-    // add a 1 bit then 0 bits until we get len % 64 == 56
-    pad[0] = 0x80;
-    if rem < 56 {
-        msg.extend_from_slice(&pad[..56 - rem]);
-    } else {
-        // gotta fill up this block then do 56 bytes in the next one
-        msg.extend_from_slice(&pad[..56] as &[u8]);
-        for _ in 0..64 - rem {
-            msg.push(0u8);
-        }
-    }
+    md_pad_extend::<Sha1>(msg)
+}
 
-    // last 8 bytes is the length of the msg in bits
-    let bitlen = len * 8;
-    let bitlen_as_block = [
-        ((bitlen >> 56) & 0xff) as u8,
-        ((bitlen >> 48) & 0xff) as u8,
-        ((bitlen >> 40) & 0xff) as u8,
-        ((bitlen >> 32) & 0xff) as u8,
-        ((bitlen >> 24) & 0xff) as u8,
-        ((bitlen >> 16) & 0xff) as u8,
-        ((bitlen >>  8) & 0xff) as u8,
-        ((bitlen >>  0) & 0xff) as u8,
-    ];
-    msg.extend_from_slice(&bitlen_as_block[..] as &[u8]);
-}
-
-fn sha1_bytes(input: &[u8]) -> Vec<u8> {
-    let mut hash = Sha1::new();
-    hash.update(input);
-    hash.digest().bytes().to_vec()
+// md4 is the same as sha1 but with a little endian length
+pub fn md4_pad(msg: &[u8]) -> Vec<u8> {
+    md_pad::<Md4>(msg)
 }
 
-fn sha256_bytes(input: &[u8]) -> Vec<u8> {
-    Sha256::digest(&input).to_vec()
+pub fn md4_pad_extend(msg: &mut Vec<u8>) {
+    md_pad_extend::<Md4>(msg)
 }
 
-pub fn hmac(key: &[u8], msg: &[u8], block_size: usize,
-            hash: fn (&[u8]) -> Vec<u8>) -> Vec<u8> {
+pub fn hmac<H: MerkleDamgard>(key: &[u8], msg: &[u8]) -> Vec<u8> {
+    let block_size = H::BLOCK_SIZE;
     let opad = vec!(0x5cu8; block_size);
     let ipad = vec!(0x36u8; block_size);
     let mut key_mem = vec!(0u8; block_size);
 
     if key.len() > block_size {
         // hash key if key too big
-        for (dst, src) in key_mem.iter_mut().zip(&hash(&key)) {
+        for (dst, src) in key_mem.iter_mut().zip(&H::hash(key)) {
             *dst = *src;
         }
     } else {
@@ -95,15 +107,60 @@ pub fn hmac(key: &[u8], msg: &[u8], block_size: usize,
     input.extend_from_slice({
         let mut ipad_input = slice_xor(&ipad, &key_mem);
         ipad_input.extend_from_slice(msg);
-        &hash(&ipad_input)
+        &H::hash(&ipad_input)
     });
-    hash(&input)
+    H::hash(&input)
 }
 
 pub fn hmac_sha1(key: &[u8], msg: &[u8]) -> Vec<u8> {
-    hmac(key, msg, 64, sha1_bytes)
+    hmac::<Sha1>(key, msg)
 }
 
 pub fn hmac_sha256(key: &[u8], msg: &[u8]) -> Vec<u8> {
-    hmac(key, msg, 64, sha256_bytes)
+    hmac::<Sha256>(key, msg)
+}
+
+/// RFC 5869 extract: a pseudorandom key from input keying material that
+/// may not be uniform, like a DH shared secret. No salt means a string of
+/// zeros as long as the hash.
+pub fn hkdf_extract<H: MerkleDamgard>(salt: &[u8], ikm: &[u8]) -> Vec<u8> {
+    if salt.is_empty() {
+        hmac::<H>(&vec!(0u8; H::DIGEST_LENGTH), ikm)
+    } else {
+        hmac::<H>(salt, ikm)
+    }
+}
+
+/// RFC 5869 expand: T(i) = HMAC(prk, T(i - 1) || info || i) until there's
+/// len bytes, which can't be more than 255 blocks
+pub fn hkdf_expand<H: MerkleDamgard>(prk: &[u8], info: &[u8],
+                                     len: usize) -> Result<Vec<u8>> {
+    let max_len = 255 * H::DIGEST_LENGTH;
+    if len > max_len {
+        return Err(Error::MessageTooLong(max_len));
+    }
+
+    let mut okm = Vec::with_capacity(len);
+    let mut block = Vec::new();
+    let mut counter = 1u8;
+    while okm.len() < len {
+        let mut input = block;
+        input.extend_from_slice(info);
+        input.push(counter);
+        block = hmac::<H>(prk, &input);
+        okm.extend_from_slice(&block);
+        counter = counter.wrapping_add(1);
+    }
+    okm.truncate(len);
+    Ok(okm)
+}
+
+pub fn hkdf<H: MerkleDamgard>(salt: &[u8], ikm: &[u8], info: &[u8],
+                              len: usize) -> Result<Vec<u8>> {
+    hkdf_expand::<H>(&hkdf_extract::<H>(salt, ikm), info, len)
+}
+
+pub fn hkdf_sha256(salt: &[u8], ikm: &[u8], info: &[u8],
+                   len: usize) -> Result<Vec<u8>> {
+    hkdf::<Sha256>(salt, ikm, info, len)
 }
@@ -4,8 +4,15 @@ use std::time::Duration;
 
 use hex::hex_to_bytes;
 use mac::{sha1_cat_mac, sha1_cat_mac_digest, sha1_pad, sha1_pad_extend,
-          sha1_cat_mac_verify, hmac_sha1, hmac_sha256};
+          sha1_cat_mac_verify, hmac_sha1, hmac_sha256, md4_cat_mac_digest,
+          md4_pad_extend, md4_cat_mac_verify, hkdf_extract, hkdf_expand,
+          hkdf_sha256};
+use md4;
+use md4::Md4;
+use merkle_damgard::MerkleDamgard;
+use sha2::{Sha224, Sha256, Sha384, Sha512, sha224, sha256, sha384, sha512};
 use sha1::{Sha1, Digest, DIGEST_LENGTH};
+use error::Error;
 use util::{rand_bytes_range, assert_slice_cmp};
 
 fn sha1_fixate_test() {
@@ -77,6 +84,145 @@ fn sha1_cat_mac_length_ext_test() {
     panic!("could not find a matching key_len");
 }
 
+fn md4_test() {
+    // RFC 1320 appendix A.5
+    let tests = [
+        ("", "31d6cfe0d16ae931b73c59d7e0c089c0"),
+        ("a", "bde52cb31de33e46245e05fbdbd6fb24"),
+        ("abc", "a448017aaf21d8525fc10ae87aa6729d"),
+        ("message digest", "d9130a8164549fe818874806e1c7014b"),
+        ("abcdefghijklmnopqrstuvwxyz", "d79e1c308aa5bbcdeea8ed63df412da9"),
+        ("ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789",
+         "043f8582f241db351ce627e153e7f0e4"),
+        ("12345678901234567890123456789012345678901234567890123456789012345678901234567890",
+         "e33b4ddc9c38f2199c3e7b164fcc0536"),
+    ];
+
+    for &(msg, expected) in &tests {
+        let mut hash = Md4::new();
+        hash.update(msg.as_bytes());
+        assert_eq!(hash.digest().to_string(), expected,
+                   "md4 test vector failed for {:?}", msg);
+        assert_eq!(&md4::digest(msg.as_bytes()) as &[u8],
+                   &hex_to_bytes(expected).unwrap() as &[u8]);
+
+        // same thing fed in a few bytes at a time
+        for &chunk_len in &[1, 7, 63, 64, 65] {
+            let mut hash = Md4::new();
+            for chunk in msg.as_bytes().chunks(chunk_len) {
+                hash.update(chunk);
+            }
+            assert_eq!(hash.digest().to_string(), expected,
+                       "md4 chunked {} failed for {:?}", chunk_len, msg);
+        }
+    }
+}
+
+// FIPS 180-4 example vectors, (message, sha224, sha256, sha384, sha512)
+const SHA2_TEST_VECTORS: [(&str, &str, &str, &str, &str); 4] = [
+    ("",
+     "d14a028c2a3a2bc9476102bb288234c415a2b01f828ea62ac5b3e42f",
+     "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+     "38b060a751ac96384cd9327eb1b1e36a21fdb71114be07434c0cc7bf63f6e1da274edebfe76f65fbd51ad2f14898b95b",
+     "cf83e1357eefb8bdf1542850d66d8007d620e4050b5715dc83f4a921d36ce9ce47d0d13c5d85f2b0ff8318d2877eec2f63b931bd47417a81a538327af927da3e"),
+    ("abc",
+     "23097d223405d8228642a477bda255b32aadbce4bda0b3f7e36c9da7",
+     "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+     "cb00753f45a35e8bb5a03d699ac65007272c32ab0eded1631a8b605a43ff5bed8086072ba1e7cc2358baeca134c825a7",
+     "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f"),
+    ("abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+     "75388b16512776cc5dba5da1fd890150b0c6455cb4f58b1952522525",
+     "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1",
+     "3391fdddfc8dc7393707a65b1b4709397cf8b1d162af05abfe8f450de5f36bc6b0455a8520bc4e6f5fe95b1fe3c8452b",
+     "204a8fc6dda82f0a0ced7beb8e08a41657c16ef468b228a8279be331a703c33596fd15c13b1b07f9aa1d3bea57789ca031ad85c7a71dd70354ec631238ca3445"),
+    ("abcdefghbcdefghicdefghijdefghijkefghijklfghijklmghijklmnhijklmnoijklmnopjklmnopqklmnopqrlmnopqrsmnopqrstnopqrstu",
+     "c97ca9a559850ce97a04a96def6d99a9e0e0e2ab14e6b8df265fc0b3",
+     "cf5b16a778af8380036ce59e7b0492370b249b11e8f07a51afac45037afee9d1",
+     "09330c33f71147e83d192fc782cd1b4753111b173b3b05d22fa08086e3b0f712fcc7c71a557e2db966c3e9fa91746039",
+     "8e959b75dae313da8cf4f72814fc143f8f7779c6eb9f7fa17299aeadb6889018501d289e4900f7e4331b99dec4b5433ac7d329eeb6dd26545e96e55b874be909"),
+];
+
+fn sha2_vector_test<H: MerkleDamgard>(name: &str, msg: &[u8],
+                                      expected: &str) {
+    let expected = hex_to_bytes(expected).unwrap();
+    assert_eq!(expected.len(), H::DIGEST_LENGTH);
+    assert_eq!(H::hash(msg), expected, "{} test vector failed for {:?}",
+               name, msg);
+
+    // same thing fed in a few bytes at a time
+    for &chunk_len in &[1, 7, 63, 64, 65, 127, 128, 129] {
+        let mut hash = H::new();
+        for chunk in msg.chunks(chunk_len) {
+            hash.update(chunk);
+        }
+        assert_eq!(hash.finish(), expected, "{} chunked {} failed for {:?}",
+                   name, chunk_len, msg);
+    }
+}
+
+fn sha2_test() {
+    for &(msg, sha224_str, sha256_str, sha384_str, sha512_str)
+            in &SHA2_TEST_VECTORS {
+        let msg = msg.as_bytes();
+        sha2_vector_test::<Sha224>("sha224", msg, sha224_str);
+        sha2_vector_test::<Sha256>("sha256", msg, sha256_str);
+        sha2_vector_test::<Sha384>("sha384", msg, sha384_str);
+        sha2_vector_test::<Sha512>("sha512", msg, sha512_str);
+    }
+
+    // a million a's, the long one from the nist examples
+    let msg = vec!(b'a'; 1_000_000);
+    assert_slice_cmp("sha224 million a", &sha224(&msg), &hex_to_bytes(
+        "20794655980c91d8bbb4c1ea97618a4bf03f42581948b2ee4ee7ad67").unwrap());
+    assert_slice_cmp("sha256 million a", &sha256(&msg), &hex_to_bytes(
+        "cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0")
+        .unwrap());
+    assert_slice_cmp("sha384 million a", &sha384(&msg), &hex_to_bytes(
+        "9d0e1809716474cb086e834e310a4a1ced149e9c00f248527972cec5704c2a5b07b8b3dc38ecc4ebae97ddd87f3d8985")
+        .unwrap());
+    assert_slice_cmp("sha512 million a", &sha512(&msg), &hex_to_bytes(
+        "e718483d0ce769644e2e42c7bc15b4638e1f98b13b2044285632a803afa973ebde0ff244877ea60a4cb0432ce577c31beb009c5c2c49aa2e4eadb217ad8cc09b")
+        .unwrap());
+}
+
+fn md4_cat_mac_length_ext_test() {
+    let orig = "comment1=cooking%20MCs;userdata=foo;comment2=%20like%20a%20pound%20of%20bacon";
+    let suffix = ";admin=true";
+
+    let key = rand_bytes_range(1, 64);
+    println!("md4_cat_mac length extension test with key {:?} {}", key,
+             key.len());
+
+    let mod_mac = {
+        let orig_digest = md4_cat_mac_digest(&key, orig.as_bytes());
+        Md4::new_with_digest(&orig_digest)
+    };
+
+    // brute force key lengths, same as the sha1 version
+    for key_len in 1..64 {
+        let mut msg = vec!(0u8; key_len);
+        msg.extend_from_slice(orig.as_bytes());
+        md4_pad_extend(&mut msg);
+        let orig_padded_len_guess = msg.len();
+
+        msg.extend_from_slice(suffix.as_bytes());
+        md4_pad_extend(&mut msg);
+
+        let mut mac = mod_mac.clone();
+        mac.update(&msg[orig_padded_len_guess..]);
+
+        // don't do digest() because it does the length padding again
+        let hash = md4::Digest{data: mac.state};
+
+        let admin_msg = &msg[key_len..orig_padded_len_guess + suffix.len()];
+        if md4_cat_mac_verify(&key, admin_msg, &hash) {
+            println!("found correct md4 mac at key_len {}", key_len);
+            return;
+        }
+    }
+    panic!("could not find a matching md4 key_len");
+}
+
 fn hmac_sha_test() {
     const TEST_VECTORS: [(&'static str, &'static str,
                           &'static str, &'static str); 2] = [
@@ -91,15 +237,59 @@ fn hmac_sha_test() {
     ];
 
     for &(key, msg, sha1_str, sha256_str) in &TEST_VECTORS {
-        assert_eq!(&hex_to_bytes(sha1_str),
+        assert_eq!(&hex_to_bytes(sha1_str).unwrap(),
                    &hmac_sha1(key.as_bytes(), msg.as_bytes()),
                    "hmac_sha1 test vector failed");
-        assert_eq!(&hex_to_bytes(sha256_str),
+        assert_eq!(&hex_to_bytes(sha256_str).unwrap(),
                    &hmac_sha256(key.as_bytes(), msg.as_bytes()),
                    "hmac_sha256 test vector failed");
     }
 }
 
+// RFC 5869 appendix A, the sha256 ones
+fn hkdf_test() {
+    let ikm_11: Vec<u8> = vec!(0x0b; 22);
+    let ikm_2: Vec<u8> = (0x00..0x50).collect();
+    let salt_1: Vec<u8> = (0x00..0x0d).collect();
+    let salt_2: Vec<u8> = (0x60..0xb0).collect();
+    let info_1: Vec<u8> = (0xf0..0xfa).collect();
+    let info_2: Vec<u8> = (0xb0..=0xff).collect();
+    let test_vectors = [
+        (&ikm_11, &salt_1, &info_1, 42,
+         "077709362c2e32df0ddc3f0dc47bba6390b6c73bb50f9c3122ec844ad7c2b3e5",
+         concat!("3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf",
+                 "34007208d5b887185865")),
+        (&ikm_2, &salt_2, &info_2, 82,
+         "06a6b88c5853361a06104c9ceb35b45cef760014904671014a193f40c15fc244",
+         concat!("b11e398dc80327a1c8e7f78c596a49344f012eda2d4efad8a050cc4c19afa97c",
+                 "59045a99cac7827271cb41c65e590e09da3275600c2f09b8367793a9aca3db71",
+                 "cc30c58179ec3e87c14c01d5c1f3434f1d87")),
+        (&ikm_11, &Vec::new(), &Vec::new(), 42,
+         "19ef24a32c717b167f33a91d6f648bdf96596776afdb6377ac434c1c293ccb04",
+         concat!("8da4e775a563c18f715f802a063c5a31b8a11f5c5ee1879ec3454e5f3c738d2d",
+                 "9d201395faa4b61a96c8")),
+    ];
+
+    for &(ikm, salt, info, len, prk_str, okm_str) in &test_vectors {
+        let prk = hkdf_extract::<Sha256>(salt, ikm);
+        assert_eq!(prk, hex_to_bytes(prk_str).unwrap());
+        let okm = hex_to_bytes(okm_str).unwrap();
+        assert_eq!(hkdf_expand::<Sha256>(&prk, info, len).unwrap(), okm);
+        assert_eq!(hkdf_sha256(salt, ikm, info, len).unwrap(), okm);
+
+        // shorter outputs are prefixes of longer ones
+        assert_eq!(hkdf_expand::<Sha256>(&prk, info, 7).unwrap(), &okm[..7]);
+    }
+
+    let prk = vec!(0u8; 32);
+    assert_eq!(hkdf_expand::<Sha256>(&prk, b"", 255 * 32).unwrap().len(),
+               255 * 32);
+    assert_eq!(hkdf_expand::<Sha256>(&prk, b"", 255 * 32 + 1),
+               Err(Error::MessageTooLong(255 * 32)));
+
+    println!("Finished HKDF tests");
+}
+
 fn insecure_cmp(calc_hmac: &[u8], hmac: &[u8]) -> Result<(), ()> {
     if calc_hmac.len() != hmac.len() {
         return Err(());
@@ -200,9 +390,11 @@ pub fn mac_test(full_test: bool) {
 
     sha1_fixate_test();
     sha1_cat_mac_length_ext_test();
-    // XXX: skip md4 because too lazy to port rust versions and don't
-    // want to import the whole thing
+    md4_test();
+    md4_cat_mac_length_ext_test();
+    sha2_test();
     hmac_sha_test();
+    hkdf_test();
     if full_test {
         hmac_sha1_timing_test();
     }
@@ -1,27 +1,39 @@
 extern crate cryptopals;
 
 use cryptopals::aes::aes_test;
+use cryptopals::asn1::test::asn1_test;
+use cryptopals::attacks::attacks_test;
 use cryptopals::base64::base64_test;
 use cryptopals::bytes::hamming_distance_test;
 use cryptopals::dh::test::dh_test;
+use cryptopals::ec::test::ec_test;
 use cryptopals::mac_test::mac_test;
 use cryptopals::mt19937_test::mt19937_test;
 use cryptopals::pkcs7::pkcs7_test;
+use cryptopals::poly::test::poly_test;
 use cryptopals::rsa::test::rsa_test;
 use cryptopals::srp::test::srp_test;
 use cryptopals::url::url_test;
+use cryptopals::util_test::util_test;
+use cryptopals::x509::test::x509_test;
 use cryptopals::xor::xor_test;
 
 fn main() {
-    rsa_test();
+    asn1_test();
+    rsa_test(false);
+    x509_test();
     srp_test();
     dh_test();
+    ec_test();
     base64_test();
     xor_test();
     hamming_distance_test();
     mt19937_test();
+    util_test();
     pkcs7_test();
+    poly_test();
     url_test();
     mac_test(false);
     aes_test(false);
+    attacks_test();
 }
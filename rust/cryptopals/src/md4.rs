@@ -0,0 +1,237 @@
+use std::cmp;
+use std::fmt;
+
+use merkle_damgard::{MerkleDamgard, DigestIsState};
+
+/// The length of a MD4 digest in bytes
+pub const DIGEST_LENGTH: usize = 16;
+
+const BLOCK_SIZE: usize = 64;
+
+/// MD4 (RFC 1320), laid out the same way as sha1::Sha1 so the length
+/// extension code looks the same for both
+#[derive(Clone)]
+pub struct Md4 {
+    pub state: Md4State,
+    block: [u8; BLOCK_SIZE],
+    block_len: usize,
+    // bytes in all the blocks processed so far
+    len: u64,
+}
+
+#[derive(Copy, Clone)]
+pub struct Md4State {
+    pub state: [u32; 4],
+}
+
+/// Digest generated from a `Md4` instance
+pub struct Digest {
+    pub data: Md4State,
+}
+
+const DEFAULT_STATE: Md4State =
+    Md4State { state: [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476] };
+
+pub fn digest(msg: &[u8]) -> [u8; DIGEST_LENGTH] {
+    let mut hash = Md4::new();
+    hash.update(msg);
+    hash.digest().bytes()
+}
+
+impl Default for Md4 {
+    fn default() -> Md4 {
+        Md4::new()
+    }
+}
+
+impl Md4 {
+    pub fn new() -> Md4 {
+        Md4 {
+            state: DEFAULT_STATE,
+            block: [0; BLOCK_SIZE],
+            block_len: 0,
+            len: 0,
+        }
+    }
+
+    /// Picks up hashing from a previous digest, the length is up to the
+    /// caller to deal with
+    pub fn new_with_digest(digest: &Digest) -> Md4 {
+        Md4 {
+            state: digest.data,
+            block: [0; BLOCK_SIZE],
+            block_len: 0,
+            len: 0,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.state = DEFAULT_STATE;
+        self.block_len = 0;
+        self.len = 0;
+    }
+
+    pub fn update(&mut self, mut data: &[u8]) {
+        // top up whatever's left over from last time first
+        if self.block_len > 0 {
+            let amt = cmp::min(data.len(), BLOCK_SIZE - self.block_len);
+            self.block[self.block_len..self.block_len + amt]
+                .copy_from_slice(&data[..amt]);
+            self.block_len += amt;
+            data = &data[amt..];
+            if self.block_len < BLOCK_SIZE {
+                return;
+            }
+            let block = self.block;
+            self.state.process(&block);
+            self.len += BLOCK_SIZE as u64;
+            self.block_len = 0;
+        }
+
+        for chunk in data.chunks(BLOCK_SIZE) {
+            if chunk.len() == BLOCK_SIZE {
+                self.state.process(chunk);
+                self.len += BLOCK_SIZE as u64;
+            } else {
+                self.block[..chunk.len()].copy_from_slice(chunk);
+                self.block_len = chunk.len();
+            }
+        }
+    }
+
+    pub fn digest(&self) -> Digest {
+        let mut state = self.state;
+        let bits = (self.len + self.block_len as u64) * 8;
+
+        // same padding as sha1 except the length is little endian
+        let mut last = [0u8; BLOCK_SIZE * 2];
+        last[..self.block_len].copy_from_slice(&self.block[..self.block_len]);
+        last[self.block_len] = 0x80;
+        if self.block_len < 56 {
+            last[56..64].copy_from_slice(&bits.to_le_bytes());
+            state.process(&last[..64]);
+        } else {
+            last[120..128].copy_from_slice(&bits.to_le_bytes());
+            state.process(&last[..64]);
+            state.process(&last[64..]);
+        }
+
+        Digest { data: state }
+    }
+}
+
+impl Digest {
+    pub fn bytes(&self) -> [u8; DIGEST_LENGTH] {
+        let mut result = [0u8; DIGEST_LENGTH];
+        for (dst, word) in result.chunks_mut(4).zip(&self.data.state) {
+            dst.copy_from_slice(&word.to_le_bytes());
+        }
+        result
+    }
+}
+
+impl Md4State {
+    fn process(&mut self, block: &[u8]) {
+        assert!(block.len() == BLOCK_SIZE);
+        let mut x = [0u32; 16];
+        for (word, chunk) in x.iter_mut().zip(block.chunks(4)) {
+            *word = u32::from_le_bytes([chunk[0], chunk[1], chunk[2],
+                                        chunk[3]]);
+        }
+
+        fn f(x: u32, y: u32, z: u32) -> u32 {
+            (x & y) | (!x & z)
+        }
+        fn g(x: u32, y: u32, z: u32) -> u32 {
+            (x & y) | (x & z) | (y & z)
+        }
+        fn h(x: u32, y: u32, z: u32) -> u32 {
+            x ^ y ^ z
+        }
+
+        // (round function, constant, word order, shifts)
+        type Round = (fn(u32, u32, u32) -> u32, u32, [usize; 16], [u32; 4]);
+        let rounds: [Round; 3] = [
+            (f, 0,
+             [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+             [3, 7, 11, 19]),
+            (g, 0x5a827999,
+             [0, 4, 8, 12, 1, 5, 9, 13, 2, 6, 10, 14, 3, 7, 11, 15],
+             [3, 5, 9, 13]),
+            (h, 0x6ed9eba1,
+             [0, 8, 4, 12, 2, 10, 6, 14, 1, 9, 5, 13, 3, 11, 7, 15],
+             [3, 9, 11, 15]),
+        ];
+
+        let mut a = self.state[0];
+        let mut b = self.state[1];
+        let mut c = self.state[2];
+        let mut d = self.state[3];
+
+        for &(func, k, order, shifts) in &rounds {
+            for i in 0..16 {
+                // the rfc goes [abcd] [dabc] [cdab] [bcda], rotating the
+                // registers after each step does the same thing
+                let tmp = a.wrapping_add(func(b, c, d))
+                           .wrapping_add(x[order[i]])
+                           .wrapping_add(k)
+                           .rotate_left(shifts[i % 4]);
+                a = d;
+                d = c;
+                c = b;
+                b = tmp;
+            }
+        }
+
+        self.state[0] = self.state[0].wrapping_add(a);
+        self.state[1] = self.state[1].wrapping_add(b);
+        self.state[2] = self.state[2].wrapping_add(c);
+        self.state[3] = self.state[3].wrapping_add(d);
+    }
+}
+
+impl MerkleDamgard for Md4 {
+    const BLOCK_SIZE: usize = BLOCK_SIZE;
+    const DIGEST_LENGTH: usize = DIGEST_LENGTH;
+    const LENGTH_SIZE: usize = 8;
+    const BIG_ENDIAN: bool = false;
+
+    fn new() -> Md4 {
+        Md4::new()
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        Md4::update(self, data)
+    }
+
+    fn finish(&self) -> Vec<u8> {
+        self.digest().bytes().to_vec()
+    }
+
+    fn export_state(&self) -> (Vec<u8>, u64) {
+        (Digest { data: self.state }.bytes().to_vec(), self.len)
+    }
+
+    fn import_state(state: &[u8], len: u64) -> Md4 {
+        assert!(state.len() == DIGEST_LENGTH,
+                "bad md4 state length {}", state.len());
+        let mut hash = Md4::new();
+        for (word, chunk) in hash.state.state.iter_mut().zip(state.chunks(4)) {
+            *word = u32::from_le_bytes([chunk[0], chunk[1], chunk[2],
+                                        chunk[3]]);
+        }
+        hash.len = len;
+        hash
+    }
+}
+
+impl DigestIsState for Md4 {}
+
+impl fmt::Display for Digest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for b in self.bytes().iter() {
+            write!(f, "{:02x}", b)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,57 @@
+/// Hashes built by iterating a compression function over padded blocks,
+/// e.g. MD4, SHA-1 and SHA-2. Exposes enough of the insides to pad a
+/// message the same way the hash does and to get at the chaining state.
+pub trait MerkleDamgard: Clone {
+    const BLOCK_SIZE: usize;
+    const DIGEST_LENGTH: usize;
+    /// Bytes at the end of the padding holding the message length in bits
+    const LENGTH_SIZE: usize;
+    /// MD4 stores the length little endian, SHA big endian
+    const BIG_ENDIAN: bool;
+
+    fn new() -> Self;
+
+    fn update(&mut self, data: &[u8]);
+
+    /// Pads and returns the digest, doesn't change self
+    fn finish(&self) -> Vec<u8>;
+
+    /// (chaining state as digest bytes, bytes processed). Only whole
+    /// blocks count, anything buffered from update isn't in here. For
+    /// truncated hashes like SHA-224 this is the whole untruncated state.
+    fn export_state(&self) -> (Vec<u8>, u64);
+
+    /// Picks up from a state as export_state gives it, len is the number
+    /// of bytes already hashed and should be a multiple of BLOCK_SIZE
+    fn import_state(state: &[u8], len: u64) -> Self;
+
+    fn hash(msg: &[u8]) -> Vec<u8> {
+        let mut h = Self::new();
+        h.update(msg);
+        h.finish()
+    }
+
+    /// What the hash tacks on to the end of a msg_len byte message: a 1
+    /// bit, 0s until there's just room for the length, then the length
+    fn padding(msg_len: u64) -> Vec<u8> {
+        let bs = Self::BLOCK_SIZE as u64;
+        let used = (msg_len + 1 + Self::LENGTH_SIZE as u64) % bs;
+        let zeros = ((bs - used) % bs) as usize;
+
+        let mut pad = vec!(0x80u8);
+        pad.extend(vec!(0u8; zeros));
+        let bitlen = (msg_len as u128) * 8;
+        if Self::BIG_ENDIAN {
+            let bytes = bitlen.to_be_bytes();
+            pad.extend_from_slice(&bytes[bytes.len() - Self::LENGTH_SIZE..]);
+        } else {
+            pad.extend_from_slice(&bitlen.to_le_bytes()[..Self::LENGTH_SIZE]);
+        }
+        pad
+    }
+}
+
+/// A MerkleDamgard hash whose digest is its whole chaining state, so
+/// import_state can pick back up from a digest, which is all length
+/// extension needs. SHA-224 and SHA-384 throw part of theirs away.
+pub trait DigestIsState: MerkleDamgard {}
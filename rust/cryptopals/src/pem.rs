@@ -0,0 +1,42 @@
+use base64::{base64_encode, base64_decode};
+use error::{Error, Result};
+
+/// Base64 of der between BEGIN and END lines, wrapped at 64 columns like
+/// openssl does
+pub fn pem_encode(label: &str, der: &[u8]) -> String {
+    let encoded = base64_encode(der);
+    let mut pem = format!("-----BEGIN {}-----\n", label);
+    for line in encoded.as_bytes().chunks(64) {
+        // base64 is all ascii
+        pem.push_str(&String::from_utf8_lossy(line));
+        pem.push('\n');
+    }
+    pem.push_str(&format!("-----END {}-----\n", label));
+    pem
+}
+
+/// The der out of the first block with this label. Anything outside the
+/// BEGIN and END lines is ignored.
+pub fn pem_decode(label: &str, pem: &str) -> Result<Vec<u8>> {
+    let begin = format!("-----BEGIN {}-----", label);
+    let end = format!("-----END {}-----", label);
+
+    let start = match pem.find(&begin) {
+        Some(i) => i + begin.len(),
+        None => return Err(Error::InvalidPem(format!("no {:?}", begin))),
+    };
+    let len = match pem[start..].find(&end) {
+        Some(len) => len,
+        None => return Err(Error::InvalidPem(format!("no {:?}", end))),
+    };
+
+    // rfc 1421 style "Proc-Type: ..." headers mean it's encrypted, which
+    // we don't do
+    let body = &pem[start..start + len];
+    if body.contains(':') {
+        return Err(Error::InvalidPem("headers not supported".to_string()));
+    }
+
+    let encoded: String = body.split_whitespace().collect();
+    base64_decode(&encoded)
+}
@@ -1,5 +1,5 @@
+use error::{Error, Result};
 use hex::{hex_to_bytes,bytes_to_hex};
-use std::string::String;
 
 pub fn pkcs7_pad(buf: &[u8], block_size: usize) -> Vec<u8> {
     let pad_len = (block_size - (buf.len() % block_size)) as u8;
@@ -13,50 +13,27 @@ pub fn pkcs7_pad(buf: &[u8], block_size: usize) -> Vec<u8> {
     out
 }
 
-pub fn pkcs7_unpad<'a>(buf: &'a [u8], block_size: usize) -> &'a [u8] {
-    match pkcs7_maybe_unpad(buf, block_size) {
-        Ok(result) => result,
-        Err(e) => panic!(e),
-    }
-}
-
-pub fn pkcs7_unpad_copy(buf: &[u8], block_size: usize) -> Vec<u8> {
-    pkcs7_unpad(buf, block_size).to_vec()
-}
-
-pub fn pkcs7_maybe_unpad_copy(buf: &[u8],
-                              block_size: usize) -> Result<Vec<u8>, String> {
-    match pkcs7_maybe_unpad(buf, block_size) {
-        Ok(result) => Ok(result.to_vec()),
-        Err(e) => Err(e),
-    }
-}
-
-pub fn pkcs7_maybe_unpad<'a>(buf: &'a [u8],
-                             block_size: usize) -> Result<&'a [u8], String> {
-    if buf.len() == 0 {
-        return Err(String::from("pkcs7_unpad len 0 buf"));
+pub fn pkcs7_unpad<'a>(buf: &'a [u8], block_size: usize) -> Result<&'a [u8]> {
+    if buf.is_empty() {
+        return Err(Error::InvalidPadding);
     }
     let pad = *buf.last().unwrap() as usize;
-    if pad == 0 {
-        return Err(format!("pkcs7_unpad 0 is not valid padding {:?}", buf));
-    } else if pad > block_size {
-        return Err(
-            format!("pkcs7_unpad padding longer than block_size {} {:?}",
-                    block_size, buf));
-    } else if pad > buf.len() {
-        return Err(format!("pkcs7_unpad padding longer than buf {:?}", buf));
+    if pad == 0 || pad > block_size || pad > buf.len() {
+        return Err(Error::InvalidPadding);
     }
 
-    for (i, &b) in (&buf[buf.len() - pad..]).iter().enumerate() {
+    for &b in &buf[buf.len() - pad..] {
         if b != pad as u8 {
-            return Err(format!("bad byte at {}: {:?}",
-                               buf.len() - pad + i, buf));
+            return Err(Error::InvalidPadding);
         }
     }
     Ok(&buf[0..(buf.len() - pad)])
 }
 
+pub fn pkcs7_unpad_copy(buf: &[u8], block_size: usize) -> Result<Vec<u8>> {
+    pkcs7_unpad(buf, block_size).map(|unpadded| unpadded.to_vec())
+}
+
 pub fn pkcs7_test() {
     let tests = [
         ("aabb", "aabb0202", 4),
@@ -64,11 +41,26 @@ pub fn pkcs7_test() {
     ];
 
     for &(unpadded, padded, block_size) in &tests {
-        let result = pkcs7_pad(&hex_to_bytes(unpadded), block_size);
-        if &result != &hex_to_bytes(padded) {
+        let result = pkcs7_pad(&hex_to_bytes(unpadded).unwrap(), block_size);
+        if &result != &hex_to_bytes(padded).unwrap() {
             panic!("FAILURE: pkcs7 padding expected {} got {}",
                    padded, bytes_to_hex(&result));
         }
+        assert_eq!(pkcs7_unpad(&result, block_size),
+                   Ok(&hex_to_bytes(unpadded).unwrap() as &[u8]));
+    }
+
+    let bad_tests = [
+        ("", 4),
+        ("aabbcc00", 4),
+        ("aabbcc05", 4),
+        ("aa030203", 4),
+        ("0505050505", 4),
+    ];
+    for &(padded, block_size) in &bad_tests {
+        let buf = hex_to_bytes(padded).unwrap();
+        assert_eq!(pkcs7_unpad(&buf, block_size), Err(Error::InvalidPadding),
+                   "pkcs7_unpad accepted {}", padded);
     }
     println!("Finished PKCS7 tests");
 }
@@ -0,0 +1,396 @@
+pub mod test;
+
+use std::fmt;
+
+use aes::gcm::{gf128_mul, block_to_u128};
+use util::rand_bytes;
+
+// GCM bit order, the msb is x^0
+pub const GF128_ONE: u128 = 1u128 << 127;
+
+pub fn gf128_square(a: u128) -> u128 {
+    gf128_mul(a, a)
+}
+
+pub fn gf128_pow(a: u128, mut exp: u128) -> u128 {
+    let mut base = a;
+    let mut result = GF128_ONE;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = gf128_mul(result, base);
+        }
+        base = gf128_square(base);
+        exp >>= 1;
+    }
+    result
+}
+
+pub fn gf128_inv(a: u128) -> u128 {
+    assert!(a != 0, "gf128_inv of zero");
+    // a^(2^128 - 1) == 1 so a^(2^128 - 2) is the inverse
+    gf128_pow(a, u128::MAX - 1)
+}
+
+// squaring is a bijection in char 2, so every element has a square root
+// a^(2^127) since (a^(2^127))^2 == a^(2^128) == a
+pub fn gf128_sqrt(a: u128) -> u128 {
+    let mut result = a;
+    for _ in 0..127 {
+        result = gf128_square(result);
+    }
+    result
+}
+
+fn gf128_rand() -> u128 {
+    block_to_u128(&rand_bytes(16))
+}
+
+/// Polynomial over GF(2^128), coeffs[i] is the coefficient of x^i.
+/// Always normalized so the zero polynomial has no coefficients.
+#[derive(Clone, PartialEq, Eq)]
+pub struct GF128Poly {
+    pub coeffs: Vec<u128>,
+}
+
+impl fmt::Debug for GF128Poly {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+        for (i, c) in self.coeffs.iter().enumerate().rev() {
+            if *c == 0 {
+                continue;
+            }
+            if i != self.degree() {
+                write!(f, " + ")?;
+            }
+            write!(f, "{:032x}*x^{}", c, i)?;
+        }
+        Ok(())
+    }
+}
+
+impl GF128Poly {
+    pub fn new(coeffs: &[u128]) -> GF128Poly {
+        let mut poly = GF128Poly{coeffs: coeffs.to_vec()};
+        poly.normalize();
+        poly
+    }
+
+    pub fn zero() -> GF128Poly {
+        GF128Poly{coeffs: Vec::new()}
+    }
+
+    pub fn one() -> GF128Poly {
+        GF128Poly{coeffs: vec!(GF128_ONE)}
+    }
+
+    /// The polynomial x
+    pub fn x() -> GF128Poly {
+        GF128Poly{coeffs: vec!(0, GF128_ONE)}
+    }
+
+    fn normalize(&mut self) {
+        while let Some(&0) = self.coeffs.last() {
+            self.coeffs.pop();
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.coeffs.is_empty()
+    }
+
+    pub fn is_one(&self) -> bool {
+        self.coeffs.len() == 1 && self.coeffs[0] == GF128_ONE
+    }
+
+    /// Degree of the zero polynomial is treated as 0
+    pub fn degree(&self) -> usize {
+        if self.is_zero() {
+            0
+        } else {
+            self.coeffs.len() - 1
+        }
+    }
+
+    pub fn lead(&self) -> u128 {
+        match self.coeffs.last() {
+            Some(&c) => c,
+            None => 0,
+        }
+    }
+
+    pub fn add(&self, other: &GF128Poly) -> GF128Poly {
+        // char 2 so subtraction is the same thing
+        let len = ::std::cmp::max(self.coeffs.len(), other.coeffs.len());
+        let mut coeffs = vec!(0u128; len);
+        for (dst, src) in coeffs.iter_mut().zip(&self.coeffs) {
+            *dst ^= *src;
+        }
+        for (dst, src) in coeffs.iter_mut().zip(&other.coeffs) {
+            *dst ^= *src;
+        }
+        GF128Poly::new(&coeffs)
+    }
+
+    pub fn scale(&self, c: u128) -> GF128Poly {
+        let coeffs: Vec<u128> = self.coeffs.iter()
+                                           .map(|a| gf128_mul(*a, c))
+                                           .collect();
+        GF128Poly::new(&coeffs)
+    }
+
+    pub fn mul(&self, other: &GF128Poly) -> GF128Poly {
+        if self.is_zero() || other.is_zero() {
+            return GF128Poly::zero();
+        }
+
+        let mut coeffs = vec!(0u128; self.coeffs.len() +
+                                     other.coeffs.len() - 1);
+        for (i, a) in self.coeffs.iter().enumerate() {
+            for (j, b) in other.coeffs.iter().enumerate() {
+                coeffs[i + j] ^= gf128_mul(*a, *b);
+            }
+        }
+        GF128Poly::new(&coeffs)
+    }
+
+    /// Returns (quotient, remainder)
+    pub fn divmod(&self, divisor: &GF128Poly) -> (GF128Poly, GF128Poly) {
+        assert!(!divisor.is_zero(), "GF128Poly divide by zero");
+
+        if self.coeffs.len() < divisor.coeffs.len() {
+            return (GF128Poly::zero(), self.clone());
+        }
+
+        let inv_lead = gf128_inv(divisor.lead());
+        let d = divisor.degree();
+        let mut rem = self.coeffs.clone();
+        let mut quot = vec!(0u128; self.coeffs.len() - d);
+
+        // long division from the top down
+        for i in (0..quot.len()).rev() {
+            let c = gf128_mul(rem[i + d], inv_lead);
+            quot[i] = c;
+            for (j, b) in divisor.coeffs.iter().enumerate() {
+                rem[i + j] ^= gf128_mul(c, *b);
+            }
+        }
+        rem.truncate(d);
+        (GF128Poly::new(&quot), GF128Poly::new(&rem))
+    }
+
+    pub fn rem(&self, divisor: &GF128Poly) -> GF128Poly {
+        self.divmod(divisor).1
+    }
+
+    pub fn monic(&self) -> GF128Poly {
+        if self.is_zero() {
+            return GF128Poly::zero();
+        }
+        self.scale(gf128_inv(self.lead()))
+    }
+
+    /// Monic gcd, so gcd(a, b) == 1 when they're coprime
+    pub fn gcd(&self, other: &GF128Poly) -> GF128Poly {
+        let mut a = self.clone();
+        let mut b = other.clone();
+        while !b.is_zero() {
+            let r = a.rem(&b);
+            a = b;
+            b = r;
+        }
+        a.monic()
+    }
+
+    pub fn derivative(&self) -> GF128Poly {
+        // d/dx c * x^i = i * c * x^(i - 1), and i * c is 0 for even i
+        let mut coeffs = Vec::new();
+        for (i, c) in self.coeffs.iter().enumerate().skip(1) {
+            coeffs.push(if i % 2 == 1 { *c } else { 0 });
+        }
+        GF128Poly::new(&coeffs)
+    }
+
+    /// Only makes sense when every odd coefficient is 0, e.g. when the
+    /// derivative is zero
+    fn sqrt(&self) -> GF128Poly {
+        let coeffs: Vec<u128> = self.coeffs.iter()
+                                           .step_by(2)
+                                           .map(|c| gf128_sqrt(*c))
+                                           .collect();
+        GF128Poly::new(&coeffs)
+    }
+
+    pub fn pow(&self, mut exp: usize) -> GF128Poly {
+        let mut base = self.clone();
+        let mut result = GF128Poly::one();
+        while exp > 0 {
+            if exp & 1 == 1 {
+                result = result.mul(&base);
+            }
+            base = base.mul(&base);
+            exp >>= 1;
+        }
+        result
+    }
+
+    /// self^(2^k) mod modulus
+    pub fn frobenius_mod(&self, k: usize, modulus: &GF128Poly) -> GF128Poly {
+        let mut result = self.rem(modulus);
+        for _ in 0..k {
+            result = result.mul(&result).rem(modulus);
+        }
+        result
+    }
+
+    pub fn eval(&self, x: u128) -> u128 {
+        // horner's rule
+        let mut result = 0u128;
+        for c in self.coeffs.iter().rev() {
+            result = gf128_mul(result, x) ^ *c;
+        }
+        result
+    }
+
+    fn rand_below(degree: usize) -> GF128Poly {
+        let coeffs: Vec<u128> = (0..degree).map(|_| gf128_rand()).collect();
+        GF128Poly::new(&coeffs)
+    }
+}
+
+/// Returns monic (factor, multiplicity) pairs where each factor is
+/// square-free and the factors are pairwise coprime
+pub fn square_free_factor(f: &GF128Poly) -> Vec<(GF128Poly, usize)> {
+    // from the wiki for "factorization of polynomials over finite fields"
+    let mut result = Vec::new();
+    let f = f.monic();
+    if f.degree() == 0 {
+        return result;
+    }
+
+    let deriv = f.derivative();
+    if deriv.is_zero() {
+        // f is a perfect square
+        for (factor, mult) in square_free_factor(&f.sqrt()) {
+            result.push((factor, mult * 2));
+        }
+        return result;
+    }
+
+    let mut c = f.gcd(&deriv);
+    let mut w = f.divmod(&c).0;
+    let mut i = 1;
+    while !w.is_one() {
+        let y = w.gcd(&c);
+        let factor = w.divmod(&y).0;
+        if !factor.is_one() {
+            result.push((factor.monic(), i));
+        }
+        w = y;
+        c = c.divmod(&w).0;
+        i += 1;
+    }
+
+    if !c.is_one() {
+        // whatever is left is a perfect square
+        for (factor, mult) in square_free_factor(&c.sqrt()) {
+            result.push((factor, mult * 2));
+        }
+    }
+    result
+}
+
+/// f must be square-free and monic. Returns (g, d) where g is the product
+/// of all the irreducible factors of degree d.
+pub fn distinct_degree_factor(f: &GF128Poly) -> Vec<(GF128Poly, usize)> {
+    let mut result = Vec::new();
+    let mut rest = f.monic();
+    let mut d = 1;
+    // x^(q^d) where q = 2^128
+    let mut x_q = GF128Poly::x();
+
+    while rest.degree() >= 2 * d {
+        x_q = x_q.frobenius_mod(128, &rest);
+        // irreducibles of degree d divide x^(q^d) - x
+        let g = rest.gcd(&x_q.add(&GF128Poly::x()));
+        if !g.is_one() {
+            rest = rest.divmod(&g).0;
+            x_q = x_q.rem(&rest);
+            result.push((g, d));
+        }
+        d += 1;
+    }
+
+    if rest.degree() > 0 {
+        let deg = rest.degree();
+        result.push((rest, deg));
+    }
+    result
+}
+
+/// Cantor-Zassenhaus for char 2. f must be square-free and monic with all
+/// irreducible factors of degree d.
+pub fn equal_degree_factor(f: &GF128Poly, d: usize) -> Vec<GF128Poly> {
+    let n = f.degree();
+    assert!(n.is_multiple_of(d), "equal_degree_factor degree {} not multiple of {}",
+            n, d);
+    if n == d {
+        return vec!(f.monic());
+    }
+
+    loop {
+        // the trace map a + a^2 + a^4 + ... + a^(2^(128d - 1)) lands in
+        // GF(2) mod each irreducible factor, so it'll usually split f
+        let a = GF128Poly::rand_below(n);
+        let mut trace = a.clone();
+        let mut term = a;
+        for _ in 1..(128 * d) {
+            term = term.mul(&term).rem(f);
+            trace = trace.add(&term);
+        }
+
+        let g = f.gcd(&trace);
+        if g.degree() > 0 && g.degree() < n {
+            let h = f.divmod(&g).0;
+            let mut result = equal_degree_factor(&g, d);
+            result.extend(equal_degree_factor(&h.monic(), d));
+            return result;
+        }
+    }
+}
+
+/// Irreducible monic factors with multiplicity
+pub fn factor(f: &GF128Poly) -> Vec<(GF128Poly, usize)> {
+    let mut result = Vec::new();
+    for (sqf, mult) in square_free_factor(f) {
+        for (g, d) in distinct_degree_factor(&sqf) {
+            for irreducible in equal_degree_factor(&g, d) {
+                result.push((irreducible, mult));
+            }
+        }
+    }
+    result
+}
+
+/// Distinct roots in GF(2^128)
+pub fn roots(f: &GF128Poly) -> Vec<u128> {
+    let mut result = Vec::new();
+    if f.degree() == 0 {
+        return result;
+    }
+
+    for (sqf, _) in square_free_factor(f) {
+        // only care about the linear factors, i.e. gcd with x^q - x
+        let x_q = GF128Poly::x().frobenius_mod(128, &sqf);
+        let linear = sqf.gcd(&x_q.add(&GF128Poly::x()));
+        if linear.degree() == 0 {
+            continue;
+        }
+        for root_factor in equal_degree_factor(&linear, 1) {
+            // monic x + r has root r
+            result.push(root_factor.coeffs[0]);
+        }
+    }
+    result
+}
@@ -0,0 +1,116 @@
+use aes::gcm::{gf128_mul, block_to_u128};
+use poly::{GF128Poly, GF128_ONE, gf128_inv, gf128_sqrt, square_free_factor,
+           distinct_degree_factor, equal_degree_factor, factor, roots};
+use util::rand_bytes;
+
+fn rand_elem() -> u128 {
+    block_to_u128(&rand_bytes(16))
+}
+
+// (x - r0) * (x - r1) * ...
+fn poly_from_roots(rs: &[u128]) -> GF128Poly {
+    let mut f = GF128Poly::one();
+    for r in rs {
+        f = f.mul(&GF128Poly::new(&[*r, GF128_ONE]));
+    }
+    f
+}
+
+fn gf128_field_test() {
+    for _ in 0..8 {
+        let a = rand_elem();
+        if a == 0 {
+            continue;
+        }
+        assert_eq!(gf128_mul(a, gf128_inv(a)), GF128_ONE,
+                   "gf128_inv failed for {:032x}", a);
+        let root = gf128_sqrt(a);
+        assert_eq!(gf128_mul(root, root), a,
+                   "gf128_sqrt failed for {:032x}", a);
+    }
+}
+
+fn poly_divmod_test() {
+    for _ in 0..8 {
+        let a = GF128Poly::new(&[rand_elem(), rand_elem(), rand_elem(),
+                                 rand_elem(), rand_elem()]);
+        let b = GF128Poly::new(&[rand_elem(), rand_elem(), GF128_ONE]);
+        let (q, r) = a.divmod(&b);
+        assert!(r.degree() < b.degree(), "poly_divmod_test remainder {:?}",
+                r);
+        assert_eq!(q.mul(&b).add(&r), a, "poly_divmod_test failed");
+    }
+
+    let common = GF128Poly::new(&[rand_elem(), GF128_ONE]);
+    let a = common.mul(&GF128Poly::new(&[rand_elem(), rand_elem(),
+                                         GF128_ONE]));
+    let b = common.mul(&GF128Poly::new(&[rand_elem(), GF128_ONE]));
+    // could share more factors by accident but very unlikely
+    assert_eq!(a.gcd(&b), common, "poly_gcd_test failed");
+}
+
+fn poly_square_free_test() {
+    let a = GF128Poly::new(&[rand_elem(), GF128_ONE]);
+    let b = GF128Poly::new(&[rand_elem(), GF128_ONE]);
+    let c = GF128Poly::new(&[rand_elem(), rand_elem(), GF128_ONE]);
+    let f = a.mul(&b.pow(2)).mul(&c.pow(3));
+
+    let factors = square_free_factor(&f);
+    let mut product = GF128Poly::one();
+    for &(ref factor, mult) in &factors {
+        product = product.mul(&factor.pow(mult));
+        assert!(factor.derivative().is_zero() ||
+                factor.gcd(&factor.derivative()).is_one(),
+                "square_free_factor returned non square-free {:?}", factor);
+    }
+    assert_eq!(product, f, "square_free_factor product mismatch");
+    for &(ref expected, mult) in &[(a, 1), (b, 2), (c, 3)] {
+        assert!(factors.iter().any(|&(ref g, m)| {
+                    m == mult && g.rem(expected).is_zero()
+                }),
+                "square_free_factor missing multiplicity {}", mult);
+    }
+}
+
+fn poly_factor_test() {
+    let rs = [rand_elem(), rand_elem(), rand_elem()];
+    let f = poly_from_roots(&rs);
+
+    let ddf = distinct_degree_factor(&f);
+    assert_eq!(ddf.len(), 1, "distinct_degree_factor {:?}", ddf);
+    assert_eq!(ddf[0].1, 1, "distinct_degree_factor expected linear");
+
+    let linear = equal_degree_factor(&f, 1);
+    assert_eq!(linear.len(), rs.len(), "equal_degree_factor {:?}", linear);
+    for r in &rs {
+        assert!(linear.contains(&GF128Poly::new(&[*r, GF128_ONE])),
+                "equal_degree_factor missing root {:032x}", r);
+    }
+
+    // throw in a repeated root and a quadratic that probably has no roots
+    let quad = GF128Poly::new(&[rand_elem(), rand_elem(), GF128_ONE]);
+    let g = f.mul(&GF128Poly::new(&[rs[0], GF128_ONE])).mul(&quad);
+    let mut found = roots(&g);
+    found.sort();
+    let mut expected = rs.to_vec();
+    for r in roots(&quad) {
+        expected.push(r);
+    }
+    expected.sort();
+    expected.dedup();
+    assert_eq!(found, expected, "poly roots mismatch");
+
+    let mut product = GF128Poly::one();
+    for (irreducible, mult) in factor(&g) {
+        product = product.mul(&irreducible.pow(mult));
+    }
+    assert_eq!(product, g, "poly factor product mismatch");
+}
+
+pub fn poly_test() {
+    gf128_field_test();
+    poly_divmod_test();
+    poly_square_free_test();
+    poly_factor_test();
+    println!("Finished GF(2^128) polynomial tests");
+}
@@ -1,34 +1,81 @@
+pub mod padding;
+pub mod serialize;
 pub mod test;
 
 extern crate gmp;
+extern crate rand;
 
-use asn1::PKCS1V15_SHA1_DIGEST_PREFIX;
+use asn1::{DigestAlgorithm, PKCS1V15_SHA1_DIGEST_PREFIX};
+use error::{Error, Result};
 use self::gmp::mpz::Mpz;
+use self::padding::{pkcs1v15_encrypt_pad, pkcs1v15_encrypt_unpad, oaep_pad,
+                    oaep_unpad};
 use sha1;
-use util::{randomish_prime, bytes_to_mpz, mpz_bytes, mpz_byte_len,
-           mpz_bytes_zero_pad};
+use util::{random_rsa_prime, random_mpz_below, bytes_to_mpz, mpz_bytes,
+           mpz_byte_len, mpz_bytes_zero_pad, constant_time_eq,
+           PrimalityTest};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct PublicKey {
     // pub for debugging
     pub e: Mpz,
     pub n: Mpz,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct PrivateKey {
     // pub for debugging
     pub d: Mpz,
     pub n: Mpz,
+    // None for keys made from just d and n
+    pub crt: Option<CrtParams>,
 }
 
-pub fn pkcs1v15_sha1_der_encode(msg: &[u8]) -> Vec<u8> {
+/// Everything PKCS#1 keeps in a private key besides n and d. e is used
+/// for blinding and to check CRT results.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CrtParams {
+    pub e: Mpz,
+    pub p: Mpz,
+    pub q: Mpz,
+    // d mod (p - 1)
+    pub dp: Mpz,
+    // d mod (q - 1)
+    pub dq: Mpz,
+    // q^-1 mod p
+    pub qinv: Mpz,
+}
+
+/// DigestInfo for msg, i.e. the prefix for alg followed by the hash
+pub fn pkcs1v15_der_encode(alg: DigestAlgorithm, msg: &[u8]) -> Vec<u8> {
     let mut der: Vec<u8> = Vec::new();
-    der.extend_from_slice(&PKCS1V15_SHA1_DIGEST_PREFIX);
-    der.extend_from_slice(&sha1::digest(msg));
+    der.extend_from_slice(alg.digest_info_prefix());
+    der.extend_from_slice(&alg.digest(msg));
     der
 }
 
+pub fn pkcs1v15_sha1_der_encode(msg: &[u8]) -> Vec<u8> {
+    pkcs1v15_der_encode(DigestAlgorithm::Sha1, msg)
+}
+
+/// EMSA-PKCS1-v1_5: 00 01 ff .. ff 00 DigestInfo, k bytes long. None if
+/// there isn't room for the 8 bytes of minimum padding.
+fn pkcs1v15_sign_pad(alg: DigestAlgorithm, msg: &[u8],
+                     k: usize) -> Option<Vec<u8>> {
+    let der = pkcs1v15_der_encode(alg, msg);
+    if k < der.len() + 11 {
+        return None;
+    }
+
+    // type 1 is 0xff padding
+    let mut res = vec!(0u8, 1u8);
+    res.extend(vec!(0xffu8; k - der.len() - 3));
+    // misc separator per RFC
+    res.push(0u8);
+    res.extend_from_slice(&der);
+    Some(res)
+}
+
 impl PublicKey {
     pub fn new(e: &Mpz, n: &Mpz) -> PublicKey {
         PublicKey{e: e.clone(), n: n.clone()}
@@ -43,6 +90,30 @@ impl PublicKey {
         mpz_bytes(&self.encrypt_to_mpz(msg))
     }
 
+    /// Length of n in bytes, which is also the length of everything the
+    /// padded modes put out
+    pub fn byte_len(&self) -> usize {
+        mpz_byte_len(&self.n)
+    }
+
+    // padded input is already k bytes, output is zero padded to k too
+    fn encrypt_padded(&self, em: &[u8]) -> Vec<u8> {
+        mpz_bytes_zero_pad(&self.encrypt_to_mpz(em), self.byte_len())
+    }
+
+    /// RSAES-PKCS1-v1_5-ENCRYPT
+    pub fn pkcs1v15_encrypt(&self, msg: &[u8]) -> Result<Vec<u8>> {
+        let em = pkcs1v15_encrypt_pad(msg, self.byte_len())?;
+        Ok(self.encrypt_padded(&em))
+    }
+
+    /// RSAES-OAEP-ENCRYPT with alg for the label hash and MGF1
+    pub fn oaep_encrypt(&self, alg: DigestAlgorithm, label: &[u8],
+                        msg: &[u8]) -> Result<Vec<u8>> {
+        let em = oaep_pad(alg, label, msg, self.byte_len())?;
+        Ok(self.encrypt_padded(&em))
+    }
+
     /// Doesn't properly check padding
     pub fn pkcs1v15_sha1_bad_verify(&self,
                                     msg: &[u8],
@@ -87,64 +158,184 @@ impl PublicKey {
         &sha1::digest(&msg) == &bytes[hash_index..
                                       hash_index + sha1::DIGEST_LENGTH]
     }
+
+    /// RSASSA-PKCS1-V1_5-VERIFY. Rather than parsing what comes out of
+    /// the signature this builds the encoding we'd expect for msg and
+    /// compares the whole thing, so there's nowhere to hide garbage.
+    pub fn pkcs1v15_verify(&self, alg: DigestAlgorithm, msg: &[u8],
+                           signature: &[u8]) -> Result<()> {
+        let k = mpz_byte_len(&self.n);
+        if signature.len() != k {
+            return Err(Error::InvalidSignature);
+        }
+        let s = bytes_to_mpz(signature);
+        if s >= self.n {
+            return Err(Error::InvalidSignature);
+        }
+
+        let expected = match pkcs1v15_sign_pad(alg, msg, k) {
+            Some(expected) => expected,
+            None => return Err(Error::InvalidSignature),
+        };
+        let m = s.powm(&self.e, &self.n);
+        if constant_time_eq(&mpz_bytes_zero_pad(&m, k), &expected) {
+            Ok(())
+        } else {
+            Err(Error::InvalidSignature)
+        }
+    }
 }
 
 impl PrivateKey {
     pub fn new(d: &Mpz, n: &Mpz) -> PrivateKey {
-        PrivateKey{d: d.clone(), n: n.clone()}
+        PrivateKey{d: d.clone(), n: n.clone(), crt: None}
+    }
+
+    /// Full key from the primes, d = e^-1 mod (p - 1)(q - 1)
+    pub fn from_primes(e: &Mpz, p: &Mpz, q: &Mpz) -> Result<PrivateKey> {
+        if p == q {
+            return Err(Error::InvalidKey("p == q"));
+        }
+        let p_1 = p - Mpz::one();
+        let q_1 = q - Mpz::one();
+        let d = match e.invert(&(&p_1 * &q_1)) {
+            Some(d) => d,
+            None => return Err(Error::InvalidKey("e not coprime with phi")),
+        };
+        let qinv = match q.invert(p) {
+            Some(qinv) => qinv,
+            None => return Err(Error::InvalidKey("q not invertible mod p")),
+        };
+
+        let crt = CrtParams {
+            e: e.clone(),
+            p: p.clone(),
+            q: q.clone(),
+            dp: d.modulus(&p_1),
+            dq: d.modulus(&q_1),
+            qinv,
+        };
+        Ok(PrivateKey { d, n: p * q, crt: Some(crt) })
     }
 
-    pub fn decrypt_mpz(&self, ciphernum: &Mpz) -> Mpz {
+    /// Only keys with the CRT parameters know e
+    pub fn public_key(&self) -> Option<PublicKey> {
+        self.crt.as_ref().map(|crt| PublicKey::new(&crt.e, &self.n))
+    }
+
+    /// c^d mod n with the CRT parameters when there are any, otherwise
+    /// the slow way. A CRT result that doesn't check out is
+    /// Error::FaultDetected rather than quietly redone the slow way, since
+    /// a key that glitches is worth knowing about.
+    pub fn decrypt_mpz(&self, ciphernum: &Mpz) -> Result<Mpz> {
+        if self.crt.is_some() {
+            self.decrypt_mpz_crt(ciphernum)
+        } else {
+            Ok(self.decrypt_mpz_plain(ciphernum))
+        }
+    }
+
+    /// One powm by d mod n, what decrypt_mpz did before the CRT
+    pub fn decrypt_mpz_plain(&self, ciphernum: &Mpz) -> Mpz {
         ciphernum.powm(&self.d, &self.n)
     }
 
-    pub fn decrypt(&self, ciphertext: &[u8]) -> Vec<u8> {
+    /// c^d mod n from the two half size exponentiations mod p and q put
+    /// back together with Garner's formula, so roughly 3-4x faster.
+    ///
+    /// c is blinded by r^e first so the time taken has nothing to do with
+    /// c, and the result is checked by re-encrypting it before it's let
+    /// out: a fault in either half gives an m that's right mod one prime
+    /// and wrong mod the other, and gcd(m^e - c, n) is then a factor.
+    pub fn decrypt_mpz_crt(&self, ciphernum: &Mpz) -> Result<Mpz> {
+        let crt = match self.crt {
+            Some(ref crt) => crt,
+            None => return Err(Error::InvalidKey("no crt parameters")),
+        };
+
+        let mut rng = rand::thread_rng();
+        let (r, r_inv) = loop {
+            let r = random_mpz_below(&mut rng, &self.n);
+            if let Some(r_inv) = r.invert(&self.n) {
+                break (r, r_inv);
+            }
+        };
+        let c = (ciphernum * r.powm(&crt.e, &self.n)).modulus(&self.n);
+
+        let m1 = c.powm(&crt.dp, &crt.p);
+        let m2 = c.powm(&crt.dq, &crt.q);
+        // Garner: m = m2 + q * (qinv * (m1 - m2) mod p)
+        let h = (&crt.qinv * (&m1 - &m2)).modulus(&crt.p);
+        let m = &m2 + &h * &crt.q;
+
+        if m.powm(&crt.e, &self.n) != c {
+            return Err(Error::FaultDetected);
+        }
+        Ok((m * r_inv).modulus(&self.n))
+    }
+
+    pub fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>> {
         let m = bytes_to_mpz(ciphertext);
-        mpz_bytes(&self.decrypt_mpz(&m))
+        Ok(mpz_bytes(&self.decrypt_mpz(&m)?))
     }
 
-    pub fn pkcs1v15_sha1_sign(&self, msg: &[u8]) -> Vec<u8> {
-        let der = pkcs1v15_sha1_der_encode(msg);
+    // the encoded message with its leading zeros put back
+    fn decrypt_padded(&self, ciphertext: &[u8]) -> Result<Vec<u8>> {
+        let k = mpz_byte_len(&self.n);
+        if ciphertext.len() != k {
+            return Err(Error::BadCiphertextLength(ciphertext.len()));
+        }
+        let c = bytes_to_mpz(ciphertext);
+        if c >= self.n {
+            return Err(Error::InvalidPadding);
+        }
+        Ok(mpz_bytes_zero_pad(&self.decrypt_mpz(&c)?, k))
+    }
 
-        let mut res: Vec<u8> = Vec::new();
+    /// RSAES-PKCS1-v1_5-DECRYPT, any bad padding is Error::InvalidPadding
+    pub fn pkcs1v15_decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>> {
+        pkcs1v15_encrypt_unpad(&self.decrypt_padded(ciphertext)?)
+    }
 
-        // type 1 is 0xff padding
-        res.push(0u8);
-        res.push(1u8);
+    /// RSAES-OAEP-DECRYPT, any bad padding or the wrong label is
+    /// Error::InvalidPadding
+    pub fn oaep_decrypt(&self, alg: DigestAlgorithm, label: &[u8],
+                        ciphertext: &[u8]) -> Result<Vec<u8>> {
+        oaep_unpad(alg, label, &self.decrypt_padded(ciphertext)?)
+    }
 
-        let bytes = mpz_byte_len(&self.n);
-        // NB: spec requires 8 padding bytes + 3 bytes (part of the encoding)
-        // but ignore it for challenge 42
-        assert!(bytes >= der.len() + 11);
-        for _ in 0..bytes - der.len() - 3 {
-            res.push(0xffu8);
-        }
+    /// Signature is zero padded to the length of n
+    pub fn pkcs1v15_sign(&self, alg: DigestAlgorithm,
+                         msg: &[u8]) -> Result<Vec<u8>> {
+        let k = mpz_byte_len(&self.n);
+        let res = match pkcs1v15_sign_pad(alg, msg, k) {
+            Some(res) => res,
+            None => return Err(Error::InvalidKey("key too small for digest")),
+        };
+        let s = self.decrypt_mpz(&bytes_to_mpz(&res))?;
+        Ok(mpz_bytes_zero_pad(&s, k))
+    }
 
-        // misc separator per RFC
-        res.push(0u8);
-        res.extend_from_slice(&der);
-        mpz_bytes(&bytes_to_mpz(&res).powm(&self.d, &self.n))
+    pub fn pkcs1v15_sha1_sign(&self, msg: &[u8]) -> Result<Vec<u8>> {
+        self.pkcs1v15_sign(DigestAlgorithm::Sha1, msg)
     }
 }
 
 pub fn new_keypair(bit_len: usize) -> (PublicKey, PrivateKey) {
+    // just hardcode e to be 3 for now
+    let e = Mpz::from(3u64);
+    let mut rng = rand::thread_rng();
     loop {
-        // roughly half the bit len should end with bit_len n
-        let p = randomish_prime(bit_len / 2);
-        let q = randomish_prime(bit_len / 2);
-        let n = &p * &q;
-
-        let totient = ((&p - Mpz::one()) * (&q - Mpz::one())).modulus(&n);
-        // just hardcode e to be 3 for now
-        let e = Mpz::one() + Mpz::one() + Mpz::one();
-
-        //  e.g. if p = 11 and q = 7, totient == 60, so it won't be coprime
-        //  with 3
-        match e.invert(&totient) {
-            Some(d) => {
-                return (PublicKey::new(&e, &n), PrivateKey::new(&d, &n));
-            },
-            None => continue,
-        };
+        // top bits set so n is exactly bit_len, and p - 1 and q - 1 are
+        // coprime with e so it has an inverse
+        let p = random_rsa_prime(&mut rng, bit_len / 2, &e,
+                                 PrimalityTest::BailliePsw);
+        let q = random_rsa_prime(&mut rng, bit_len - bit_len / 2, &e,
+                                 PrimalityTest::BailliePsw);
+
+        // only fails if p == q
+        if let Ok(priv_key) = PrivateKey::from_primes(&e, &p, &q) {
+            return (PublicKey::new(&e, &priv_key.n), priv_key);
+        }
     }
 }
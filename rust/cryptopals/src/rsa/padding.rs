@@ -0,0 +1,130 @@
+use asn1::DigestAlgorithm;
+use error::{Error, Result};
+use util::{rand_bytes, constant_time_eq};
+use xor::slice_xor;
+
+/// Smallest amount of random padding PKCS#1 v1.5 allows
+pub const PKCS1V15_MIN_PADDING: usize = 8;
+
+fn rand_nonzero_bytes(len: usize) -> Vec<u8> {
+    let mut bytes = rand_bytes(len);
+    for b in bytes.iter_mut() {
+        while *b == 0 {
+            *b = rand_bytes(1)[0];
+        }
+    }
+    bytes
+}
+
+/// EME-PKCS1-v1_5 encoding: 00 02 nonzero random bytes 00 msg, k bytes long
+pub fn pkcs1v15_encrypt_pad(msg: &[u8], k: usize) -> Result<Vec<u8>> {
+    let overhead = 3 + PKCS1V15_MIN_PADDING;
+    if msg.len() + overhead > k {
+        return Err(Error::MessageTooLong(k.saturating_sub(overhead)));
+    }
+
+    // type 2 is random padding
+    let mut em = vec!(0u8, 2u8);
+    em.extend(rand_nonzero_bytes(k - msg.len() - 3));
+    em.push(0u8);
+    em.extend_from_slice(msg);
+    Ok(em)
+}
+
+/// Undoes pkcs1v15_encrypt_pad. Every way the padding can be wrong gives
+/// the same error, and it looks at every byte either way, so at least
+/// the obvious side channels don't say which check failed.
+pub fn pkcs1v15_encrypt_unpad(em: &[u8]) -> Result<Vec<u8>> {
+    if em.len() < 3 + PKCS1V15_MIN_PADDING {
+        return Err(Error::InvalidPadding);
+    }
+
+    let mut good = em[0] == 0 && em[1] == 2;
+    // index of the 00 after the padding, 0 until we find it
+    let mut separator = 0;
+    for (i, &b) in em.iter().enumerate().skip(2) {
+        let first_zero = b == 0 && separator == 0;
+        separator = if first_zero { i } else { separator };
+    }
+    good &= separator >= 2 + PKCS1V15_MIN_PADDING;
+
+    if good {
+        Ok(em[separator + 1..].to_vec())
+    } else {
+        Err(Error::InvalidPadding)
+    }
+}
+
+/// MGF1 from RFC 8017 appendix B.2.1, hash(seed || counter) for counter
+/// = 0, 1, ... until there's len bytes
+pub fn mgf1(alg: DigestAlgorithm, seed: &[u8], len: usize) -> Vec<u8> {
+    let mut mask = Vec::with_capacity(len + alg.digest_length());
+    let mut counter = 0u32;
+    while mask.len() < len {
+        let mut input = seed.to_vec();
+        input.extend_from_slice(&counter.to_be_bytes());
+        mask.extend(alg.digest(&input));
+        counter += 1;
+    }
+    mask.truncate(len);
+    mask
+}
+
+/// EME-OAEP encoding with alg for both the label hash and MGF1:
+/// 00 || maskedSeed || maskedDB, where DB = lHash || 00 .. 00 || 01 || msg
+pub fn oaep_pad(alg: DigestAlgorithm, label: &[u8], msg: &[u8],
+                k: usize) -> Result<Vec<u8>> {
+    let h_len = alg.digest_length();
+    let overhead = 2 * h_len + 2;
+    if msg.len() + overhead > k {
+        return Err(Error::MessageTooLong(k.saturating_sub(overhead)));
+    }
+
+    let mut db = alg.digest(label);
+    db.extend(vec!(0u8; k - msg.len() - 2 * h_len - 2));
+    db.push(1u8);
+    db.extend_from_slice(msg);
+
+    let seed = rand_bytes(h_len);
+    let masked_db = slice_xor(&db, &mgf1(alg, &seed, db.len()));
+    let masked_seed = slice_xor(&seed, &mgf1(alg, &masked_db, h_len));
+
+    let mut em = vec!(0u8);
+    em.extend(masked_seed);
+    em.extend(masked_db);
+    Ok(em)
+}
+
+/// Undoes oaep_pad, same as pkcs1v15_encrypt_unpad all the failures look
+/// the same (see Manger's attack)
+pub fn oaep_unpad(alg: DigestAlgorithm, label: &[u8],
+                  em: &[u8]) -> Result<Vec<u8>> {
+    let h_len = alg.digest_length();
+    if em.len() < 2 * h_len + 2 {
+        return Err(Error::InvalidPadding);
+    }
+
+    let (masked_seed, masked_db) = em[1..].split_at(h_len);
+    let seed = slice_xor(masked_seed, &mgf1(alg, masked_db, h_len));
+    let db = slice_xor(masked_db, &mgf1(alg, &seed, masked_db.len()));
+
+    let mut good = em[0] == 0;
+    good &= constant_time_eq(&db[..h_len], &alg.digest(label));
+
+    // the 01 after the zeros, 0 until we find it. anything else nonzero
+    // before it is bad padding.
+    let mut separator = 0;
+    let mut bad_byte = false;
+    for (i, &b) in db.iter().enumerate().skip(h_len) {
+        let looking = separator == 0;
+        bad_byte |= looking && b != 0 && b != 1;
+        separator = if looking && b == 1 { i } else { separator };
+    }
+    good &= separator != 0 && !bad_byte;
+
+    if good {
+        Ok(db[separator + 1..].to_vec())
+    } else {
+        Err(Error::InvalidPadding)
+    }
+}
@@ -0,0 +1,179 @@
+extern crate gmp;
+
+use self::gmp::mpz::Mpz;
+use asn1::RSA_ENCRYPTION_OID;
+use asn1::der::{DerReader, encode_integer, encode_null, encode_oid,
+                encode_octet_string, encode_bit_string, encode_sequence};
+use error::{Error, Result};
+use pem::{pem_encode, pem_decode};
+use rsa::{PublicKey, PrivateKey, CrtParams};
+
+pub const PKCS1_PUBLIC_PEM_LABEL: &str = "RSA PUBLIC KEY";
+pub const PKCS1_PRIVATE_PEM_LABEL: &str = "RSA PRIVATE KEY";
+pub const SPKI_PEM_LABEL: &str = "PUBLIC KEY";
+pub const PKCS8_PEM_LABEL: &str = "PRIVATE KEY";
+
+// AlgorithmIdentifier { rsaEncryption, NULL }
+fn rsa_algorithm_identifier() -> Vec<u8> {
+    encode_sequence(&[encode_oid(&RSA_ENCRYPTION_OID), encode_null()])
+}
+
+fn read_rsa_algorithm_identifier(reader: &mut DerReader) -> Result<()> {
+    let mut alg = reader.read_sequence()?;
+    if alg.read_oid()? != RSA_ENCRYPTION_OID {
+        return Err(Error::InvalidDer("not an rsa key"));
+    }
+    // parameters are supposed to be NULL but some encoders leave it out
+    if !alg.is_empty() {
+        alg.read_null()?;
+    }
+    alg.finish()
+}
+
+fn read_version(reader: &mut DerReader) -> Result<()> {
+    if reader.read_integer()? != Mpz::zero() {
+        // 1 is multi-prime, which we don't do
+        return Err(Error::InvalidDer("unsupported version"));
+    }
+    Ok(())
+}
+
+impl PublicKey {
+    /// RSAPublicKey ::= SEQUENCE { modulus, publicExponent }
+    pub fn to_pkcs1_der(&self) -> Vec<u8> {
+        encode_sequence(&[encode_integer(&self.n), encode_integer(&self.e)])
+    }
+
+    pub fn from_pkcs1_der(der: &[u8]) -> Result<PublicKey> {
+        let mut outer = DerReader::new(der);
+        let mut seq = outer.read_sequence()?;
+        outer.finish()?;
+
+        let n = seq.read_integer()?;
+        let e = seq.read_integer()?;
+        seq.finish()?;
+        if n <= Mpz::zero() || e <= Mpz::zero() {
+            return Err(Error::InvalidKey("negative modulus or exponent"));
+        }
+        Ok(PublicKey::new(&e, &n))
+    }
+
+    /// SubjectPublicKeyInfo, what openssl means by a public key
+    pub fn to_spki_der(&self) -> Vec<u8> {
+        encode_sequence(&[rsa_algorithm_identifier(),
+                          encode_bit_string(&self.to_pkcs1_der())])
+    }
+
+    pub fn from_spki_der(der: &[u8]) -> Result<PublicKey> {
+        let mut outer = DerReader::new(der);
+        let mut seq = outer.read_sequence()?;
+        outer.finish()?;
+
+        read_rsa_algorithm_identifier(&mut seq)?;
+        let key = seq.read_bit_string()?;
+        seq.finish()?;
+        PublicKey::from_pkcs1_der(key)
+    }
+
+    pub fn to_pkcs1_pem(&self) -> String {
+        pem_encode(PKCS1_PUBLIC_PEM_LABEL, &self.to_pkcs1_der())
+    }
+
+    pub fn from_pkcs1_pem(pem: &str) -> Result<PublicKey> {
+        PublicKey::from_pkcs1_der(&pem_decode(PKCS1_PUBLIC_PEM_LABEL, pem)?)
+    }
+
+    pub fn to_spki_pem(&self) -> String {
+        pem_encode(SPKI_PEM_LABEL, &self.to_spki_der())
+    }
+
+    pub fn from_spki_pem(pem: &str) -> Result<PublicKey> {
+        PublicKey::from_spki_der(&pem_decode(SPKI_PEM_LABEL, pem)?)
+    }
+}
+
+impl PrivateKey {
+    /// RSAPrivateKey ::= SEQUENCE { version, n, e, d, p, q, dP, dQ, qInv },
+    /// so only keys with the CRT parameters can be encoded
+    pub fn to_pkcs1_der(&self) -> Result<Vec<u8>> {
+        let crt = match self.crt {
+            Some(ref crt) => crt,
+            None => return Err(Error::InvalidKey("no crt parameters")),
+        };
+        Ok(encode_sequence(&[
+            encode_integer(&Mpz::zero()),
+            encode_integer(&self.n),
+            encode_integer(&crt.e),
+            encode_integer(&self.d),
+            encode_integer(&crt.p),
+            encode_integer(&crt.q),
+            encode_integer(&crt.dp),
+            encode_integer(&crt.dq),
+            encode_integer(&crt.qinv),
+        ]))
+    }
+
+    pub fn from_pkcs1_der(der: &[u8]) -> Result<PrivateKey> {
+        let mut outer = DerReader::new(der);
+        let mut seq = outer.read_sequence()?;
+        outer.finish()?;
+
+        read_version(&mut seq)?;
+        let n = seq.read_integer()?;
+        let e = seq.read_integer()?;
+        let d = seq.read_integer()?;
+        let crt = CrtParams {
+            e,
+            p: seq.read_integer()?,
+            q: seq.read_integer()?,
+            dp: seq.read_integer()?,
+            dq: seq.read_integer()?,
+            qinv: seq.read_integer()?,
+        };
+        seq.finish()?;
+
+        // cheap enough to catch a key that's been mangled
+        if &crt.p * &crt.q != n {
+            return Err(Error::InvalidKey("n != p * q"));
+        }
+        Ok(PrivateKey { d, n, crt: Some(crt) })
+    }
+
+    /// PrivateKeyInfo ::= SEQUENCE { version, algorithm, privateKey }
+    /// with the PKCS#1 key in the octet string
+    pub fn to_pkcs8_der(&self) -> Result<Vec<u8>> {
+        Ok(encode_sequence(&[
+            encode_integer(&Mpz::zero()),
+            rsa_algorithm_identifier(),
+            encode_octet_string(&self.to_pkcs1_der()?),
+        ]))
+    }
+
+    pub fn from_pkcs8_der(der: &[u8]) -> Result<PrivateKey> {
+        let mut outer = DerReader::new(der);
+        let mut seq = outer.read_sequence()?;
+        outer.finish()?;
+
+        read_version(&mut seq)?;
+        read_rsa_algorithm_identifier(&mut seq)?;
+        let key = seq.read_octet_string()?;
+        // there can be [0] attributes after, which we don't care about
+        PrivateKey::from_pkcs1_der(key)
+    }
+
+    pub fn to_pkcs1_pem(&self) -> Result<String> {
+        Ok(pem_encode(PKCS1_PRIVATE_PEM_LABEL, &self.to_pkcs1_der()?))
+    }
+
+    pub fn from_pkcs1_pem(pem: &str) -> Result<PrivateKey> {
+        PrivateKey::from_pkcs1_der(&pem_decode(PKCS1_PRIVATE_PEM_LABEL, pem)?)
+    }
+
+    pub fn to_pkcs8_pem(&self) -> Result<String> {
+        Ok(pem_encode(PKCS8_PEM_LABEL, &self.to_pkcs8_der()?))
+    }
+
+    pub fn from_pkcs8_pem(pem: &str) -> Result<PrivateKey> {
+        PrivateKey::from_pkcs8_der(&pem_decode(PKCS8_PEM_LABEL, pem)?)
+    }
+}
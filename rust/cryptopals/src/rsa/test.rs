@@ -1,9 +1,17 @@
 extern crate gmp;
 
+use std::time::Instant;
+
 use self::gmp::mpz::Mpz;
-use util::{rand_bytes, bytes_to_mpz, mpz_bytes, mpz_print_padded};
+use asn1::DigestAlgorithm;
+use error::Error;
+use hex::hex_to_bytes;
+use util::{rand_bytes, randomish_mpz_lt, bytes_to_mpz, mpz_bytes,
+           mpz_print_padded, mpz_byte_len, mpz_bytes_zero_pad, read_fixture};
 
 use rsa::{new_keypair, PublicKey, PrivateKey, pkcs1v15_sha1_der_encode};
+use rsa::serialize::PKCS1_PRIVATE_PEM_LABEL;
+use pem::pem_encode;
 
 fn rsa_keypair_test(bit_len: usize) {
     let (pub_key, priv_key) = new_keypair(bit_len);
@@ -19,7 +27,7 @@ fn rsa_keypair_test(bit_len: usize) {
                      pub_key.e, pub_key.n, priv_key.d);
 
     let ciphertext = pub_key.encrypt(&plaintext);
-    let decrypted = priv_key.decrypt(&ciphertext);
+    let decrypted = priv_key.decrypt(&ciphertext).unwrap();
     assert_eq!(&decrypted, &plaintext, "rsa_keypair_test failed");
 }
 
@@ -91,7 +99,7 @@ fn unpadded_msg_test() {
     };
 
     // should be a server doing this here but lazymode
-    let pprime = priv_key.decrypt_mpz(&cprime);
+    let pprime = priv_key.decrypt_mpz(&cprime).unwrap();
 
     // c' = (s ^ e) * c mod n
     // p' = c'^d = (s^e * c)^d = s^ed * c^d mod n = s * c ^d mod n
@@ -183,7 +191,7 @@ fn pkcs1v15_e3_no_pad_check_test() {
         None => panic!("pkcs1v15_e3_no_pad_check_test couldn't find root"),
     };
 
-    let signature = priv_key.pkcs1v15_sha1_sign(plaintext.as_bytes());
+    let signature = priv_key.pkcs1v15_sha1_sign(plaintext.as_bytes()).unwrap();
     assert!(pub_key.pkcs1v15_sha1_bad_verify(plaintext.as_bytes(),
                                              &signature));
 
@@ -191,22 +199,364 @@ fn pkcs1v15_e3_no_pad_check_test() {
                                              &forged_signature),
             "pkcs1v15_e3_no_pad_check_test forgery failed {:?}",
             &forged_signature);
+
+    // the real verify should see the garbage after the hash
+    let k = mpz_byte_len(&pub_key.n);
+    let forged_padded = mpz_bytes_zero_pad(&bytes_to_mpz(&forged_signature),
+                                           k);
+    assert_eq!(pub_key.pkcs1v15_verify(DigestAlgorithm::Sha1,
+                                       plaintext.as_bytes(), &forged_padded),
+               Err(Error::InvalidSignature),
+               "pkcs1v15_verify accepted the e=3 forgery");
+    assert!(pub_key.pkcs1v15_verify(DigestAlgorithm::Sha1,
+                                    plaintext.as_bytes(), &signature).is_ok());
 }
 
 fn pkcs1v15_test() {
     let (pub_key, priv_key) = new_keypair(512);
     println!("pkcs1v15_test {:?} {:?}", &pub_key, &priv_key);
     let msg = "beep boop meow";
-    let signature = priv_key.pkcs1v15_sha1_sign(msg.as_bytes());
+    let signature = priv_key.pkcs1v15_sha1_sign(msg.as_bytes()).unwrap();
     assert!(pub_key.pkcs1v15_sha1_bad_verify(msg.as_bytes(), &signature));
 }
 
-pub fn rsa_test() {
+fn pkcs1v15_verify_test() {
+    let (pub_key, priv_key) = new_keypair(1024);
+    let msg = "beep boop meow".as_bytes();
+    let algs = [DigestAlgorithm::Sha1, DigestAlgorithm::Sha256,
+                DigestAlgorithm::Sha384, DigestAlgorithm::Sha512];
+
+    for &alg in &algs {
+        let signature = priv_key.pkcs1v15_sign(alg, msg).unwrap();
+        assert_eq!(signature.len(), mpz_byte_len(&pub_key.n));
+        assert_eq!(pub_key.pkcs1v15_verify(alg, msg, &signature), Ok(()),
+                   "pkcs1v15_verify {:?} failed", alg);
+
+        assert!(pub_key.pkcs1v15_verify(alg, "beep boop woof".as_bytes(),
+                                        &signature).is_err(),
+                "pkcs1v15_verify {:?} accepted the wrong message", alg);
+
+        for &other in algs.iter().filter(|&&other| other != alg) {
+            assert!(pub_key.pkcs1v15_verify(other, msg, &signature).is_err(),
+                    "pkcs1v15_verify {:?} accepted a {:?} signature",
+                    other, alg);
+        }
+
+        let mut flipped = signature.clone();
+        let last = flipped.len() - 1;
+        flipped[last] ^= 1;
+        assert!(pub_key.pkcs1v15_verify(alg, msg, &flipped).is_err());
+
+        // signatures have to be exactly as long as n
+        let mut long = vec!(0u8);
+        long.extend_from_slice(&signature);
+        assert!(pub_key.pkcs1v15_verify(alg, msg, &long).is_err());
+        assert!(pub_key.pkcs1v15_verify(alg, msg, &signature[1..]).is_err());
+
+        // and less than it
+        let too_big = mpz_bytes_zero_pad(&(&bytes_to_mpz(&signature) +
+                                           &pub_key.n),
+                                         signature.len());
+        if too_big.len() == signature.len() {
+            assert!(pub_key.pkcs1v15_verify(alg, msg, &too_big).is_err());
+        }
+    }
+
+    // 32 bytes doesn't have room for a SHA-512 DigestInfo and its padding
+    let (_, small_key) = new_keypair(256);
+    assert_eq!(small_key.pkcs1v15_sign(DigestAlgorithm::Sha512, msg),
+               Err(Error::InvalidKey("key too small for digest")));
+}
+
+fn encryption_padding_test() {
+    let (pub_key, priv_key) = new_keypair(1024);
+    let k = pub_key.byte_len();
+    println!("rsa encryption padding test with {} byte n", k);
+
+    for &len in &[0, 1, 16, 32, k - 11] {
+        let msg = rand_bytes(len);
+        let ciphertext = pub_key.pkcs1v15_encrypt(&msg).unwrap();
+        assert_eq!(ciphertext.len(), k);
+        assert_eq!(priv_key.pkcs1v15_decrypt(&ciphertext).unwrap(), msg,
+                   "pkcs1v15 round trip failed for {} bytes", len);
+    }
+    assert_eq!(pub_key.pkcs1v15_encrypt(&rand_bytes(k - 10)),
+               Err(Error::MessageTooLong(k - 11)));
+
+    for &alg in &[DigestAlgorithm::Sha1, DigestAlgorithm::Sha256] {
+        let max_len = k - 2 * alg.digest_length() - 2;
+        for &label in &["", "beep boop"] {
+            for &len in &[0, 1, 16, max_len] {
+                let msg = rand_bytes(len);
+                let ciphertext = pub_key.oaep_encrypt(alg, label.as_bytes(),
+                                                      &msg).unwrap();
+                assert_eq!(ciphertext.len(), k);
+                assert_eq!(priv_key.oaep_decrypt(alg, label.as_bytes(),
+                                                 &ciphertext).unwrap(), msg,
+                           "oaep {:?} round trip failed for {} bytes",
+                           alg, len);
+            }
+        }
+        assert_eq!(pub_key.oaep_encrypt(alg, &[], &rand_bytes(max_len + 1)),
+                   Err(Error::MessageTooLong(max_len)));
+
+        let ciphertext = pub_key.oaep_encrypt(alg, "meow".as_bytes(),
+                                              "hi mom".as_bytes()).unwrap();
+        assert_eq!(priv_key.oaep_decrypt(alg, "woof".as_bytes(), &ciphertext),
+                   Err(Error::InvalidPadding),
+                   "oaep {:?} accepted the wrong label", alg);
+    }
+
+    // unpadded messages shouldn't unpad, 03 so it can't be v1.5 by chance
+    let mut msg = vec!(0u8, 3u8);
+    msg.extend(rand_bytes(k - 2));
+    let ciphertext = mpz_bytes_zero_pad(&pub_key.encrypt_to_mpz(&msg), k);
+    assert_eq!(priv_key.pkcs1v15_decrypt(&ciphertext),
+               Err(Error::InvalidPadding));
+    assert_eq!(priv_key.oaep_decrypt(DigestAlgorithm::Sha1, &[], &ciphertext),
+               Err(Error::InvalidPadding));
+
+    // neither should v1.5 padding with a short run of padding
+    let mut short_pad = vec!(0u8, 2u8);
+    short_pad.extend(vec!(0xffu8; 7));
+    short_pad.push(0u8);
+    short_pad.extend(rand_bytes(k - short_pad.len()));
+    let ciphertext = mpz_bytes_zero_pad(&pub_key.encrypt_to_mpz(&short_pad),
+                                        k);
+    assert_eq!(priv_key.pkcs1v15_decrypt(&ciphertext),
+               Err(Error::InvalidPadding));
+
+    let ciphertext = pub_key.pkcs1v15_encrypt("hi mom".as_bytes()).unwrap();
+    assert_eq!(priv_key.pkcs1v15_decrypt(&ciphertext[1..]),
+               Err(Error::BadCiphertextLength(k - 1)));
+}
+
+// ciphertexts from python's cryptography package, so we know we agree
+// with somebody else about the encodings
+fn encryption_padding_interop_test() {
+    let n = Mpz::from_str_radix(concat!(
+        "c4e0d0ded3c4d4b16b7905faaf6a261f5643e0eec107f890e907437153e83bb7",
+        "8a2df30448c6b47545c23f8b2bf9b2dfa2212e63553b52b35ec92b77deb53138",
+        "6f8c9079d5fe9de50c704f0eef828d4f4bb0296dd291e652a7e18174b2090760",
+        "45f160973b2dc8d869fcdd8da2665ac16d2b61599ec749c3d87f6a29b9837cad"),
+        16).unwrap();
+    let d = Mpz::from_str_radix(concat!(
+        "9fdca8dd767929f7927be7c6016fa0daeb321ccfe13fb6aefbf8e0e381ad5de8",
+        "0fb475d2b1c15f40a64f2f47b38282a32243ed25cd4c693faaa490be39c148df",
+        "cc4a521db1181f246c755276db3169154362adaafed489ec6ff4315c437392b3",
+        "9fd78fe0ec25f250bc6c69e83fa4ac4a02734fedeec1cee4ec71c0f2dbc2a9e1"),
+        16).unwrap();
+    let priv_key = PrivateKey::new(&d, &n);
+    let msg = "beep boop meow".as_bytes();
+
+    let pkcs1v15 = hex_to_bytes(concat!(
+        "af27c6ce01bdc60a1cf5575cd0cfcd6a1c5fff7c17b970d7d023ba438fc4d2f7",
+        "1114ade5818d89ee8253ede8fb6901649f25d9a998c95e56f9809114eb64885b",
+        "1fa117d67414bd72f818eb99abf3695f3e3745410b022846eba9f7764cfb2b1d",
+        "4ff65e32f4ae773d683d5c924da769a61ab347a40c3d90c5e8229bce0666df5d"))
+        .unwrap();
+    assert_eq!(priv_key.pkcs1v15_decrypt(&pkcs1v15).unwrap(), msg);
+
+    let oaep_sha1 = hex_to_bytes(concat!(
+        "85273937ff8c314d6e93fb1e8575af6b5c867e63560c6f2cbcf97acfcb66024d",
+        "3872ffe80c72fa80f72c5d6a3a4c8cad610df1399a4d4f778d8b1adadfecee8d",
+        "7647e63889f5b37643791dfba0be26bb5f684614fbacd5b9e91c231421afa53a",
+        "3edda8dcea1097e04caccba8881a1d66e0cc91e6ead2d4a4146836a8a3a5d01d"))
+        .unwrap();
+    assert_eq!(priv_key.oaep_decrypt(DigestAlgorithm::Sha1, &[],
+                                     &oaep_sha1).unwrap(), msg);
+
+    let oaep_sha256 = hex_to_bytes(concat!(
+        "6ff1f6c9ea4a8abcb19d11a76e16eb60f638fc380ea053a7a111aaada4c44b63",
+        "74149c9c436113f0af3439590cd9e630abc355f6e7aeffcfeb8304abd106ec69",
+        "47594da7b1f650595ded5389575855b2dc46b80e5059679850c78a911a1b3561",
+        "9e94c43d48aa1fae5c2f42665183329adef7c9eb997e1848b815da14c9c95093"))
+        .unwrap();
+    assert_eq!(priv_key.oaep_decrypt(DigestAlgorithm::Sha256,
+                                     "label".as_bytes(),
+                                     &oaep_sha256).unwrap(), msg);
+    assert_eq!(priv_key.oaep_decrypt(DigestAlgorithm::Sha1,
+                                     "label".as_bytes(), &oaep_sha256),
+               Err(Error::InvalidPadding));
+}
+
+// keys from openssl genrsa, with the other formats from openssl rsa
+// -traditional, -pubout and -RSAPublicKey_out
+fn key_serialization_openssl_test() {
+    let pkcs8_pem = read_fixture("data/rsa1024_pkcs8.pem");
+    let pkcs1_pem = read_fixture("data/rsa1024_pkcs1.pem");
+    let spki_pem = read_fixture("data/rsa1024_pub.pem");
+    let pkcs1_pub_pem = read_fixture("data/rsa1024_pkcs1_pub.pem");
+
+    let priv_key = PrivateKey::from_pkcs8_pem(&pkcs8_pem).unwrap();
+    assert_eq!(PrivateKey::from_pkcs1_pem(&pkcs1_pem).unwrap(), priv_key);
+    let pub_key = PublicKey::from_spki_pem(&spki_pem).unwrap();
+    assert_eq!(PublicKey::from_pkcs1_pem(&pkcs1_pub_pem).unwrap(), pub_key);
+    assert_eq!(priv_key.public_key(), Some(pub_key.clone()));
+    assert_eq!(pub_key.e, Mpz::from(65537u64));
+    assert_eq!(pub_key.n.bit_length(), 1024);
+
+    // we should write out exactly what openssl did
+    assert_eq!(priv_key.to_pkcs8_pem().unwrap(), pkcs8_pem);
+    assert_eq!(priv_key.to_pkcs1_pem().unwrap(), pkcs1_pem);
+    assert_eq!(pub_key.to_spki_pem(), spki_pem);
+    assert_eq!(pub_key.to_pkcs1_pem(), pkcs1_pub_pem);
+
+    let crt = priv_key.crt.clone().unwrap();
+    assert_eq!(&crt.p * &crt.q, priv_key.n);
+    assert_eq!((&crt.qinv * &crt.q).modulus(&crt.p), Mpz::one());
+
+    let msg = "beep boop meow".as_bytes();
+    let signature = priv_key.pkcs1v15_sign(DigestAlgorithm::Sha256, msg)
+                            .unwrap();
+    assert_eq!(pub_key.pkcs1v15_verify(DigestAlgorithm::Sha256, msg,
+                                       &signature), Ok(()));
+    let ciphertext = pub_key.oaep_encrypt(DigestAlgorithm::Sha1, &[],
+                                          msg).unwrap();
+    assert_eq!(priv_key.oaep_decrypt(DigestAlgorithm::Sha1, &[],
+                                     &ciphertext).unwrap(), msg);
+}
+
+fn key_serialization_test() {
+    let (pub_key, priv_key) = new_keypair(512);
+
+    let pem = priv_key.to_pkcs1_pem().unwrap();
+    assert_eq!(PrivateKey::from_pkcs1_pem(&pem).unwrap(), priv_key);
+    let pem = priv_key.to_pkcs8_pem().unwrap();
+    assert_eq!(PrivateKey::from_pkcs8_pem(&pem).unwrap(), priv_key);
+    assert_eq!(PublicKey::from_spki_der(&pub_key.to_spki_der()).unwrap(),
+               pub_key);
+    assert_eq!(PublicKey::from_pkcs1_der(&pub_key.to_pkcs1_der()).unwrap(),
+               pub_key);
+
+    // the labels have to match
+    assert!(PrivateKey::from_pkcs8_pem(&priv_key.to_pkcs1_pem().unwrap())
+            .is_err());
+    assert!(PublicKey::from_spki_pem(&pub_key.to_pkcs1_pem()).is_err());
+
+    // nothing to write without the crt parameters
+    let bare = PrivateKey::new(&priv_key.d, &priv_key.n);
+    assert_eq!(bare.to_pkcs1_der(),
+               Err(Error::InvalidKey("no crt parameters")));
+    assert_eq!(bare.public_key(), None);
+
+    // truncating or tacking on anything should be caught
+    let der = priv_key.to_pkcs1_der().unwrap();
+    for len in 0..der.len() {
+        assert!(PrivateKey::from_pkcs1_der(&der[..len]).is_err(),
+                "truncated pkcs1 key parsed at {} bytes", len);
+    }
+    let mut long = der.clone();
+    long.push(0u8);
+    assert!(PrivateKey::from_pkcs1_der(&long).is_err());
+
+    // a pkcs8 key isn't a pkcs1 one
+    let pkcs8 = priv_key.to_pkcs8_der().unwrap();
+    assert!(PrivateKey::from_pkcs1_der(&pkcs8).is_err());
+
+    // p swapped for something else doesn't make n
+    let mut bad = priv_key.clone();
+    if let Some(ref mut crt) = bad.crt {
+        crt.p += Mpz::one();
+    }
+    let bad_pem = pem_encode(PKCS1_PRIVATE_PEM_LABEL,
+                             &bad.to_pkcs1_der().unwrap());
+    assert_eq!(PrivateKey::from_pkcs1_pem(&bad_pem),
+               Err(Error::InvalidKey("n != p * q")));
+}
+
+fn crt_decrypt_test() {
+    let priv_key = PrivateKey::from_pkcs8_pem(
+        &read_fixture("data/rsa1024_pkcs8.pem")).unwrap();
+    let (_, generated) = new_keypair(512);
+
+    for key in &[priv_key.clone(), generated] {
+        let n = &key.n;
+        let mut nums = vec!(Mpz::zero(), Mpz::one(), n - Mpz::one());
+        for _ in 0..16 {
+            nums.push(randomish_mpz_lt(n));
+        }
+        for c in &nums {
+            let expected = key.decrypt_mpz_plain(c);
+            assert_eq!(key.decrypt_mpz_crt(c).unwrap(), expected);
+            assert_eq!(key.decrypt_mpz(c), Ok(expected));
+        }
+    }
+
+    // a glitched dp: the CRT result gets caught, and decrypting or
+    // signing with the key fails rather than giving anything out
+    let mut faulty = priv_key.clone();
+    if let Some(ref mut crt) = faulty.crt {
+        crt.dp += Mpz::one();
+    }
+    let c = randomish_mpz_lt(&priv_key.n);
+    assert_eq!(faulty.decrypt_mpz_crt(&c), Err(Error::FaultDetected));
+    assert_eq!(faulty.decrypt_mpz(&c), Err(Error::FaultDetected));
+    assert_eq!(faulty.pkcs1v15_sign(DigestAlgorithm::Sha256, b"beep boop"),
+               Err(Error::FaultDetected));
+
+    // why it matters: the unchecked faulty result is right mod q and wrong
+    // mod p, so it gives away q
+    {
+        let crt = faulty.crt.as_ref().unwrap();
+        let m1 = c.powm(&crt.dp, &crt.p);
+        let m2 = c.powm(&crt.dq, &crt.q);
+        let h = (&crt.qinv * (&m1 - &m2)).modulus(&crt.p);
+        let m = &m2 + &h * &crt.q;
+        let diff = (m.powm(&crt.e, &faulty.n) - &c).modulus(&faulty.n);
+        assert_eq!(diff.gcd(&faulty.n), crt.q);
+    }
+
+    let bare = PrivateKey::new(&priv_key.d, &priv_key.n);
+    assert_eq!(bare.decrypt_mpz_crt(&c),
+               Err(Error::InvalidKey("no crt parameters")));
+    assert_eq!(bare.decrypt_mpz(&c), Ok(priv_key.decrypt_mpz_plain(&c)));
+
+    println!("Finished RSA CRT decrypt tests");
+}
+
+// average time per decryption, plain vs CRT
+fn crt_decrypt_benchmark() {
+    const ITERATIONS: u32 = 10;
+
+    for &bit_len in &[1024, 2048, 4096] {
+        let (_, priv_key) = new_keypair(bit_len);
+        let nums: Vec<Mpz> = (0..ITERATIONS).map(|_| {
+            randomish_mpz_lt(&priv_key.n)
+        }).collect();
+
+        let now = Instant::now();
+        for c in &nums {
+            priv_key.decrypt_mpz_plain(c);
+        }
+        let plain = now.elapsed() / ITERATIONS;
+
+        let now = Instant::now();
+        for c in &nums {
+            priv_key.decrypt_mpz_crt(c).unwrap();
+        }
+        let crt = now.elapsed() / ITERATIONS;
+
+        println!("RSA-{} decrypt: plain {:?}, crt {:?} ({:.1}x)", bit_len,
+                 plain, crt, plain.as_secs_f64() / crt.as_secs_f64());
+    }
+}
+
+pub fn rsa_test(full_test: bool) {
     rsa_keypair_test(32);
     rsa_keypair_test(512);
     rsa_keypair_test(2048);
     rsa_e3_broadcast_test();
     unpadded_msg_test();
     pkcs1v15_test();
+    pkcs1v15_verify_test();
+    encryption_padding_test();
+    encryption_padding_interop_test();
+    key_serialization_openssl_test();
+    key_serialization_test();
+    crt_decrypt_test();
     pkcs1v15_e3_no_pad_check_test();
+    if full_test {
+        crt_decrypt_benchmark();
+    }
 }
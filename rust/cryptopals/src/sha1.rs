@@ -18,6 +18,8 @@ use self::core::cmp;
 use self::core::fmt;
 use self::core::mem;
 
+use merkle_damgard::{MerkleDamgard, DigestIsState};
+
 /// The length of a SHA1 digest in bytes
 pub const DIGEST_LENGTH: usize = 20;
 
@@ -255,6 +257,43 @@ impl Sha1State {
     }
 }
 
+impl MerkleDamgard for Sha1 {
+    const BLOCK_SIZE: usize = 64;
+    const DIGEST_LENGTH: usize = DIGEST_LENGTH;
+    const LENGTH_SIZE: usize = 8;
+    const BIG_ENDIAN: bool = true;
+
+    fn new() -> Sha1 {
+        Sha1::new()
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        Sha1::update(self, data)
+    }
+
+    fn finish(&self) -> Vec<u8> {
+        self.digest().bytes().to_vec()
+    }
+
+    fn export_state(&self) -> (Vec<u8>, u64) {
+        (Digest { data: self.state }.bytes().to_vec(), self.len)
+    }
+
+    fn import_state(state: &[u8], len: u64) -> Sha1 {
+        assert!(state.len() == DIGEST_LENGTH,
+                "bad sha1 state length {}", state.len());
+        let mut hash = Sha1::new();
+        for (word, chunk) in hash.state.state.iter_mut().zip(state.chunks(4)) {
+            *word = ((chunk[0] as u32) << 24) | ((chunk[1] as u32) << 16) |
+                    ((chunk[2] as u32) << 8) | (chunk[3] as u32);
+        }
+        hash.len = len;
+        hash
+    }
+}
+
+impl DigestIsState for Sha1 {}
+
 impl Clone for Blocks {
     fn clone(&self) -> Blocks {
         Blocks { ..*self }
@@ -0,0 +1,536 @@
+use std::cmp;
+
+use merkle_damgard::{MerkleDamgard, DigestIsState};
+
+pub const SHA224_DIGEST_LENGTH: usize = 28;
+pub const SHA256_DIGEST_LENGTH: usize = 32;
+pub const SHA384_DIGEST_LENGTH: usize = 48;
+pub const SHA512_DIGEST_LENGTH: usize = 64;
+
+const SHA256_BLOCK_SIZE: usize = 64;
+const SHA512_BLOCK_SIZE: usize = 128;
+
+const SHA224_INITIAL_STATE: [u32; 8] = [
+    0xc1059ed8, 0x367cd507, 0x3070dd17, 0xf70e5939,
+    0xffc00b31, 0x68581511, 0x64f98fa7, 0xbefa4fa4,
+];
+
+const SHA256_INITIAL_STATE: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a,
+    0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+const SHA384_INITIAL_STATE: [u64; 8] = [
+    0xcbbb9d5dc1059ed8, 0x629a292a367cd507,
+    0x9159015a3070dd17, 0x152fecd8f70e5939,
+    0x67332667ffc00b31, 0x8eb44a8768581511,
+    0xdb0c2e0d64f98fa7, 0x47b5481dbefa4fa4,
+];
+
+const SHA512_INITIAL_STATE: [u64; 8] = [
+    0x6a09e667f3bcc908, 0xbb67ae8584caa73b,
+    0x3c6ef372fe94f82b, 0xa54ff53a5f1d36f1,
+    0x510e527fade682d1, 0x9b05688c2b3e6c1f,
+    0x1f83d9abfb41bd6b, 0x5be0cd19137e2179,
+];
+
+const SHA256_K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5,
+    0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3,
+    0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc,
+    0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7,
+    0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13,
+    0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3,
+    0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5,
+    0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208,
+    0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+const SHA512_K: [u64; 80] = [
+    0x428a2f98d728ae22, 0x7137449123ef65cd, 0xb5c0fbcfec4d3b2f,
+    0xe9b5dba58189dbbc, 0x3956c25bf348b538, 0x59f111f1b605d019,
+    0x923f82a4af194f9b, 0xab1c5ed5da6d8118, 0xd807aa98a3030242,
+    0x12835b0145706fbe, 0x243185be4ee4b28c, 0x550c7dc3d5ffb4e2,
+    0x72be5d74f27b896f, 0x80deb1fe3b1696b1, 0x9bdc06a725c71235,
+    0xc19bf174cf692694, 0xe49b69c19ef14ad2, 0xefbe4786384f25e3,
+    0x0fc19dc68b8cd5b5, 0x240ca1cc77ac9c65, 0x2de92c6f592b0275,
+    0x4a7484aa6ea6e483, 0x5cb0a9dcbd41fbd4, 0x76f988da831153b5,
+    0x983e5152ee66dfab, 0xa831c66d2db43210, 0xb00327c898fb213f,
+    0xbf597fc7beef0ee4, 0xc6e00bf33da88fc2, 0xd5a79147930aa725,
+    0x06ca6351e003826f, 0x142929670a0e6e70, 0x27b70a8546d22ffc,
+    0x2e1b21385c26c926, 0x4d2c6dfc5ac42aed, 0x53380d139d95b3df,
+    0x650a73548baf63de, 0x766a0abb3c77b2a8, 0x81c2c92e47edaee6,
+    0x92722c851482353b, 0xa2bfe8a14cf10364, 0xa81a664bbc423001,
+    0xc24b8b70d0f89791, 0xc76c51a30654be30, 0xd192e819d6ef5218,
+    0xd69906245565a910, 0xf40e35855771202a, 0x106aa07032bbd1b8,
+    0x19a4c116b8d2d0c8, 0x1e376c085141ab53, 0x2748774cdf8eeb99,
+    0x34b0bcb5e19b48a8, 0x391c0cb3c5c95a63, 0x4ed8aa4ae3418acb,
+    0x5b9cca4f7763e373, 0x682e6ff3d6b2b8a3, 0x748f82ee5defb2fc,
+    0x78a5636f43172f60, 0x84c87814a1f0ab72, 0x8cc702081a6439ec,
+    0x90befffa23631e28, 0xa4506cebde82bde9, 0xbef9a3f7b2c67915,
+    0xc67178f2e372532b, 0xca273eceea26619c, 0xd186b8c721c0c207,
+    0xeada7dd6cde0eb1e, 0xf57d4f7fee6ed178, 0x06f067aa72176fba,
+    0x0a637dc5a2c898a6, 0x113f9804bef90dae, 0x1b710b35131c471b,
+    0x28db77f523047d84, 0x32caab7b40c72493, 0x3c9ebe0a15c9bebc,
+    0x431d67c49c100d4c, 0x4cc5d4becb3e42b6, 0x597f299cfc657e2a,
+    0x5fcb6fab3ad6faec, 0x6c44198c4a475817,
+];
+
+/// SHA-256 (FIPS 180-4) with the state out in the open, so unlike the
+/// sha2 crate we can fixate it
+#[derive(Clone)]
+pub struct Sha256 {
+    pub state: [u32; 8],
+    block: [u8; SHA256_BLOCK_SIZE],
+    block_len: usize,
+    // bytes in all the blocks processed so far
+    len: u64,
+}
+
+/// SHA-256 with a different iv, truncated to 28 bytes
+#[derive(Clone)]
+pub struct Sha224 {
+    inner: Sha256,
+}
+
+#[derive(Clone)]
+pub struct Sha512 {
+    pub state: [u64; 8],
+    block: [u8; SHA512_BLOCK_SIZE],
+    block_len: usize,
+    len: u64,
+}
+
+/// SHA-512 with a different iv, truncated to 48 bytes
+#[derive(Clone)]
+pub struct Sha384 {
+    inner: Sha512,
+}
+
+pub fn sha224(msg: &[u8]) -> [u8; SHA224_DIGEST_LENGTH] {
+    let mut hash = Sha224::new();
+    hash.update(msg);
+    hash.digest()
+}
+
+pub fn sha256(msg: &[u8]) -> [u8; SHA256_DIGEST_LENGTH] {
+    let mut hash = Sha256::new();
+    hash.update(msg);
+    hash.digest()
+}
+
+pub fn sha384(msg: &[u8]) -> [u8; SHA384_DIGEST_LENGTH] {
+    let mut hash = Sha384::new();
+    hash.update(msg);
+    hash.digest()
+}
+
+pub fn sha512(msg: &[u8]) -> [u8; SHA512_DIGEST_LENGTH] {
+    let mut hash = Sha512::new();
+    hash.update(msg);
+    hash.digest()
+}
+
+// Buffers partial blocks between updates and hands whole ones to process.
+// Returns the number of whole blocks processed.
+fn update_blocks<F: FnMut(&[u8])>(block: &mut [u8], block_len: &mut usize,
+                                  mut data: &[u8], mut process: F) -> u64 {
+    let block_size = block.len();
+    let mut processed = 0;
+
+    // top up whatever's left over from last time first
+    if *block_len > 0 {
+        let amt = cmp::min(data.len(), block_size - *block_len);
+        block[*block_len..*block_len + amt].copy_from_slice(&data[..amt]);
+        *block_len += amt;
+        data = &data[amt..];
+        if *block_len < block_size {
+            return processed;
+        }
+        process(block);
+        processed += 1;
+        *block_len = 0;
+    }
+
+    for chunk in data.chunks(block_size) {
+        if chunk.len() == block_size {
+            process(chunk);
+            processed += 1;
+        } else {
+            block[..chunk.len()].copy_from_slice(chunk);
+            *block_len = chunk.len();
+        }
+    }
+    processed
+}
+
+fn u32s_to_bytes(words: &[u32]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(words.len() * 4);
+    for word in words {
+        result.extend_from_slice(&word.to_be_bytes());
+    }
+    result
+}
+
+fn u64s_to_bytes(words: &[u64]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(words.len() * 8);
+    for word in words {
+        result.extend_from_slice(&word.to_be_bytes());
+    }
+    result
+}
+
+impl Default for Sha256 {
+    fn default() -> Sha256 {
+        Sha256::new()
+    }
+}
+
+impl Sha256 {
+    pub fn new() -> Sha256 {
+        Sha256::with_state(SHA256_INITIAL_STATE)
+    }
+
+    fn with_state(state: [u32; 8]) -> Sha256 {
+        Sha256 {
+            state,
+            block: [0; SHA256_BLOCK_SIZE],
+            block_len: 0,
+            len: 0,
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        let state = &mut self.state;
+        let blocks = update_blocks(&mut self.block, &mut self.block_len, data,
+                                   |block| sha256_process(state, block));
+        self.len += blocks * SHA256_BLOCK_SIZE as u64;
+    }
+
+    fn final_state(&self) -> [u32; 8] {
+        let mut hash = self.clone();
+        let msg_len = self.len + self.block_len as u64;
+        hash.update(&<Sha256 as MerkleDamgard>::padding(msg_len));
+        hash.state
+    }
+
+    pub fn digest(&self) -> [u8; SHA256_DIGEST_LENGTH] {
+        let mut result = [0u8; SHA256_DIGEST_LENGTH];
+        result.copy_from_slice(&u32s_to_bytes(&self.final_state()));
+        result
+    }
+}
+
+impl Default for Sha224 {
+    fn default() -> Sha224 {
+        Sha224::new()
+    }
+}
+
+impl Sha224 {
+    pub fn new() -> Sha224 {
+        Sha224 { inner: Sha256::with_state(SHA224_INITIAL_STATE) }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.inner.update(data);
+    }
+
+    pub fn digest(&self) -> [u8; SHA224_DIGEST_LENGTH] {
+        let mut result = [0u8; SHA224_DIGEST_LENGTH];
+        result.copy_from_slice(
+            &u32s_to_bytes(&self.inner.final_state())[..SHA224_DIGEST_LENGTH]);
+        result
+    }
+}
+
+fn sha256_process(state: &mut [u32; 8], block: &[u8]) {
+    let mut w = [0u32; 64];
+    for (word, chunk) in w.iter_mut().zip(block.chunks(4)) {
+        *word = u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+    }
+    for i in 16..64 {
+        let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^
+                 (w[i - 15] >> 3);
+        let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^
+                 (w[i - 2] >> 10);
+        w[i] = w[i - 16].wrapping_add(s0)
+                        .wrapping_add(w[i - 7])
+                        .wrapping_add(s1);
+    }
+
+    let mut v = *state;
+    for i in 0..64 {
+        let s1 = v[4].rotate_right(6) ^ v[4].rotate_right(11) ^
+                 v[4].rotate_right(25);
+        let ch = (v[4] & v[5]) ^ (!v[4] & v[6]);
+        let t1 = v[7].wrapping_add(s1)
+                     .wrapping_add(ch)
+                     .wrapping_add(SHA256_K[i])
+                     .wrapping_add(w[i]);
+        let s0 = v[0].rotate_right(2) ^ v[0].rotate_right(13) ^
+                 v[0].rotate_right(22);
+        let maj = (v[0] & v[1]) ^ (v[0] & v[2]) ^ (v[1] & v[2]);
+        let t2 = s0.wrapping_add(maj);
+
+        // h = g, g = f, ..., e = d + t1, ..., a = t1 + t2
+        v.rotate_right(1);
+        v[4] = v[4].wrapping_add(t1);
+        v[0] = t1.wrapping_add(t2);
+    }
+
+    for (s, x) in state.iter_mut().zip(&v) {
+        *s = s.wrapping_add(*x);
+    }
+}
+
+impl Default for Sha512 {
+    fn default() -> Sha512 {
+        Sha512::new()
+    }
+}
+
+impl Sha512 {
+    pub fn new() -> Sha512 {
+        Sha512::with_state(SHA512_INITIAL_STATE)
+    }
+
+    fn with_state(state: [u64; 8]) -> Sha512 {
+        Sha512 {
+            state,
+            block: [0; SHA512_BLOCK_SIZE],
+            block_len: 0,
+            len: 0,
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        let state = &mut self.state;
+        let blocks = update_blocks(&mut self.block, &mut self.block_len, data,
+                                   |block| sha512_process(state, block));
+        self.len += blocks * SHA512_BLOCK_SIZE as u64;
+    }
+
+    fn final_state(&self) -> [u64; 8] {
+        let mut hash = self.clone();
+        let msg_len = self.len + self.block_len as u64;
+        hash.update(&<Sha512 as MerkleDamgard>::padding(msg_len));
+        hash.state
+    }
+
+    pub fn digest(&self) -> [u8; SHA512_DIGEST_LENGTH] {
+        let mut result = [0u8; SHA512_DIGEST_LENGTH];
+        result.copy_from_slice(&u64s_to_bytes(&self.final_state()));
+        result
+    }
+}
+
+impl Default for Sha384 {
+    fn default() -> Sha384 {
+        Sha384::new()
+    }
+}
+
+impl Sha384 {
+    pub fn new() -> Sha384 {
+        Sha384 { inner: Sha512::with_state(SHA384_INITIAL_STATE) }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.inner.update(data);
+    }
+
+    pub fn digest(&self) -> [u8; SHA384_DIGEST_LENGTH] {
+        let mut result = [0u8; SHA384_DIGEST_LENGTH];
+        result.copy_from_slice(
+            &u64s_to_bytes(&self.inner.final_state())[..SHA384_DIGEST_LENGTH]);
+        result
+    }
+}
+
+fn sha512_process(state: &mut [u64; 8], block: &[u8]) {
+    let mut w = [0u64; 80];
+    for (word, chunk) in w.iter_mut().zip(block.chunks(8)) {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(chunk);
+        *word = u64::from_be_bytes(bytes);
+    }
+    for i in 16..80 {
+        let s0 = w[i - 15].rotate_right(1) ^ w[i - 15].rotate_right(8) ^
+                 (w[i - 15] >> 7);
+        let s1 = w[i - 2].rotate_right(19) ^ w[i - 2].rotate_right(61) ^
+                 (w[i - 2] >> 6);
+        w[i] = w[i - 16].wrapping_add(s0)
+                        .wrapping_add(w[i - 7])
+                        .wrapping_add(s1);
+    }
+
+    // same shape as sha256, just 64 bit words and different rotations
+    let mut v = *state;
+    for i in 0..80 {
+        let s1 = v[4].rotate_right(14) ^ v[4].rotate_right(18) ^
+                 v[4].rotate_right(41);
+        let ch = (v[4] & v[5]) ^ (!v[4] & v[6]);
+        let t1 = v[7].wrapping_add(s1)
+                     .wrapping_add(ch)
+                     .wrapping_add(SHA512_K[i])
+                     .wrapping_add(w[i]);
+        let s0 = v[0].rotate_right(28) ^ v[0].rotate_right(34) ^
+                 v[0].rotate_right(39);
+        let maj = (v[0] & v[1]) ^ (v[0] & v[2]) ^ (v[1] & v[2]);
+        let t2 = s0.wrapping_add(maj);
+
+        v.rotate_right(1);
+        v[4] = v[4].wrapping_add(t1);
+        v[0] = t1.wrapping_add(t2);
+    }
+
+    for (s, x) in state.iter_mut().zip(&v) {
+        *s = s.wrapping_add(*x);
+    }
+}
+
+fn bytes_to_u32s(state: &[u8]) -> [u32; 8] {
+    assert!(state.len() == 32, "bad sha256 state length {}", state.len());
+    let mut words = [0u32; 8];
+    for (word, chunk) in words.iter_mut().zip(state.chunks(4)) {
+        *word = u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+    }
+    words
+}
+
+fn bytes_to_u64s(state: &[u8]) -> [u64; 8] {
+    assert!(state.len() == 64, "bad sha512 state length {}", state.len());
+    let mut words = [0u64; 8];
+    for (word, chunk) in words.iter_mut().zip(state.chunks(8)) {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(chunk);
+        *word = u64::from_be_bytes(bytes);
+    }
+    words
+}
+
+// the truncated ones export and import the whole state, but their digest
+// doesn't have enough in it to pick up from
+impl MerkleDamgard for Sha224 {
+    const BLOCK_SIZE: usize = SHA256_BLOCK_SIZE;
+    const DIGEST_LENGTH: usize = SHA224_DIGEST_LENGTH;
+    const LENGTH_SIZE: usize = 8;
+    const BIG_ENDIAN: bool = true;
+
+    fn new() -> Sha224 {
+        Sha224::new()
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        Sha224::update(self, data)
+    }
+
+    fn finish(&self) -> Vec<u8> {
+        self.digest().to_vec()
+    }
+
+    fn export_state(&self) -> (Vec<u8>, u64) {
+        self.inner.export_state()
+    }
+
+    fn import_state(state: &[u8], len: u64) -> Sha224 {
+        Sha224 { inner: Sha256::import_state(state, len) }
+    }
+}
+
+impl MerkleDamgard for Sha256 {
+    const BLOCK_SIZE: usize = SHA256_BLOCK_SIZE;
+    const DIGEST_LENGTH: usize = SHA256_DIGEST_LENGTH;
+    const LENGTH_SIZE: usize = 8;
+    const BIG_ENDIAN: bool = true;
+
+    fn new() -> Sha256 {
+        Sha256::new()
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        Sha256::update(self, data)
+    }
+
+    fn finish(&self) -> Vec<u8> {
+        self.digest().to_vec()
+    }
+
+    fn export_state(&self) -> (Vec<u8>, u64) {
+        (u32s_to_bytes(&self.state), self.len)
+    }
+
+    fn import_state(state: &[u8], len: u64) -> Sha256 {
+        let mut hash = Sha256::with_state(bytes_to_u32s(state));
+        hash.len = len;
+        hash
+    }
+}
+
+impl DigestIsState for Sha256 {}
+
+impl MerkleDamgard for Sha384 {
+    const BLOCK_SIZE: usize = SHA512_BLOCK_SIZE;
+    const DIGEST_LENGTH: usize = SHA384_DIGEST_LENGTH;
+    const LENGTH_SIZE: usize = 16;
+    const BIG_ENDIAN: bool = true;
+
+    fn new() -> Sha384 {
+        Sha384::new()
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        Sha384::update(self, data)
+    }
+
+    fn finish(&self) -> Vec<u8> {
+        self.digest().to_vec()
+    }
+
+    fn export_state(&self) -> (Vec<u8>, u64) {
+        self.inner.export_state()
+    }
+
+    fn import_state(state: &[u8], len: u64) -> Sha384 {
+        Sha384 { inner: Sha512::import_state(state, len) }
+    }
+}
+
+impl MerkleDamgard for Sha512 {
+    const BLOCK_SIZE: usize = SHA512_BLOCK_SIZE;
+    const DIGEST_LENGTH: usize = SHA512_DIGEST_LENGTH;
+    const LENGTH_SIZE: usize = 16;
+    const BIG_ENDIAN: bool = true;
+
+    fn new() -> Sha512 {
+        Sha512::new()
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        Sha512::update(self, data)
+    }
+
+    fn finish(&self) -> Vec<u8> {
+        self.digest().to_vec()
+    }
+
+    fn export_state(&self) -> (Vec<u8>, u64) {
+        (u64s_to_bytes(&self.state), self.len)
+    }
+
+    fn import_state(state: &[u8], len: u64) -> Sha512 {
+        let mut hash = Sha512::with_state(bytes_to_u64s(state));
+        hash.len = len;
+        hash
+    }
+}
+
+impl DigestIsState for Sha512 {}
@@ -2,12 +2,11 @@ use std::sync::mpsc::{channel, Sender, Receiver};
 use std::thread;
 
 extern crate gmp;
-extern crate sha2;
 
 use self::gmp::mpz::Mpz;
-use self::sha2::{Sha256, Digest};
 
 use mac::hmac_sha256;
+use sha2::sha256;
 use srp;
 use util::{rand_bytes, randomish_mpz_lt, bytes_to_mpz, mpz_bytes};
 
@@ -72,7 +71,7 @@ fn srp_client(tx: Sender<SRPMsg>, rx: Receiver<SRPMsg>,
     let pub_b = rx.recv().unwrap().expect_num();
 
     let ab = [mpz_bytes(&pub_a), mpz_bytes(&pub_b)].concat();
-    let u = bytes_to_mpz(&Sha256::digest(&ab).to_vec());
+    let u = bytes_to_mpz(&sha256(&ab));
 
     let x = bytes_to_mpz(&srp::salted_hash(&salt, TEST_IDENTITY.as_bytes(),
                                            TEST_PASSWORD.as_bytes()));
@@ -85,7 +84,7 @@ fn srp_client(tx: Sender<SRPMsg>, rx: Receiver<SRPMsg>,
             tmp.powm(&(priv_a + (u * x)), &n)
         }
     };
-    let k = Sha256::digest(&mpz_bytes(&s)).to_vec();
+    let k = sha256(&mpz_bytes(&s)).to_vec();
 
     let hmac = hmac_sha256(&k, &salt);
     tx.send(SRPMsg::Bytes(hmac)).unwrap();
@@ -112,10 +111,10 @@ fn srp_server(tx: Sender<SRPMsg>, rx: Receiver<SRPMsg>) {
     tx.send(SRPMsg::Num(pub_b.clone())).unwrap();
 
     let ab = [mpz_bytes(&pub_a), mpz_bytes(&pub_b)].concat();
-    let u = bytes_to_mpz(&Sha256::digest(&ab).to_vec());
+    let u = bytes_to_mpz(&sha256(&ab));
 
     let s = (pub_a * v.powm(&u, &n)).powm(&priv_b, &n);
-    let k = Sha256::digest(&mpz_bytes(&s)).to_vec();
+    let k = sha256(&mpz_bytes(&s)).to_vec();
 
     let hmac = hmac_sha256(&k, &salt);
     let client_hmac = rx.recv().unwrap().expect_bytes();
@@ -160,7 +159,7 @@ fn srp_simplified_client(tx: Sender<SRPMsg>, rx: Receiver<SRPMsg>) {
     let x = bytes_to_mpz(&srp::salted_hash(&salt, TEST_IDENTITY.as_bytes(),
                                            TEST_PASSWORD.as_bytes()));
     let s = pub_b.powm(&(priv_a + (u * x)), &n);
-    let k = Sha256::digest(&mpz_bytes(&s)).to_vec();
+    let k = sha256(&mpz_bytes(&s)).to_vec();
 
     let hmac = hmac_sha256(&k, &salt);
     tx.send(SRPMsg::Bytes(hmac)).unwrap();
@@ -215,7 +214,7 @@ fn srp_simplified_server(tx: Sender<SRPMsg>, rx: Receiver<SRPMsg>,
                                                    TEST_IDENTITY.as_bytes(),
                                                    word.as_bytes()));
             let s = (&pub_a * g.powm(&x, &n)).modulus(&n);
-            let k = Sha256::digest(&mpz_bytes(&s)).to_vec();
+            let k = sha256(&mpz_bytes(&s)).to_vec();
             let hmac = hmac_sha256(&k, &salt);
             if hmac == client_hmac {
                 return;
@@ -227,7 +226,7 @@ fn srp_simplified_server(tx: Sender<SRPMsg>, rx: Receiver<SRPMsg>,
                                                TEST_PASSWORD.as_bytes()));
         let v = g.powm(&x, &n);
         let s = (pub_a * v.powm(&u, &n)).powm(&priv_b, &n);
-        let k = Sha256::digest(&mpz_bytes(&s)).to_vec();
+        let k = sha256(&mpz_bytes(&s)).to_vec();
 
         let hmac = hmac_sha256(&k, &salt);
         let client_hmac = rx.recv().unwrap().expect_bytes();
@@ -1,4 +1,5 @@
 use aes::AESCipher;
+use error::{Error, Result};
 
 pub const SSV_PREFIX: &'static str = "comment1=cooking%20MCs;userdata=";
 pub const SSV_SUFFIX: &'static str =
@@ -14,15 +15,15 @@ pub fn ssv_aes_encrypt(cipher: &AESCipher, plaintext: &[u8]) -> Vec<u8> {
     cipher.pad_and_encrypt(&result)
 }
 
-pub fn ssv_aes_decrypt(cipher: &AESCipher, ciphertext: &[u8]) -> Vec<u8> {
+pub fn ssv_aes_decrypt(cipher: &AESCipher,
+                       ciphertext: &[u8]) -> Result<Vec<u8>> {
     cipher.decrypt_and_unpad(ciphertext)
 }
 
 /// Checks that output is ASCII otherwise returns error with plaintext
 pub fn ssv_aes_decrypt_and_check(cipher: &AESCipher,
-                                 ciphertext: &[u8]) -> Result<Vec<u8>,
-                                                              Vec<u8>> {
-    let result = ssv_aes_decrypt(cipher, ciphertext);
+                                 ciphertext: &[u8]) -> Result<Vec<u8>> {
+    let result = ssv_aes_decrypt(cipher, ciphertext)?;
     let mut not_ascii = false;
     for b in &result {
         if *b > 127u8 {
@@ -31,7 +32,7 @@ pub fn ssv_aes_decrypt_and_check(cipher: &AESCipher,
         }
     }
     if not_ascii {
-        Err(result)
+        Err(Error::InvalidAscii(result))
     } else {
         Ok(result)
     }
@@ -3,47 +3,51 @@ use std::str;
 
 use aes::AESCipher;
 use aes::ecb::AESCipherECB;
+use error::{Error, Result};
 use pkcs7::pkcs7_pad;
 
-pub fn url_decode(params: &str) -> HashMap<String, String> {
+pub fn url_decode(params: &str) -> Result<HashMap<String, String>> {
     let mut map: HashMap<String, String> = HashMap::new();
     let mut word: Vec<char> = Vec::new();
     let mut key = None;
 
     fn insert(map: &mut HashMap<String, String>,
               key: Option<String>,
-              word: Vec<char>) { 
+              word: Vec<char>) -> Result<()> {
         match key {
             Some(key_str) => {
                 map.insert(key_str, word.into_iter().collect());
             },
             None => {
                 if word.len() == 0 {
-                    panic!("bad && or ends with &");
+                    return Err(Error::MalformedQuery(
+                        String::from("bad && or ends with &")));
                 }
                 map.insert(word.into_iter().collect(), "".to_string());
             },
         };
-    };
+        Ok(())
+    }
 
     for c in params.chars() {
         if c == '=' {
             if word.len() == 0 {
-                panic!("bad &= or started with =");
+                return Err(Error::MalformedQuery(
+                    String::from("bad &= or started with =")));
             }
 
             key = Some(word.into_iter().collect());
             word = Vec::new();
         } else if c == '&' {
-            insert(&mut map, key, word);
+            insert(&mut map, key, word)?;
             key = None;
             word = Vec::new();
         } else {
             word.push(c);
         }
     }
-    insert(&mut map, key, word);
-    map
+    insert(&mut map, key, word)?;
+    Ok(map)
 }
 
 fn profile_for(email: &str) -> String {
@@ -60,9 +64,9 @@ fn encrypt_profile_for(cipher: &AESCipher, email: &str) -> Vec<u8> {
 
 fn decrypt_profile_for(cipher: &AESCipher,
                        ciphertext: &[u8]) -> HashMap<String, String> {
-    let buf = cipher.decrypt_and_unpad(ciphertext);
+    let buf = cipher.decrypt_and_unpad(ciphertext).unwrap();
     let s = str::from_utf8(&buf).unwrap();
-    url_decode(s)
+    url_decode(s).unwrap()
 }
 
 fn trick_url_decode(cipher: &AESCipher) {
@@ -105,11 +109,14 @@ fn trick_url_decode(cipher: &AESCipher) {
 }
 
 pub fn url_test() {
-    let map = url_decode("boop=1&beep=bop&meow=cat");
+    let map = url_decode("boop=1&beep=bop&meow=cat").unwrap();
     println!("url_decode: {:?}", map);
+    for bad in &["=1", "a=1&&b=2", "a=1&", "a=1&=2"] {
+        assert!(url_decode(bad).is_err(), "url_decode accepted {}", bad);
+    }
 
     let key = "YELLOW SUBMARINE".as_bytes();
-    let cipher: AESCipherECB = AESCipherECB::new(key);
+    let cipher: AESCipherECB = AESCipherECB::new(key).unwrap();
     let ciphertext = encrypt_profile_for(&cipher, "foo@bar.com");
     let out = decrypt_profile_for(&cipher, &ciphertext);
     println!("foo@bar.com encrypt and decrypt: {:?}", out);
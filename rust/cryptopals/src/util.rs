@@ -1,10 +1,12 @@
 extern crate gmp;
 extern crate rand;
 
+use std::fs;
+use std::sync::OnceLock;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use self::gmp::mpz::Mpz;
-use self::rand::Rng;
+use self::rand::{Rng, SeedableRng, XorShiftRng};
 use hex::{hex_to_bytes, bytes_to_hex};
 
 pub type EncryptOracle = Fn (&[u8]) -> Vec<u8>;
@@ -56,8 +58,17 @@ pub fn assert_slice_cmp(label: &'static str, expected: &[u8], got: &[u8]) {
             label, expected, got);
 }
 
+/// Contents of a test data file, panicking with the filename if it's not
+/// there since the tests can't do anything without it
+pub fn read_fixture(filename: &str) -> String {
+    match fs::read_to_string(filename) {
+        Ok(contents) => contents,
+        Err(e) => panic!("{}: {}", filename, e),
+    }
+}
+
 pub fn mpz_bytes(mpz: &Mpz) -> Vec<u8> {
-    hex_to_bytes(&mpz.to_str_radix(16))
+    hex_to_bytes(&mpz.to_str_radix(16)).unwrap()
 }
 
 pub fn bytes_to_mpz(bytes: &[u8]) -> Mpz {
@@ -105,3 +116,317 @@ pub fn mpz_print_padded(mpz: &Mpz, byte_len: usize) {
         println!("{}", bytes_to_hex(chunk));
     }
 }
+
+// doesn't bail early so timing only leaks the lengths
+pub fn constant_time_eq(left: &[u8], right: &[u8]) -> bool {
+    if left.len() != right.len() {
+        return false;
+    }
+
+    let mut diff = 0u8;
+    for (l, r) in left.iter().zip(right) {
+        diff |= l ^ r;
+    }
+    diff == 0
+}
+
+/// The same stream every time for a given seed, for tests that need to
+/// be reproducible
+pub fn seeded_rng(seed: u32) -> XorShiftRng {
+    XorShiftRng::from_seed([seed, 0x9e37_79b9, 0x7f4a_7c15, 0xf39c_c060])
+}
+
+/// Uniform in [0, 2^bits)
+pub fn random_mpz_bits<R: Rng>(rng: &mut R, bits: usize) -> Mpz {
+    if bits == 0 {
+        return Mpz::zero();
+    }
+    let mut bytes = vec!(0u8; bits.div_ceil(8));
+    rng.fill_bytes(&mut bytes);
+    // mask off whatever's over in the top byte
+    bytes[0] &= 0xff >> (8 * bytes.len() - bits);
+    bytes_to_mpz(&bytes)
+}
+
+/// Uniform in [0, max), by drawing until one's under max
+pub fn random_mpz_below<R: Rng>(rng: &mut R, max: &Mpz) -> Mpz {
+    assert!(*max > Mpz::zero(), "empty range");
+    loop {
+        let num = random_mpz_bits(rng, max.bit_length());
+        if num < *max {
+            return num;
+        }
+    }
+}
+
+/// How hard to try before calling something prime
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrimalityTest {
+    // random bases, each round lets through at most 1/4 of composites
+    MillerRabin { rounds: usize },
+    // Miller-Rabin base 2 plus a strong Lucas test, no known
+    // counterexamples and no randomness
+    BailliePsw,
+}
+
+// primes under 1000 for trial division before the expensive tests,
+// worked out the first time they're needed
+fn small_primes() -> &'static [u64] {
+    static SMALL_PRIMES: OnceLock<Vec<u64>> = OnceLock::new();
+    SMALL_PRIMES.get_or_init(|| {
+        (2..1000u64).filter(|&n| (2..n).take_while(|d| d * d <= n)
+                                       .all(|d| n % d != 0))
+                    .collect()
+    })
+}
+
+fn mpz_mod_u64(n: &Mpz, m: u64) -> u64 {
+    Option::<u64>::from(&(n % m)).unwrap()
+}
+
+// Some(answer) if n is small or has a small factor, None if it needs a
+// real test
+fn trial_division(n: &Mpz, primes: &[u64]) -> Option<bool> {
+    if *n < Mpz::from(2u64) {
+        return Some(false);
+    }
+    for &p in primes {
+        if *n == Mpz::from(p) {
+            return Some(true);
+        }
+        if mpz_mod_u64(n, p) == 0 {
+            return Some(false);
+        }
+    }
+    None
+}
+
+/// Whether odd n > 2 is a strong probable prime to base a, i.e. with
+/// n - 1 = d 2^s either a^d = 1 or a^(d 2^r) = -1 for some r < s
+pub fn is_strong_probable_prime(n: &Mpz, a: &Mpz) -> bool {
+    let n_1 = n - Mpz::one();
+    let mut s = 0;
+    while !n_1.tstbit(s) {
+        s += 1;
+    }
+    let d = &n_1 >> s;
+
+    let mut x = a.powm(&d, n);
+    if x == Mpz::one() || x == n_1 {
+        return true;
+    }
+    for _ in 1..s {
+        x = x.powm(&Mpz::from(2u64), n);
+        if x == n_1 {
+            return true;
+        }
+    }
+    false
+}
+
+/// Miller-Rabin with rounds random bases in [2, n - 2]
+pub fn miller_rabin<R: Rng>(n: &Mpz, rounds: usize, rng: &mut R) -> bool {
+    let three = Mpz::from(3u64);
+    if *n <= three {
+        return *n >= Mpz::from(2u64);
+    }
+    if !n.tstbit(0) {
+        return false;
+    }
+    let range = n - Mpz::from(3u64);
+    (0..rounds).all(|_| {
+        let a = random_mpz_below(rng, &range) + Mpz::from(2u64);
+        is_strong_probable_prime(n, &a)
+    })
+}
+
+/// The Jacobi symbol (a/n) for odd positive n
+pub fn jacobi(a: &Mpz, n: &Mpz) -> i32 {
+    assert!(n.tstbit(0) && *n > Mpz::zero(), "n has to be odd and positive");
+    let mut a = a.modulus(n);
+    let mut n = n.clone();
+    let mut result = 1;
+    while !a.is_zero() {
+        while !a.tstbit(0) {
+            a >>= 1;
+            // (2/n) is -1 for n = 3, 5 mod 8
+            if n.tstbit(1) != n.tstbit(2) {
+                result = -result;
+            }
+        }
+        // reciprocity flips it when both are 3 mod 4
+        if a.tstbit(1) && n.tstbit(1) {
+            result = -result;
+        }
+        let rem = n.modulus(&a);
+        n = a;
+        a = rem;
+    }
+    if n == Mpz::one() { result } else { 0 }
+}
+
+// x / 2 mod odd n
+fn half_mod(x: &Mpz, n: &Mpz) -> Mpz {
+    let x = x.modulus(n);
+    if x.tstbit(0) { (x + n) >> 1 } else { x >> 1 }
+}
+
+/// Strong Lucas probable prime test with Selfridge's parameters: D is
+/// the first of 5, -7, 9, -11, ... with (D/n) = -1, P = 1 and
+/// Q = (1 - D) / 4. n has to be odd. Perfect squares have no such D, so
+/// they're weeded out first.
+pub fn is_strong_lucas_probable_prime(n: &Mpz) -> bool {
+    let root = n.sqrt();
+    if &root * &root == *n {
+        return false;
+    }
+
+    let mut d_abs = 5u64;
+    let mut negative = false;
+    let d = loop {
+        let d = if negative { -Mpz::from(d_abs) } else { Mpz::from(d_abs) };
+        match jacobi(&d, n) {
+            -1 => break d,
+            // D shares a factor with n, fine if that factor is n itself
+            0 if Mpz::from(d_abs) != *n => return false,
+            _ => {},
+        }
+        d_abs += 2;
+        negative = !negative;
+    };
+    let q = (Mpz::one() - &d) / Mpz::from(4u64);
+
+    // n + 1 = k 2^s, then U_k and V_k by doubling up through k's bits
+    let n_1 = n + Mpz::one();
+    let mut s = 0;
+    while !n_1.tstbit(s) {
+        s += 1;
+    }
+    let k = &n_1 >> s;
+
+    let mut u = Mpz::one();
+    let mut v = Mpz::one();
+    let mut q_k = q.modulus(n);
+    for bit in (0..k.bit_length() - 1).rev() {
+        // U_2j = U_j V_j, V_2j = V_j^2 - 2 Q^j
+        u = (&u * &v).modulus(n);
+        v = (&v * &v - Mpz::from(2u64) * &q_k).modulus(n);
+        q_k = (&q_k * &q_k).modulus(n);
+        if k.tstbit(bit) {
+            // U_j+1 = (P U_j + V_j) / 2, V_j+1 = (D U_j + P V_j) / 2
+            let next_u = half_mod(&(&u + &v), n);
+            v = half_mod(&(&d * &u + &v), n);
+            u = next_u;
+            q_k = (&q_k * &q).modulus(n);
+        }
+    }
+
+    if u.is_zero() || v.is_zero() {
+        return true;
+    }
+    for _ in 1..s {
+        // V_2j again
+        v = (&v * &v - Mpz::from(2u64) * &q_k).modulus(n);
+        q_k = (&q_k * &q_k).modulus(n);
+        if v.is_zero() {
+            return true;
+        }
+    }
+    false
+}
+
+/// Baillie-PSW: trial division, Miller-Rabin base 2, then a strong Lucas
+/// test
+pub fn baillie_psw(n: &Mpz) -> bool {
+    if let Some(answer) = trial_division(n, small_primes()) {
+        return answer;
+    }
+    is_strong_probable_prime(n, &Mpz::from(2u64)) &&
+    is_strong_lucas_probable_prime(n)
+}
+
+pub fn is_probable_prime<R: Rng>(n: &Mpz, test: PrimalityTest,
+                                 rng: &mut R) -> bool {
+    match test {
+        PrimalityTest::MillerRabin { rounds } => {
+            match trial_division(n, small_primes()) {
+                Some(answer) => answer,
+                None => miller_rabin(n, rounds, rng),
+            }
+        },
+        PrimalityTest::BailliePsw => baillie_psw(n),
+    }
+}
+
+// random odd bits long candidates with the top bits set to top, which is
+// top_len bits long, until one passes the test and accept
+fn random_prime_with<R, F>(rng: &mut R, bits: usize, top: u64, top_len: usize,
+                           test: PrimalityTest, accept: F) -> Mpz
+    where R: Rng, F: Fn(&Mpz) -> bool {
+    assert!(top_len >= 1 && top_len < bits && top >> (top_len - 1) == 1,
+            "top bits don't fit");
+    let primes = small_primes();
+    loop {
+        let mut candidate = random_mpz_bits(rng, bits - top_len) |
+                            (Mpz::from(top) << (bits - top_len));
+        candidate.setbit(0);
+        if trial_division(&candidate, primes) == Some(false) ||
+           !accept(&candidate) {
+            continue;
+        }
+        if is_probable_prime(&candidate, test, rng) {
+            return candidate;
+        }
+    }
+}
+
+/// A prime exactly bits long
+pub fn random_prime<R: Rng>(rng: &mut R, bits: usize,
+                            test: PrimalityTest) -> Mpz {
+    random_prime_with(rng, bits, 1, 1, test, |_| true)
+}
+
+/// A prime bits long with the top two bits set, so the product of two of
+/// them is always exactly 2 * bits long. p - 1 is coprime with e so e is
+/// invertible mod phi.
+pub fn random_rsa_prime<R: Rng>(rng: &mut R, bits: usize, e: &Mpz,
+                                test: PrimalityTest) -> Mpz {
+    random_prime_with(rng, bits, 0b11, 2, test, |p| {
+        (p - Mpz::one()).gcd(e) == Mpz::one()
+    })
+}
+
+/// A safe prime p = 2q + 1 with q prime too, exactly bits long. q is
+/// sieved and tested first since it's cheaper to throw away there.
+pub fn random_safe_prime<R: Rng>(rng: &mut R, bits: usize,
+                                 test: PrimalityTest) -> Mpz {
+    assert!(bits >= 3, "no safe primes under 5");
+    let primes = small_primes();
+    loop {
+        let q = random_prime_with(rng, bits - 1, 1, 1, test, |q| {
+            // p = 2q + 1 mustn't have a small factor either
+            let p = (q << 1) + Mpz::one();
+            trial_division(&p, primes) != Some(false)
+        });
+        let p = (&q << 1) + Mpz::one();
+        if is_probable_prime(&p, test, rng) {
+            return p;
+        }
+    }
+}
+
+/// Chinese remainder theorem: from x = a_i mod m_i gives x mod the
+/// product of the m_i, as (x, product). None if the moduli aren't
+/// pairwise coprime.
+pub fn crt(residues: &[(Mpz, Mpz)]) -> Option<(Mpz, Mpz)> {
+    let mut x = Mpz::zero();
+    let mut m = Mpz::one();
+    for (a, m_i) in residues {
+        // x + m t = a mod m_i, so t = (a - x) / m mod m_i
+        let inv = m.invert(m_i)?;
+        let t = ((a - &x) * inv).modulus(m_i);
+        x += &m * t;
+        m *= m_i;
+    }
+    Some((x, m))
+}
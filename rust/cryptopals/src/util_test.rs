@@ -0,0 +1,161 @@
+extern crate gmp;
+
+use self::gmp::mpz::{Mpz, ProbabPrimeResult};
+use util::{jacobi, miller_rabin, baillie_psw, is_strong_probable_prime,
+           is_strong_lucas_probable_prime, is_probable_prime, random_prime,
+           random_rsa_prime, random_safe_prime, random_mpz_below,
+           seeded_rng, crt, PrimalityTest};
+
+fn gmp_is_prime(n: &Mpz) -> bool {
+    n.probab_prime(40) != ProbabPrimeResult::NotPrime
+}
+
+fn jacobi_test() {
+    // for an odd prime it's Euler's criterion
+    for &p in &[3u64, 5, 7, 11, 13, 101, 997] {
+        let p = Mpz::from(p);
+        let exp = (&p - Mpz::one()) >> 1;
+        for a in 0..60u64 {
+            let a = Mpz::from(a);
+            let euler = a.powm(&exp, &p);
+            let expected = if euler.is_zero() {
+                0
+            } else if euler == Mpz::one() {
+                1
+            } else {
+                -1
+            };
+            assert_eq!(jacobi(&a, &p), expected, "({}/{})", a, p);
+        }
+    }
+
+    // composites and negative tops
+    assert_eq!(jacobi(&Mpz::from(1001u64), &Mpz::from(9907u64)), -1);
+    assert_eq!(jacobi(&Mpz::from(19u64), &Mpz::from(45u64)), 1);
+    assert_eq!(jacobi(&Mpz::from(8u64), &Mpz::from(21u64)), -1);
+    assert_eq!(jacobi(&Mpz::from(5u64), &Mpz::from(21u64)), 1);
+    assert_eq!(jacobi(&Mpz::from(6u64), &Mpz::from(15u64)), 0);
+    assert_eq!(jacobi(&-Mpz::from(7u64), &Mpz::from(11u64)), 1);
+}
+
+fn primality_test() {
+    let mut rng = seeded_rng(1);
+
+    // everything small agrees with gmp
+    for n in 0..20000u64 {
+        let n = Mpz::from(n);
+        let expected = gmp_is_prime(&n);
+        assert_eq!(baillie_psw(&n), expected, "baillie-psw on {}", n);
+        assert_eq!(miller_rabin(&n, 20, &mut rng), expected,
+                   "miller-rabin on {}", n);
+    }
+
+    // carmichael numbers fool fermat but not miller-rabin
+    for &n in &[561u64, 1105, 1729, 41041, 825265, 321197185] {
+        let n = Mpz::from(n);
+        assert!(!miller_rabin(&n, 20, &mut rng), "{}", n);
+        assert!(!baillie_psw(&n), "{}", n);
+    }
+
+    // strong pseudoprimes to base 2 that the lucas half has to catch
+    for &n in &[2047u64, 3277, 4033, 4681, 8321, 3215031751] {
+        let n = Mpz::from(n);
+        assert!(is_strong_probable_prime(&n, &Mpz::from(2u64)), "{}", n);
+        assert!(!baillie_psw(&n), "{}", n);
+    }
+
+    // and strong lucas pseudoprimes that the base 2 half catches
+    for &n in &[5459u64, 5777, 10877, 16109, 18971] {
+        let n = Mpz::from(n);
+        assert!(is_strong_lucas_probable_prime(&n), "{}", n);
+        assert!(!baillie_psw(&n), "{}", n);
+    }
+
+    // mersenne primes and not primes, F7 and a square of a prime
+    let m127 = (Mpz::one() << 127) - Mpz::one();
+    let m521 = (Mpz::one() << 521) - Mpz::one();
+    let m523 = (Mpz::one() << 523) - Mpz::one();
+    let f7 = (Mpz::one() << 128) + Mpz::one();
+    for &(ref n, expected) in &[(m127.clone(), true), (m521, true),
+                                (m523, false), (f7, false),
+                                (&m127 * &m127, false)] {
+        assert_eq!(baillie_psw(n), expected);
+        for &test in &[PrimalityTest::BailliePsw,
+                       PrimalityTest::MillerRabin { rounds: 20 }] {
+            assert_eq!(is_probable_prime(n, test, &mut rng), expected);
+        }
+    }
+
+    println!("Finished primality tests");
+}
+
+fn prime_generation_test() {
+    let tests = [PrimalityTest::BailliePsw,
+                 PrimalityTest::MillerRabin { rounds: 20 }];
+
+    for &test in &tests {
+        for &bits in &[2, 3, 8, 17, 64, 256, 512] {
+            let p = random_prime(&mut seeded_rng(bits as u32), bits, test);
+            assert_eq!(p.bit_length(), bits);
+            assert!(gmp_is_prime(&p), "{}", p);
+        }
+    }
+
+    // same seed same prime, different seed different prime
+    let a = random_prime(&mut seeded_rng(7), 256, PrimalityTest::BailliePsw);
+    let b = random_prime(&mut seeded_rng(7), 256, PrimalityTest::BailliePsw);
+    let c = random_prime(&mut seeded_rng(8), 256, PrimalityTest::BailliePsw);
+    assert_eq!(a, b);
+    assert!(a != c);
+
+    let mut rng = seeded_rng(2);
+    let e = Mpz::from(3u64);
+    for &bits in &[16, 128, 512] {
+        for _ in 0..4 {
+            let p = random_rsa_prime(&mut rng, bits, &e, tests[0]);
+            let q = random_rsa_prime(&mut rng, bits, &e, tests[1]);
+            assert!(p.tstbit(bits - 1) && p.tstbit(bits - 2));
+            assert_eq!((&p * &q).bit_length(), 2 * bits);
+            assert_eq!((&p - Mpz::one()).gcd(&e), Mpz::one());
+            assert_eq!((&q - Mpz::one()).gcd(&e), Mpz::one());
+            assert!(gmp_is_prime(&p) && gmp_is_prime(&q));
+        }
+    }
+
+    for &test in &tests {
+        for &bits in &[3, 10, 64, 256] {
+            let p = random_safe_prime(&mut rng, bits, test);
+            let q = (&p - Mpz::one()) >> 1;
+            assert_eq!(p.bit_length(), bits);
+            assert!(gmp_is_prime(&p) && gmp_is_prime(&q), "{}", p);
+        }
+    }
+
+    // everything under 10 should come up
+    let ten = Mpz::from(10u64);
+    let mut seen = [false; 10];
+    for _ in 0..1000 {
+        let n = random_mpz_below(&mut rng, &ten);
+        seen[Option::<u64>::from(&n).unwrap() as usize] = true;
+    }
+    assert!(seen.iter().all(|&s| s));
+
+    println!("Finished prime generation tests");
+}
+
+fn crt_test() {
+    let residues = [(Mpz::from(2u64), Mpz::from(3u64)),
+                    (Mpz::from(3u64), Mpz::from(5u64)),
+                    (Mpz::from(2u64), Mpz::from(7u64))];
+    assert_eq!(crt(&residues), Some((Mpz::from(23u64), Mpz::from(105u64))));
+    assert_eq!(crt(&[]), Some((Mpz::zero(), Mpz::one())));
+    assert_eq!(crt(&[(Mpz::one(), Mpz::from(4u64)),
+                     (Mpz::one(), Mpz::from(6u64))]), None);
+}
+
+pub fn util_test() {
+    jacobi_test();
+    primality_test();
+    prime_generation_test();
+    crt_test();
+}
@@ -0,0 +1,476 @@
+extern crate gmp;
+
+pub mod test;
+
+use std::fmt;
+use std::str;
+
+use self::gmp::mpz::Mpz;
+use asn1::DigestAlgorithm;
+use asn1::der::DerReader;
+use asn1::value::{Oid, Time, Value};
+use error::{Error, Result};
+use pem::{pem_encode, pem_decode};
+use rsa::PublicKey;
+
+pub const CERTIFICATE_PEM_LABEL: &str = "CERTIFICATE";
+
+// 1.2.840.113549.1.1.{5,11,12,13}, the PKCS#1 v1.5 signature algorithms
+const SIGNATURE_ALGORITHMS: [([u64; 7], DigestAlgorithm); 4] = [
+    ([1, 2, 840, 113549, 1, 1, 5], DigestAlgorithm::Sha1),
+    ([1, 2, 840, 113549, 1, 1, 11], DigestAlgorithm::Sha256),
+    ([1, 2, 840, 113549, 1, 1, 12], DigestAlgorithm::Sha384),
+    ([1, 2, 840, 113549, 1, 1, 13], DigestAlgorithm::Sha512),
+];
+
+// 2.5.4.x, the attribute types openssl has short names for
+const NAME_ATTRIBUTES: [(u64, &str); 7] = [
+    (3, "CN"), (5, "serialNumber"), (6, "C"), (7, "L"), (8, "ST"),
+    (10, "O"), (11, "OU"),
+];
+
+// 2.5.29.x
+const KEY_USAGE: [u64; 4] = [2, 5, 29, 15];
+const SUBJECT_ALT_NAME: [u64; 4] = [2, 5, 29, 17];
+const BASIC_CONSTRAINTS: [u64; 4] = [2, 5, 29, 19];
+
+// KeyUsage bits, bit n of the BIT STRING is 1 << n
+pub const KEY_USAGE_DIGITAL_SIGNATURE: u16 = 1 << 0;
+pub const KEY_USAGE_NON_REPUDIATION: u16 = 1 << 1;
+pub const KEY_USAGE_KEY_ENCIPHERMENT: u16 = 1 << 2;
+pub const KEY_USAGE_DATA_ENCIPHERMENT: u16 = 1 << 3;
+pub const KEY_USAGE_KEY_AGREEMENT: u16 = 1 << 4;
+pub const KEY_USAGE_KEY_CERT_SIGN: u16 = 1 << 5;
+pub const KEY_USAGE_CRL_SIGN: u16 = 1 << 6;
+pub const KEY_USAGE_ENCIPHER_ONLY: u16 = 1 << 7;
+pub const KEY_USAGE_DECIPHER_ONLY: u16 = 1 << 8;
+
+/// A distinguished name, flattened to its attributes in order. Multi
+/// valued RDNs just become consecutive attributes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Name {
+    attributes: Vec<(Oid, String)>,
+}
+
+impl Name {
+    pub fn attributes(&self) -> &[(Oid, String)] {
+        &self.attributes
+    }
+
+    /// The first value of this attribute type
+    pub fn get(&self, oid: &Oid) -> Option<&str> {
+        self.attributes.iter().find(|(o, _)| o == oid)
+                              .map(|(_, v)| v.as_str())
+    }
+
+    pub fn common_name(&self) -> Option<&str> {
+        self.attributes.iter()
+                       .find(|(o, _)| o.arcs() == [2, 5, 4, 3])
+                       .map(|(_, v)| v.as_str())
+    }
+
+    // Name ::= SEQUENCE OF SET OF SEQUENCE { type, value }
+    fn from_value(value: &Value) -> Result<Name> {
+        let mut attributes = Vec::new();
+        let rdns = value.as_values()
+                        .ok_or(Error::InvalidDer("name not a sequence"))?;
+        for rdn in rdns {
+            let atvs = match *rdn {
+                Value::Set(ref atvs) if !atvs.is_empty() => atvs,
+                _ => return Err(Error::InvalidDer("bad relative name")),
+            };
+            for atv in atvs {
+                match atv.as_values() {
+                    Some(&[Value::Oid(ref oid), ref v]) => {
+                        // no BMPString or TeletexString
+                        let s = v.as_str().ok_or(Error::InvalidDer(
+                            "unsupported name string type"))?;
+                        attributes.push((oid.clone(), s.to_string()));
+                    },
+                    _ => return Err(Error::InvalidDer("bad name attribute")),
+                }
+            }
+        }
+        Ok(Name { attributes })
+    }
+}
+
+/// Like openssl prints them, e.g. "C=US, O=Example, CN=example.com"
+impl fmt::Display for Name {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut parts = Vec::new();
+        for (oid, v) in &self.attributes {
+            let short = match oid.arcs() {
+                [2, 5, 4, n] => NAME_ATTRIBUTES.iter()
+                                               .find(|&&(m, _)| m == *n)
+                                               .map(|&(_, s)| s.to_string()),
+                _ => None,
+            };
+            parts.push(format!("{}={}",
+                               short.unwrap_or_else(|| oid.to_string()), v));
+        }
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BasicConstraints {
+    pub ca: bool,
+    // how many intermediate CAs are allowed below this one
+    pub path_len: Option<u32>,
+}
+
+/// An extension as it was in the certificate. The ones we understand are
+/// also parsed into fields on Certificate.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Extension {
+    pub oid: Oid,
+    pub critical: bool,
+    pub value: Vec<u8>,
+}
+
+impl Extension {
+    fn is_known(&self) -> bool {
+        let arcs = self.oid.arcs();
+        arcs == KEY_USAGE || arcs == SUBJECT_ALT_NAME ||
+        arcs == BASIC_CONSTRAINTS
+    }
+}
+
+/// An X.509 certificate signed with RSA PKCS#1 v1.5 and holding an RSA
+/// key, which is all we can check anyway
+#[derive(Debug, Clone, PartialEq)]
+pub struct Certificate {
+    // 1 or 3, not the 0 or 2 that's encoded
+    pub version: u8,
+    pub serial: Mpz,
+    pub signature_algorithm: DigestAlgorithm,
+    pub issuer: Name,
+    pub subject: Name,
+    pub not_before: Time,
+    pub not_after: Time,
+    pub public_key: PublicKey,
+    pub extensions: Vec<Extension>,
+    pub basic_constraints: Option<BasicConstraints>,
+    pub key_usage: Option<u16>,
+    // just the dNSNames
+    pub subject_alt_names: Vec<String>,
+    der: Vec<u8>,
+    tbs: Vec<u8>,
+    signature: Vec<u8>,
+}
+
+fn next_field<'a, I>(fields: &mut I, what: &'static str) -> Result<&'a Value>
+    where I: Iterator<Item = &'a Value> {
+    fields.next().ok_or(Error::InvalidDer(what))
+}
+
+// AlgorithmIdentifier ::= SEQUENCE { oid, parameters (NULL for these) }
+fn signature_algorithm(oid: &Oid) -> Result<DigestAlgorithm> {
+    SIGNATURE_ALGORITHMS.iter().find(|(arcs, _)| oid.arcs() == arcs)
+                        .map(|&(_, alg)| alg)
+                        .ok_or(Error::NotImplemented("signature algorithm"))
+}
+
+fn read_algorithm_identifier(value: &Value) -> Result<DigestAlgorithm> {
+    match value.as_values() {
+        Some(&[Value::Oid(ref oid)]) |
+        Some(&[Value::Oid(ref oid), Value::Null]) => signature_algorithm(oid),
+        _ => Err(Error::InvalidDer("bad algorithm identifier")),
+    }
+}
+
+fn parse_basic_constraints(der: &[u8]) -> Result<BasicConstraints> {
+    let value = Value::from_der(der)?;
+    let fields = value.as_values()
+                      .ok_or(Error::InvalidDer("bad basic constraints"))?;
+    // cA BOOLEAN DEFAULT FALSE, pathLenConstraint INTEGER OPTIONAL
+    let (ca, path_len) = match fields {
+        [] => (false, None),
+        [Value::Boolean(ca)] => (*ca, None),
+        [Value::Integer(ref n)] => (false, Some(n)),
+        [Value::Boolean(ca), Value::Integer(ref n)] => (*ca, Some(n)),
+        _ => return Err(Error::InvalidDer("bad basic constraints")),
+    };
+    let path_len = match path_len {
+        Some(n) => match n.to_str_radix(10).parse::<u32>() {
+            Ok(n) => Some(n),
+            Err(_) => return Err(Error::InvalidDer("bad path length")),
+        },
+        None => None,
+    };
+    Ok(BasicConstraints { ca, path_len })
+}
+
+fn parse_key_usage(der: &[u8]) -> Result<u16> {
+    let bytes = match Value::from_der(der)? {
+        Value::BitString { ref bytes, .. } if bytes.len() <= 2 => {
+            bytes.clone()
+        },
+        _ => return Err(Error::InvalidDer("bad key usage")),
+    };
+    // bit 0 is the top bit of the first byte
+    let mut usage = 0u16;
+    for (i, &b) in bytes.iter().enumerate() {
+        usage |= (b.reverse_bits() as u16) << (8 * i);
+    }
+    Ok(usage)
+}
+
+fn parse_subject_alt_names(der: &[u8]) -> Result<Vec<String>> {
+    let value = Value::from_der(der)?;
+    let names = value.as_values().ok_or(Error::InvalidDer("bad alt names"))?;
+    let mut dns_names = Vec::new();
+    for name in names {
+        // dNSName is [2] IMPLICIT IA5String, skip the other kinds
+        if let Value::ContextSpecific { number: 2, constructed: false,
+                                        ref contents } = *name {
+            match str::from_utf8(contents) {
+                Ok(s) if s.is_ascii() => dns_names.push(s.to_string()),
+                _ => return Err(Error::InvalidDer("bad dns name")),
+            }
+        }
+    }
+    Ok(dns_names)
+}
+
+// Extensions ::= SEQUENCE OF SEQUENCE { oid, critical DEFAULT FALSE, value }
+fn parse_extensions(contents: &[u8]) -> Result<Vec<Extension>> {
+    let value = Value::from_der(contents)?;
+    let values = value.as_values()
+                      .ok_or(Error::InvalidDer("bad extensions"))?;
+    let mut extensions: Vec<Extension> = Vec::new();
+    for ext in values {
+        let (oid, critical, value) = match ext.as_values() {
+            Some(&[Value::Oid(ref oid), Value::OctetString(ref value)]) => {
+                (oid, false, value)
+            },
+            Some(&[Value::Oid(ref oid), Value::Boolean(true),
+                   Value::OctetString(ref value)]) => (oid, true, value),
+            _ => return Err(Error::InvalidDer("bad extension")),
+        };
+        if extensions.iter().any(|e| e.oid == *oid) {
+            return Err(Error::InvalidDer("duplicate extension"));
+        }
+        extensions.push(Extension {
+            oid: oid.clone(),
+            critical,
+            value: value.clone(),
+        });
+    }
+    Ok(extensions)
+}
+
+impl Certificate {
+    /// Certificate ::= SEQUENCE { tbsCertificate, signatureAlgorithm,
+    /// signatureValue BIT STRING }
+    pub fn from_der(der: &[u8]) -> Result<Certificate> {
+        let mut outer = DerReader::new(der);
+        let mut cert = outer.read_sequence()?;
+        outer.finish()?;
+
+        // the signature is over the bytes as they are, so don't re-encode
+        let tbs = cert.read_raw()?;
+        let outer_alg = read_algorithm_identifier(
+            &Value::from_der(cert.read_raw()?)?)?;
+        let signature = cert.read_bit_string()?;
+        cert.finish()?;
+
+        let tbs_value = Value::from_der(tbs)?;
+        let fields = tbs_value.as_values()
+                              .ok_or(Error::InvalidDer("bad tbs"))?;
+        let mut fields = fields.iter().peekable();
+
+        // [0] EXPLICIT Version DEFAULT v1
+        let mut version = 1;
+        if let Some(&&Value::ContextSpecific { number: 0, constructed: true,
+                                               ref contents }) = fields.peek() {
+            version = match Value::from_der(contents)? {
+                Value::Integer(ref n) if *n == Mpz::one() => 2,
+                Value::Integer(ref n) if *n == Mpz::from(2u64) => 3,
+                _ => return Err(Error::InvalidDer("bad version")),
+            };
+            fields.next();
+        }
+
+        let serial = next_field(&mut fields, "no serial")?.as_integer()
+                         .ok_or(Error::InvalidDer("bad serial"))?.clone();
+        let signature_algorithm = read_algorithm_identifier(
+            next_field(&mut fields, "no signature algorithm")?)?;
+        if signature_algorithm != outer_alg {
+            return Err(Error::InvalidDer("signature algorithms differ"));
+        }
+        let issuer = Name::from_value(next_field(&mut fields, "no issuer")?)?;
+
+        let (not_before, not_after) =
+            match next_field(&mut fields, "no validity")?.as_values() {
+                Some([a, b]) => match (a.as_time(), b.as_time()) {
+                    (Some(a), Some(b)) => (a, b),
+                    _ => return Err(Error::InvalidDer("bad validity")),
+                },
+                _ => return Err(Error::InvalidDer("bad validity")),
+            };
+
+        let subject = Name::from_value(next_field(&mut fields,
+                                                  "no subject")?)?;
+        let public_key = PublicKey::from_spki_der(
+            &next_field(&mut fields, "no public key")?.to_der()?)?;
+
+        // [1] and [2] unique ids, which nobody uses, then [3] extensions
+        let mut extensions = Vec::new();
+        let mut last = 0;
+        for field in fields {
+            let number = match *field {
+                Value::ContextSpecific { number, .. } if number > last &&
+                                                         number <= 3 &&
+                                                         version > 1 => number,
+                _ => return Err(Error::InvalidDer("unexpected tbs field")),
+            };
+            last = number;
+            if number == 3 {
+                match *field {
+                    Value::ContextSpecific { constructed: true, ref contents,
+                                             .. } if version == 3 => {
+                        extensions = parse_extensions(contents)?;
+                    },
+                    _ => return Err(Error::InvalidDer("bad extensions")),
+                }
+            }
+        }
+
+        let mut basic_constraints = None;
+        let mut key_usage = None;
+        let mut subject_alt_names = Vec::new();
+        for ext in &extensions {
+            let arcs = ext.oid.arcs();
+            if arcs == BASIC_CONSTRAINTS {
+                basic_constraints = Some(parse_basic_constraints(&ext.value)?);
+            } else if arcs == KEY_USAGE {
+                key_usage = Some(parse_key_usage(&ext.value)?);
+            } else if arcs == SUBJECT_ALT_NAME {
+                subject_alt_names = parse_subject_alt_names(&ext.value)?;
+            }
+        }
+
+        Ok(Certificate {
+            version,
+            serial,
+            signature_algorithm,
+            issuer,
+            subject,
+            not_before,
+            not_after,
+            public_key,
+            extensions,
+            basic_constraints,
+            key_usage,
+            subject_alt_names,
+            der: der.to_vec(),
+            tbs: tbs.to_vec(),
+            signature: signature.to_vec(),
+        })
+    }
+
+    pub fn from_pem(pem: &str) -> Result<Certificate> {
+        Certificate::from_der(&pem_decode(CERTIFICATE_PEM_LABEL, pem)?)
+    }
+
+    pub fn to_der(&self) -> &[u8] {
+        &self.der
+    }
+
+    pub fn to_pem(&self) -> String {
+        pem_encode(CERTIFICATE_PEM_LABEL, &self.der)
+    }
+
+    /// Whether the issuer signed this, given the issuer's key
+    pub fn verify_signature(&self, issuer_key: &PublicKey) -> Result<()> {
+        issuer_key.pkcs1v15_verify(self.signature_algorithm, &self.tbs,
+                                   &self.signature)
+    }
+
+    pub fn is_valid_at(&self, time: &Time) -> bool {
+        self.not_before <= *time && *time <= self.not_after
+    }
+
+    pub fn is_ca(&self) -> bool {
+        match self.basic_constraints {
+            Some(BasicConstraints { ca, .. }) => ca,
+            None => false,
+        }
+    }
+
+    // the checks that don't depend on where it is in the chain
+    fn check(&self, time: &Time) -> Result<()> {
+        if !self.is_valid_at(time) {
+            return Err(Error::InvalidCertificate(format!(
+                "{} is only valid from {} to {}", self.subject,
+                self.not_before, self.not_after)));
+        }
+        // anything critical we don't understand means we can't use it
+        if let Some(ext) = self.extensions.iter()
+                                          .find(|e| e.critical &&
+                                                    !e.is_known()) {
+            return Err(Error::InvalidCertificate(format!(
+                "{} has unknown critical extension {}", self.subject,
+                ext.oid)));
+        }
+        Ok(())
+    }
+
+    // can sign certificates with cas_below intermediate CAs between it and
+    // the leaf
+    fn check_issuer(&self, cas_below: usize) -> Result<()> {
+        let path_len = match self.basic_constraints {
+            Some(BasicConstraints { ca: true, path_len }) => path_len,
+            _ => return Err(Error::InvalidCertificate(
+                format!("{} is not a CA", self.subject))),
+        };
+        if let Some(usage) = self.key_usage {
+            if usage & KEY_USAGE_KEY_CERT_SIGN == 0 {
+                return Err(Error::InvalidCertificate(format!(
+                    "{} can't sign certificates", self.subject)));
+            }
+        }
+        if let Some(max) = path_len {
+            if cas_below > max as usize {
+                return Err(Error::InvalidCertificate(format!(
+                    "{} allows {} CAs below it, chain has {}", self.subject,
+                    max, cas_below)));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Checks chain, leaf first, leads up to the trusted root: every cert is
+/// valid at time and signed by the next one along, and everything doing
+/// the signing is a CA allowed to. The chain can end with the root or
+/// leave it off. The root's own signature isn't checked since it's
+/// trusted anyway.
+pub fn verify_chain(chain: &[Certificate], root: &Certificate,
+                    time: &Time) -> Result<()> {
+    let chain = match chain.split_last() {
+        Some((last, rest)) if last.der == root.der => rest,
+        _ => chain,
+    };
+    if chain.is_empty() {
+        return Err(Error::InvalidCertificate("empty chain".to_string()));
+    }
+
+    root.check(time)?;
+    for (i, cert) in chain.iter().enumerate() {
+        cert.check(time)?;
+
+        let issuer = chain.get(i + 1).unwrap_or(root);
+        // names are compared exactly, not with the RFC 5280 folding
+        if cert.issuer != issuer.subject {
+            return Err(Error::InvalidCertificate(format!(
+                "{} was issued by {}, not {}", cert.subject, cert.issuer,
+                issuer.subject)));
+        }
+        issuer.check_issuer(i)?;
+        cert.verify_signature(&issuer.public_key)?;
+    }
+    Ok(())
+}
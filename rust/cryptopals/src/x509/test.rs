@@ -0,0 +1,203 @@
+extern crate gmp;
+
+use std::slice;
+
+use self::gmp::mpz::Mpz;
+use asn1::DigestAlgorithm;
+use asn1::value::{Oid, Time};
+use error::Error;
+use util::read_fixture;
+use x509::{Certificate, BasicConstraints, verify_chain,
+           KEY_USAGE_DIGITAL_SIGNATURE, KEY_USAGE_KEY_ENCIPHERMENT,
+           KEY_USAGE_KEY_CERT_SIGN, KEY_USAGE_CRL_SIGN};
+
+fn load(filename: &str) -> Certificate {
+    match Certificate::from_pem(&read_fixture(filename)) {
+        Ok(cert) => cert,
+        Err(e) => panic!("{}: {}", filename, e),
+    }
+}
+
+// made with openssl req and openssl x509 -req: a root, an intermediate
+// with pathlen:0 signed with sha384, and a 1024 bit leaf under that.
+// other_root has the same name as root but a different key, rogue is
+// signed by the leaf, and sub_intermediate is a second CA under the
+// intermediate with sub_leaf under it.
+fn root() -> Certificate {
+    load("data/x509_root.pem")
+}
+
+fn intermediate() -> Certificate {
+    load("data/x509_intermediate.pem")
+}
+
+fn leaf() -> Certificate {
+    load("data/x509_leaf.pem")
+}
+
+fn at(year: u16) -> Time {
+    Time::new(year, 1, 1, 0, 0, 0).unwrap()
+}
+
+fn expect_invalid(result: ::error::Result<()>, contains: &str) {
+    match result {
+        Err(Error::InvalidCertificate(ref s)) if s.contains(contains) => {},
+        other => panic!("expected {:?}, got {:?}", contains, other),
+    }
+}
+
+fn parse_test() {
+    let leaf = leaf();
+    assert_eq!(leaf.version, 3);
+    assert_eq!(leaf.serial, Mpz::from(0x3003u64));
+    assert_eq!(leaf.signature_algorithm, DigestAlgorithm::Sha256);
+    assert_eq!(leaf.subject.to_string(),
+               "C=US, O=Cryptopals Test, CN=beep.example.com");
+    assert_eq!(leaf.issuer.to_string(),
+               "C=US, O=Cryptopals Test, CN=Cryptopals Test Intermediate CA");
+    assert_eq!(leaf.subject.common_name(), Some("beep.example.com"));
+    let org: Oid = "2.5.4.10".parse().unwrap();
+    assert_eq!(leaf.subject.get(&org), Some("Cryptopals Test"));
+    assert_eq!(leaf.not_before, Time::new(2024, 6, 1, 0, 0, 0).unwrap());
+    assert_eq!(leaf.not_after, Time::new(2034, 6, 1, 0, 0, 0).unwrap());
+
+    // from openssl x509 -modulus
+    let n = Mpz::from_str_radix(concat!(
+        "C8B160A91E8058611A9B5092738F5007BBBE7D7C6C4AAA61503722B9FF24DC27",
+        "23A1EB6A0C5113923792BA0E8B4BF023683E58E6A15AFD64FF62D0BF9DA41A73",
+        "3BBA8F37BD54D705739BB0B30147F713DB645E457ED67F09BA4B0B9463D27738",
+        "12AF009858D31C98545BB891329FCE29A46FDADE8BD8AEE52E5D132C023EB837"),
+        16).unwrap();
+    assert_eq!(leaf.public_key.n, n);
+    assert_eq!(leaf.public_key.e, Mpz::from(65537u64));
+
+    // basicConstraints, keyUsage, extendedKeyUsage, subjectAltName and
+    // the two key identifiers
+    assert_eq!(leaf.extensions.len(), 6);
+    assert_eq!(leaf.basic_constraints,
+               Some(BasicConstraints { ca: false, path_len: None }));
+    assert_eq!(leaf.key_usage,
+               Some(KEY_USAGE_DIGITAL_SIGNATURE | KEY_USAGE_KEY_ENCIPHERMENT));
+    assert_eq!(leaf.subject_alt_names,
+               vec!("beep.example.com".to_string(),
+                    "boop.example.com".to_string()));
+    assert!(!leaf.is_ca());
+
+    let intermediate = intermediate();
+    assert_eq!(intermediate.serial, Mpz::from(0x2002u64));
+    assert_eq!(intermediate.signature_algorithm, DigestAlgorithm::Sha384);
+    assert_eq!(intermediate.basic_constraints,
+               Some(BasicConstraints { ca: true, path_len: Some(0) }));
+    assert_eq!(intermediate.key_usage,
+               Some(KEY_USAGE_KEY_CERT_SIGN | KEY_USAGE_CRL_SIGN));
+    assert!(intermediate.subject_alt_names.is_empty());
+
+    let root = root();
+    assert_eq!(root.subject, root.issuer);
+    assert_eq!(root.basic_constraints,
+               Some(BasicConstraints { ca: true, path_len: None }));
+    assert_eq!(root.not_after, at(2049));
+
+    let sub_leaf = load("data/x509_sub_leaf.pem");
+    assert_eq!(sub_leaf.signature_algorithm, DigestAlgorithm::Sha512);
+
+    // the der is kept as is, so it comes back out the same
+    let pem = read_fixture("data/x509_leaf.pem");
+    assert_eq!(leaf.to_pem(), pem);
+
+    println!("Finished x509 parse tests");
+}
+
+fn signature_test() {
+    let (root, intermediate, leaf) = (root(), intermediate(), leaf());
+    root.verify_signature(&root.public_key).unwrap();
+    intermediate.verify_signature(&root.public_key).unwrap();
+    leaf.verify_signature(&intermediate.public_key).unwrap();
+    assert_eq!(leaf.verify_signature(&root.public_key),
+               Err(Error::InvalidSignature));
+
+    // flip a bit in the subject's CN, which is still valid DER
+    let mut der = leaf.to_der().to_vec();
+    let pos = der.windows(4).position(|w| w == b"beep").unwrap();
+    der[pos] ^= 0x01;
+    let tampered = Certificate::from_der(&der).unwrap();
+    assert_eq!(tampered.subject.common_name(), Some("ceep.example.com"));
+    assert_eq!(tampered.verify_signature(&intermediate.public_key),
+               Err(Error::InvalidSignature));
+
+    println!("Finished x509 signature tests");
+}
+
+fn chain_test() {
+    let (root, intermediate, leaf) = (root(), intermediate(), leaf());
+    let chain = vec!(leaf.clone(), intermediate.clone());
+    verify_chain(&chain, &root, &at(2030)).unwrap();
+
+    // with the root on the end, and just the intermediate on its own
+    let with_root = vec!(leaf.clone(), intermediate.clone(), root.clone());
+    verify_chain(&with_root, &root, &at(2030)).unwrap();
+    verify_chain(slice::from_ref(&intermediate), &root, &at(2030)).unwrap();
+
+    // the leaf isn't valid yet in 2024-01 and has expired by 2035, the
+    // intermediate by 2045
+    expect_invalid(verify_chain(&chain, &root, &at(2024)), "valid from");
+    expect_invalid(verify_chain(&chain, &root, &at(2035)), "valid from");
+    expect_invalid(verify_chain(slice::from_ref(&intermediate), &root,
+                                &at(2045)),
+                   "valid from");
+
+    // same name, wrong key
+    let other_root = load("data/x509_other_root.pem");
+    assert_eq!(other_root.subject, root.subject);
+    assert_eq!(verify_chain(&chain, &other_root, &at(2030)),
+               Err(Error::InvalidSignature));
+
+    expect_invalid(verify_chain(slice::from_ref(&leaf), &root, &at(2030)),
+                   "was issued by");
+    expect_invalid(verify_chain(&[], &root, &at(2030)), "empty");
+    expect_invalid(verify_chain(slice::from_ref(&root), &root, &at(2030)),
+                   "empty");
+
+    // the leaf isn't a CA so can't vouch for anything
+    let rogue = load("data/x509_rogue.pem");
+    rogue.verify_signature(&leaf.public_key).unwrap();
+    let rogue_chain = vec!(rogue, leaf.clone(), intermediate.clone());
+    expect_invalid(verify_chain(&rogue_chain, &root, &at(2030)),
+                   "is not a CA");
+
+    // the intermediate has pathlen:0 so there can't be another CA under it
+    let sub_intermediate = load("data/x509_sub_intermediate.pem");
+    let sub_leaf = load("data/x509_sub_leaf.pem");
+    verify_chain(slice::from_ref(&sub_leaf), &sub_intermediate,
+                 &at(2030)).unwrap();
+    expect_invalid(verify_chain(&[sub_leaf, sub_intermediate, intermediate],
+                                &root, &at(2030)),
+                   "allows 0 CAs below it");
+
+    println!("Finished x509 chain tests");
+}
+
+fn garbage_test() {
+    let der = leaf().to_der().to_vec();
+    assert!(Certificate::from_der(&der[..der.len() - 1]).is_err());
+    let mut trailing = der.clone();
+    trailing.push(0);
+    assert!(Certificate::from_der(&trailing).is_err());
+    assert!(Certificate::from_der(&[]).is_err());
+    assert!(Certificate::from_pem(&read_fixture("data/rsa1024_pub.pem"))
+                .is_err());
+
+    // every truncation has to fail cleanly rather than panic
+    for len in 0..der.len() {
+        assert!(Certificate::from_der(&der[..len]).is_err());
+    }
+
+    println!("Finished x509 garbage tests");
+}
+
+pub fn x509_test() {
+    parse_test();
+    signature_test();
+    chain_test();
+    garbage_test();
+}
@@ -62,7 +62,7 @@ fn detect_byte_xor_cipher(filename: &str) -> (Vec<u8>, usize) {
             Err(e) => { panic!("{}", e); }
         };
 
-        let line_bytes = hex_to_bytes(&l);
+        let line_bytes = hex_to_bytes(&l).unwrap();
         let (key, score) = guess_byte_xor_cipher(&line_bytes);
         if score >= best_score {
             // XXX: same score?
@@ -94,8 +94,8 @@ pub fn repeating_key_xor(s: &[u8], key: &[u8]) -> Vec<u8> {
 }
 
 fn fixed_xor_test() {
-    let buf = hex_to_bytes("1c0111001f010100061a024b53535009181c");
-    let key = hex_to_bytes("686974207468652062756c6c277320657965");
+    let buf = hex_to_bytes("1c0111001f010100061a024b53535009181c").unwrap();
+    let key = hex_to_bytes("686974207468652062756c6c277320657965").unwrap();
     let answer = "746865206b696420646f6e277420706c6179";
 
     let result = fixed_xor(buf.as_slice(), key.as_slice());
@@ -111,7 +111,7 @@ fn fixed_xor_test() {
 }
 
 fn byte_xor_cipher_test(ciphertext: &str, plaintext: &str) {
-    let cipher_bytes = hex_to_bytes(ciphertext);
+    let cipher_bytes = hex_to_bytes(ciphertext).unwrap();
     let (key, _) = guess_byte_xor_cipher(&cipher_bytes);
 
     let decrypted_bytes = repeating_key_xor(&cipher_bytes, &[key; 1]);